@@ -0,0 +1,828 @@
+use crate::config::{self, StorageLocation};
+use crate::migrations;
+use crate::models::backups::{
+    BackupImage, BackupInfo, BackupManifest, BackupSettings, BackupVerification,
+};
+use crate::repository::{self, attachments};
+use crate::settings;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, DatabaseName, OpenFlags, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const IMAGES_DIR_NAME: &str = "images";
+const PARTIAL_SUFFIX: &str = ".partial";
+
+// The scheduler waits a little after startup, then checks periodically
+// whether the newest backup is older than the configured interval.
+const SCHEDULER_STARTUP_DELAY: Duration = Duration::from_secs(60);
+const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/** Resolves the folder backups of `location` are written to. */
+pub fn resolve_backups_dir(location: &StorageLocation, settings: &BackupSettings) -> PathBuf {
+    if let Some(folder) = settings
+        .folder
+        .as_deref()
+        .map(str::trim)
+        .filter(|folder| !folder.is_empty())
+    {
+        return PathBuf::from(folder);
+    }
+
+    location.backups_dir()
+}
+
+/** Converts days since the Unix epoch to a `(year, month, day)` date. */
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/** Converts a `(year, month, day)` date to days since the Unix epoch. */
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/** Formats seconds since the Unix epoch as a UTC `YYYYMMDD-HHMMSS` backup ID. */
+fn format_id(seconds: i64) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/** Returns the current UTC time in the `YYYYMMDD-HHMMSS` form backup IDs use. */
+pub fn timestamp_id() -> String {
+    format_id(now_seconds())
+}
+
+/** Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp. */
+fn format_rfc3339(seconds: i64) -> String {
+    let id = format_id(seconds);
+    format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &id[0..4],
+        &id[4..6],
+        &id[6..8],
+        &id[9..11],
+        &id[11..13],
+        &id[13..15]
+    )
+}
+
+/** Parses a `YYYYMMDD-HHMMSS` backup ID into seconds since the Unix epoch. */
+fn parse_timestamp(id: &str) -> Option<i64> {
+    let digits = id.get(..15)?;
+    if digits.as_bytes()[8] != b'-' {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| digits.get(range)?.parse::<u32>().ok();
+
+    let days = days_from_civil(i64::from(field(0..4)?), field(4..6)?, field(6..8)?);
+    let time = field(9..11)? * 3600 + field(11..13)? * 60 + field(13..15)?;
+
+    Some(days * 86_400 + i64::from(time))
+}
+
+/** Returns the timestamp of a backup taken for the rotation.
+ *
+ * These are named `YYYYMMDD-HHMMSS`, optionally followed by `-N` when
+ * several were taken in the same second. Labeled backups return `None`.
+ */
+fn rotation_timestamp(id: &str) -> Option<i64> {
+    let suffix = id.get(15..)?;
+    let numbered = suffix
+        .strip_prefix('-')
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    if !suffix.is_empty() && !numbered {
+        return None;
+    }
+
+    parse_timestamp(id)
+}
+
+/** Rejects IDs that could point outside the backups folder. */
+fn backup_path(backups_dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(format!("Invalid backup ID: {}", id));
+    }
+
+    let path = backups_dir.join(id);
+    if !path.is_dir() {
+        return Err(format!("Backup not found: {}", id));
+    }
+
+    Ok(path)
+}
+
+fn read_manifest(backup_dir: &Path) -> Result<BackupManifest, String> {
+    let manifest = fs::read_to_string(backup_dir.join(MANIFEST_FILE_NAME))
+        .map_err(|e| format!("Failed to read backup manifest: {}", e))?;
+
+    serde_json::from_str(&manifest).map_err(|e| format!("Failed to parse backup manifest: {}", e))
+}
+
+fn backup_info(id: &str, backup_dir: &Path, manifest: &BackupManifest) -> BackupInfo {
+    let database_size = fs::metadata(backup_dir.join(&manifest.database_file))
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+
+    BackupInfo {
+        id: id.to_string(),
+        path: backup_dir.to_string_lossy().to_string(),
+        created_at: manifest.created_at.clone(),
+        schema_version: manifest.schema_version,
+        image_count: manifest.images.len(),
+        size_bytes: database_size + manifest.images.iter().map(|image| image.size).sum::<u64>(),
+    }
+}
+
+/** Snapshots the database and its attachment files into a new backup.
+ *
+ * The database is copied with SQLite's online backup API, so the app
+ * can keep writing while the backup runs. Image files are read from the
+ * rows in the snapshot, which keeps them consistent with it. The backup
+ * is assembled in a `.partial` folder and renamed once complete.
+ */
+pub fn create_backup(conn: &Connection, backups_dir: &Path) -> Result<BackupInfo, String> {
+    write_backup_folder(conn, backups_dir, None)
+}
+
+/** Takes a backup whose ID ends in `label`, e.g. `pre-migration-v10`.
+ *
+ * Labeled backups are kept out of the rotation and stay until deleted.
+ */
+pub fn create_labeled_backup(
+    conn: &Connection,
+    backups_dir: &Path,
+    label: &str,
+) -> Result<BackupInfo, String> {
+    write_backup_folder(conn, backups_dir, Some(label))
+}
+
+fn write_backup_folder(
+    conn: &Connection,
+    backups_dir: &Path,
+    label: Option<&str>,
+) -> Result<BackupInfo, String> {
+    fs::create_dir_all(backups_dir)
+        .map_err(|e| format!("Failed to create backups directory: {}", e))?;
+
+    let created_at = now_seconds();
+    let base_id = match label {
+        Some(label) => format!("{}-{}", format_id(created_at), label),
+        None => format_id(created_at),
+    };
+    let mut id = base_id.clone();
+    let mut suffix = 2;
+    while backups_dir.join(&id).exists() {
+        id = format!("{}-{}", base_id, suffix);
+        suffix += 1;
+    }
+
+    let partial_dir = backups_dir.join(format!("{}{}", id, PARTIAL_SUFFIX));
+    let result = write_backup(conn, &partial_dir, created_at);
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            fs::remove_dir_all(&partial_dir).ok();
+            return Err(e);
+        }
+    };
+
+    let backup_dir = backups_dir.join(&id);
+    fs::rename(&partial_dir, &backup_dir)
+        .map_err(|e| format!("Failed to finalize backup: {}", e))?;
+
+    Ok(backup_info(&id, &backup_dir, &manifest))
+}
+
+fn write_backup(
+    conn: &Connection,
+    backup_dir: &Path,
+    created_at: i64,
+) -> Result<BackupManifest, String> {
+    let images_dir = backup_dir.join(IMAGES_DIR_NAME);
+    fs::create_dir_all(&images_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let database_file = config::database_file_name().to_string();
+    conn.backup(DatabaseName::Main, backup_dir.join(&database_file), None)
+        .map_err(|e| format!("Failed to back up database: {}", e))?;
+
+    let snapshot = Connection::open_with_flags(
+        backup_dir.join(&database_file),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .map_err(|e| format!("Failed to open database backup: {}", e))?;
+
+    let schema_version: i64 = snapshot
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    // Backups taken right before the attachments migration still have the
+    // old `images` table.
+    let table = if migrations::has_table(&snapshot, "attachments")? {
+        "attachments"
+    } else {
+        "images"
+    };
+    let rows = {
+        let mut stmt = snapshot
+            .prepare(&format!("SELECT id, file_path FROM {} ORDER BY id", table))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment row: {}", e))?
+    };
+
+    let mut images = Vec::new();
+    let mut missing_images = Vec::new();
+    // Attachments with the same content share a file, which is copied once.
+    let mut copied: HashMap<String, u64> = HashMap::new();
+    for (image_id, file_path) in rows {
+        let source = Path::new(&file_path);
+        let Some(file_name) = source.file_name() else {
+            missing_images.push(image_id);
+            continue;
+        };
+        let file_name = file_name.to_string_lossy().to_string();
+
+        let copy = match copied.get(&file_name) {
+            Some(size) => Ok(*size),
+            None => fs::copy(source, images_dir.join(&file_name)),
+        };
+        match copy {
+            Ok(size) => {
+                copied.insert(file_name.clone(), size);
+                images.push(BackupImage {
+                    id: image_id,
+                    file_name,
+                    size,
+                });
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => missing_images.push(image_id),
+            Err(e) => return Err(format!("Failed to back up attachment {}: {}", file_path, e)),
+        }
+    }
+
+    let manifest = BackupManifest {
+        created_at: format_rfc3339(created_at),
+        schema_version,
+        database_file,
+        images,
+        missing_images,
+    };
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize backup manifest: {}", e))?;
+    fs::write(backup_dir.join(MANIFEST_FILE_NAME), json)
+        .map_err(|e| format!("Failed to write backup manifest: {}", e))?;
+
+    Ok(manifest)
+}
+
+/** Lists complete backups, newest first. */
+pub fn list_backups(backups_dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match fs::read_dir(backups_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read backups directory: {}", e)),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read backups directory: {}", e))?;
+        let id = entry.file_name().to_string_lossy().to_string();
+        if !entry.path().is_dir() || id.ends_with(PARTIAL_SUFFIX) {
+            continue;
+        }
+
+        // Folders without a readable manifest are not backups.
+        if let Ok(manifest) = read_manifest(&entry.path()) {
+            backups.push(backup_info(&id, &entry.path(), &manifest));
+        }
+    }
+    backups.sort_by(|a, b| b.id.cmp(&a.id));
+
+    Ok(backups)
+}
+
+/** Checks that a backup's database is intact and its attachment files are complete. */
+pub fn verify_backup(backups_dir: &Path, id: &str) -> Result<BackupVerification, String> {
+    let backup_dir = backup_path(backups_dir, id)?;
+    let mut problems = Vec::new();
+
+    match read_manifest(&backup_dir) {
+        Ok(manifest) => {
+            problems.extend(verify_database(&backup_dir.join(&manifest.database_file)));
+
+            for image in &manifest.images {
+                let path = backup_dir.join(IMAGES_DIR_NAME).join(&image.file_name);
+                match fs::metadata(&path) {
+                    Ok(metadata) if metadata.len() == image.size => {}
+                    Ok(_) => problems.push(format!(
+                        "Attachment {} has the wrong size.",
+                        image.file_name
+                    )),
+                    Err(_) => problems.push(format!("Attachment {} is missing.", image.file_name)),
+                }
+            }
+        }
+        Err(e) => problems.push(e),
+    }
+
+    Ok(BackupVerification {
+        id: id.to_string(),
+        ok: problems.is_empty(),
+        problems,
+    })
+}
+
+fn verify_database(path: &Path) -> Vec<String> {
+    let conn = match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(conn) => conn,
+        Err(e) => return vec![format!("Failed to open database backup: {}", e)],
+    };
+
+    let messages = conn.prepare("PRAGMA integrity_check").and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()
+    });
+
+    match messages {
+        Ok(messages) => messages
+            .into_iter()
+            .filter(|message| message != "ok")
+            .map(|message| format!("Database integrity: {}", message))
+            .collect(),
+        Err(e) => vec![format!("Failed to check database backup: {}", e)],
+    }
+}
+
+/** Returns the newest backup this version can restore that passes verification. */
+pub fn latest_verified_backup(backups_dir: &Path) -> Result<Option<BackupInfo>, String> {
+    for backup in list_backups(backups_dir)? {
+        if backup.schema_version <= migrations::latest_version()
+            && verify_backup(backups_dir, &backup.id)?.ok
+        {
+            return Ok(Some(backup));
+        }
+    }
+
+    Ok(None)
+}
+
+/** Deletes backups outside the rotation and returns their IDs.
+ *
+ * The newest backup of each of the most recent `keep_hourly` hours,
+ * `keep_daily` days and `keep_weekly` weeks is kept; a backup kept by
+ * any tier survives. The newest rotated backup is always kept.
+ */
+pub fn rotate_backups(
+    backups_dir: &Path,
+    settings: &BackupSettings,
+) -> Result<Vec<String>, String> {
+    let backups = list_backups(backups_dir)?;
+
+    let mut keep: HashSet<&str> = HashSet::new();
+    if let Some(newest) = backups
+        .iter()
+        .find(|backup| rotation_timestamp(&backup.id).is_some())
+    {
+        keep.insert(&newest.id);
+    }
+
+    // (bucket length, offset, buckets to keep). Day 0 was a Thursday, so
+    // weeks are shifted by three days to start on Monday.
+    let tiers = [
+        (3600, 0, settings.keep_hourly),
+        (86_400, 0, settings.keep_daily),
+        (7 * 86_400, 3 * 86_400, settings.keep_weekly),
+    ];
+    for (bucket_seconds, offset, count) in tiers {
+        let mut buckets = HashSet::new();
+
+        // Backups are sorted newest first, so the first one seen in a
+        // bucket is that bucket's newest.
+        for backup in &backups {
+            let Some(seconds) = rotation_timestamp(&backup.id) else {
+                continue;
+            };
+            let bucket = (seconds + offset).div_euclid(bucket_seconds);
+            if buckets.len() < count as usize && buckets.insert(bucket) {
+                keep.insert(&backup.id);
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    for backup in &backups {
+        // Labeled backups and folders not named by this module are never
+        // rotated out.
+        if keep.contains(backup.id.as_str()) || rotation_timestamp(&backup.id).is_none() {
+            continue;
+        }
+
+        match fs::remove_dir_all(&backup.path) {
+            Ok(()) => removed.push(backup.id.clone()),
+            Err(e) => eprintln!("Failed to remove backup {}: {}", backup.id, e),
+        }
+    }
+
+    Ok(removed)
+}
+
+/** Replaces the live database and attachment files with a backup's contents.
+ *
+ * The backup is verified first. Its database is copied over the live
+ * one with the online backup API, migrations are run in case the backup
+ * predates the current schema, and attachment files are copied into
+ * `images_dir` with their rows pointed at them.
+ */
+pub fn restore_backup(
+    conn: &mut Connection,
+    backups_dir: &Path,
+    id: &str,
+    images_dir: &Path,
+) -> Result<(), String> {
+    let verification = verify_backup(backups_dir, id)?;
+    if !verification.ok {
+        return Err(format!(
+            "Backup {} failed verification: {}",
+            id,
+            verification.problems.join("; ")
+        ));
+    }
+
+    let backup_dir = backup_path(backups_dir, id)?;
+    let manifest = read_manifest(&backup_dir)?;
+    if manifest.schema_version > migrations::latest_version() {
+        return Err(format!(
+            "Backup {} was taken by a newer version of Unfold (schema {}). Update Unfold to restore it.",
+            id, manifest.schema_version
+        ));
+    }
+
+    fs::create_dir_all(images_dir)
+        .map_err(|e| format!("Failed to create images directory: {}", e))?;
+    let file_names: HashSet<&str> = manifest
+        .images
+        .iter()
+        .map(|image| image.file_name.as_str())
+        .collect();
+    for file_name in file_names {
+        fs::copy(
+            backup_dir.join(IMAGES_DIR_NAME).join(file_name),
+            images_dir.join(file_name),
+        )
+        .map_err(|e| format!("Failed to restore attachment {}: {}", file_name, e))?;
+    }
+
+    let source = Connection::open_with_flags(
+        backup_dir.join(&manifest.database_file),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .map_err(|e| format!("Failed to open database backup: {}", e))?;
+    Backup::new(&source, conn)
+        .and_then(|backup| backup.run_to_completion(256, Duration::from_millis(10), None))
+        .map_err(|e| format!("Failed to restore database: {}", e))?;
+
+    migrations::run_migrations(conn, None)?;
+
+    for image in &manifest.images {
+        let file_path = images_dir.join(&image.file_name);
+        conn.execute(
+            "UPDATE attachments SET file_path = ?1 WHERE id = ?2",
+            params![file_path.to_string_lossy(), image.id],
+        )
+        .map_err(|e| format!("Failed to update restored attachment path: {}", e))?;
+    }
+    conn.execute(
+        "UPDATE attachment_blobs
+         SET file_path = COALESCE(
+             (SELECT file_path FROM attachments WHERE blob_hash = attachment_blobs.hash LIMIT 1),
+             file_path
+         )",
+        [],
+    )
+    .map_err(|e| format!("Failed to update restored blob paths: {}", e))?;
+    // Variants point into the vault the backup was taken from; they are
+    // rendered again when next requested.
+    conn.execute("DELETE FROM attachment_variants", [])
+        .map_err(|e| format!("Failed to reset image variants: {}", e))?;
+    attachments::backfill_image_dimensions(conn)?;
+
+    Ok(())
+}
+
+/** Takes a backup if the newest one is older than the configured interval, then rotates. */
+fn run_scheduled_backup(app: &AppHandle) -> Result<(), String> {
+    let settings: BackupSettings = settings::load(app, settings::BACKUPS_KEY);
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let backups_dir = resolve_backups_dir(&config::storage_location(app)?, &settings);
+    let newest = list_backups(&backups_dir)?
+        .first()
+        .and_then(|backup| parse_timestamp(&backup.id));
+    let interval = i64::from(settings.interval_minutes.max(1)) * 60;
+    if newest.is_some_and(|newest| now_seconds() - newest < interval) {
+        return Ok(());
+    }
+
+    let conn = repository::open_connection(app)?;
+    create_backup(&conn, &backups_dir)?;
+    rotate_backups(&backups_dir, &settings)?;
+
+    Ok(())
+}
+
+/** Starts the background thread that takes automatic backups. */
+pub fn spawn_scheduler(app: AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(SCHEDULER_STARTUP_DELAY);
+        loop {
+            if let Err(e) = run_scheduled_backup(&app) {
+                eprintln!("Automatic backup failed: {}", e);
+            }
+            std::thread::sleep(SCHEDULER_POLL_INTERVAL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database,
+    };
+    use uuid::Uuid;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("unfold-backups-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /** Creates an empty backup folder with a manifest, as `list_backups` expects. */
+    fn fake_backup(backups_dir: &Path, id: &str) {
+        let backup_dir = backups_dir.join(id);
+        fs::create_dir_all(&backup_dir).unwrap();
+        let manifest = BackupManifest {
+            created_at: String::new(),
+            schema_version: 1,
+            database_file: config::database_file_name().to_string(),
+            images: Vec::new(),
+            missing_images: Vec::new(),
+        };
+        fs::write(
+            backup_dir.join(MANIFEST_FILE_NAME),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn backup_ids(backups_dir: &Path) -> Vec<String> {
+        list_backups(backups_dir)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.id)
+            .collect()
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+        assert_eq!(
+            days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 28),
+            2
+        );
+
+        for days in (-800_000..800_000).step_by(997) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn timestamps_round_trip_through_ids() {
+        let seconds = days_from_civil(2024, 1, 8) * 86_400 + 10 * 3600 + 30 * 60 + 5;
+
+        assert_eq!(format_id(seconds), "20240108-103005");
+        assert_eq!(format_rfc3339(seconds), "2024-01-08T10:30:05Z");
+        assert_eq!(parse_timestamp("20240108-103005"), Some(seconds));
+        assert_eq!(parse_timestamp("20240108_103005"), None);
+        assert_eq!(parse_timestamp("2024"), None);
+    }
+
+    #[test]
+    fn only_unlabeled_backups_have_a_rotation_timestamp() {
+        let seconds = parse_timestamp("20240108-103005");
+
+        assert_eq!(rotation_timestamp("20240108-103005"), seconds);
+        assert_eq!(rotation_timestamp("20240108-103005-2"), seconds);
+        assert_eq!(rotation_timestamp("20240108-103005-12"), seconds);
+        assert_eq!(rotation_timestamp("20240108-103005-"), None);
+        assert_eq!(rotation_timestamp("20240108-103005-pre-restore"), None);
+        assert_eq!(
+            rotation_timestamp("20240108-103005-pre-migration-v10"),
+            None
+        );
+        assert_eq!(rotation_timestamp("notes"), None);
+    }
+
+    #[test]
+    fn backup_path_accepts_labeled_and_numbered_ids() {
+        let backups_dir = temp_dir();
+        for id in ["20240108-103005-pre-restore", "20240108-103005-2"] {
+            fs::create_dir_all(backups_dir.join(id)).unwrap();
+            assert_eq!(backup_path(&backups_dir, id).unwrap(), backups_dir.join(id));
+        }
+
+        assert!(backup_path(&backups_dir, "20240108-103005").is_err());
+        for id in ["", "..", "../other", "a/b", "a\\b", ".hidden"] {
+            assert!(
+                backup_path(&backups_dir, id).is_err(),
+                "{:?} was accepted",
+                id
+            );
+        }
+
+        fs::remove_dir_all(&backups_dir).ok();
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_backup_of_each_bucket() {
+        let backups_dir = temp_dir();
+        // 2024-01-01 and 2024-01-08 are Mondays.
+        for id in [
+            "20240108-100000-2",
+            "20240108-100000",
+            "20240108-093000",
+            "20240108-090000",
+            "20240107-230000",
+            "20240107-120000",
+            "20240101-000000",
+            "20231201-000000-pre-restore",
+            "notes",
+        ] {
+            fake_backup(&backups_dir, id);
+        }
+        let settings = BackupSettings {
+            keep_hourly: 2,
+            keep_daily: 2,
+            keep_weekly: 2,
+            ..BackupSettings::default()
+        };
+
+        let mut removed = rotate_backups(&backups_dir, &settings).unwrap();
+        removed.sort();
+
+        // Sunday the 7th and Monday the 1st share a week, so the 1st goes.
+        assert_eq!(
+            removed,
+            [
+                "20240101-000000",
+                "20240107-120000",
+                "20240108-090000",
+                "20240108-100000",
+            ]
+        );
+        assert_eq!(
+            backup_ids(&backups_dir),
+            [
+                "notes",
+                "20240108-100000-2",
+                "20240108-093000",
+                "20240107-230000",
+                "20231201-000000-pre-restore",
+            ]
+        );
+
+        fs::remove_dir_all(&backups_dir).ok();
+    }
+
+    #[test]
+    fn rotation_always_keeps_the_newest_backup() {
+        let backups_dir = temp_dir();
+        fake_backup(&backups_dir, "20240108-100000");
+        fake_backup(&backups_dir, "20240101-100000");
+        let settings = BackupSettings {
+            keep_hourly: 0,
+            keep_daily: 0,
+            keep_weekly: 0,
+            ..BackupSettings::default()
+        };
+
+        let removed = rotate_backups(&backups_dir, &settings).unwrap();
+
+        assert_eq!(removed, ["20240101-100000"]);
+        assert_eq!(backup_ids(&backups_dir), ["20240108-100000"]);
+
+        fs::remove_dir_all(&backups_dir).ok();
+    }
+
+    #[test]
+    fn labeled_backups_are_listed_and_verified() {
+        let backups_dir = temp_dir();
+        let conn = open_test_database();
+
+        let backup = create_labeled_backup(&conn, &backups_dir, "pre-restore").unwrap();
+
+        assert!(backup.id.ends_with("-pre-restore"));
+        assert_eq!(backup_ids(&backups_dir), [backup.id.as_str()]);
+        assert!(verify_backup(&backups_dir, &backup.id).unwrap().ok);
+
+        fs::remove_dir_all(&backups_dir).ok();
+    }
+
+    #[test]
+    fn restore_points_attachments_at_the_new_images_dir() {
+        let root = temp_dir();
+        let old_images_dir = root.join("old-images");
+        let new_images_dir = root.join("new-images");
+        let backups_dir = root.join("backups");
+        fs::create_dir_all(&old_images_dir).unwrap();
+
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "Note", Some("{}"));
+        let old_path = old_images_dir.join("shared.png");
+        fs::write(&old_path, b"data").unwrap();
+        let old_path = old_path.to_string_lossy().to_string();
+        let first = insert_test_attachment(&conn, &note.id, &old_path, Some("hash"));
+        let second = insert_test_attachment(&conn, &note.id, &old_path, Some("hash"));
+        let backup = create_backup(&conn, &backups_dir).unwrap();
+        assert_eq!(backup.image_count, 2);
+
+        let mut restored = open_test_database();
+        restore_backup(&mut restored, &backups_dir, &backup.id, &new_images_dir).unwrap();
+
+        let new_path = new_images_dir.join("shared.png");
+        assert_eq!(fs::read(&new_path).unwrap(), b"data");
+        let new_path = new_path.to_string_lossy().to_string();
+        for attachment in [&first, &second] {
+            let file_path: String = restored
+                .query_row(
+                    "SELECT file_path FROM attachments WHERE id = ?1",
+                    [&attachment.id],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(file_path, new_path);
+        }
+        let blob_path: String = restored
+            .query_row(
+                "SELECT file_path FROM attachment_blobs WHERE hash = 'hash'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(blob_path, new_path);
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
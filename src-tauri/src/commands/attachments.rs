@@ -0,0 +1,210 @@
+use super::images::render_image_variants;
+use crate::events;
+use crate::models::attachments::{
+    Attachment, AttachmentKind, AttachmentSettings, UploadAttachmentRequest,
+};
+use crate::models::events::ChangeKind;
+use crate::repository::{self, attachments};
+use crate::settings;
+use crate::utils;
+use rusqlite::TransactionBehavior;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, command};
+use uuid::Uuid;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/** Stores `data` as an attachment of `note_id`.
+ *
+ * The content is stored once under its SHA-256 hash, next to the image
+ * attachments; uploading the same file again only adds a row pointing at
+ * the existing blob. Files over the size limit for their kind are refused
+ * before anything is written. Images are measured, and their thumbnail and
+ * display variants rendered, see [`render_image_variants`]. `original_id`
+ * links a re-encoded image to its kept original.
+ */
+pub(crate) fn store_attachment(
+    app: &AppHandle,
+    note_id: &str,
+    file_name: &str,
+    mime_type: &str,
+    data: &[u8],
+    original_id: Option<&str>,
+) -> Result<Attachment, String> {
+    let (original_name, extension) = attachments::split_file_name(file_name, mime_type);
+    let kind = attachments::classify(mime_type, &extension);
+    let size = data.len() as u64;
+
+    let limits: AttachmentSettings = settings::load(app, settings::ATTACHMENTS_KEY);
+    if let Some(max_mb) = limits.max_mb(kind)
+        && size > max_mb.saturating_mul(BYTES_PER_MB)
+    {
+        return Err(format!(
+            "{} is {:.1} MB, over the {} MB limit for {} attachments.",
+            original_name,
+            size as f64 / BYTES_PER_MB as f64,
+            max_mb,
+            kind.as_str()
+        ));
+    }
+
+    // Images are measured and later resized from the decoded pixels.
+    let decoded = match kind {
+        AttachmentKind::Image => utils::images::decode_image(data),
+        _ => None,
+    };
+
+    // Files go next to the database the row is written to, even if the
+    // vault is switched meanwhile.
+    let mut conn = repository::open_connection(app)?;
+    let location = conn.location().clone();
+    let attachments_dir = location.images_dir();
+    fs::create_dir_all(&attachments_dir)
+        .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+
+    let hash = utils::content_hash(data);
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let blob_path = match attachments::get_blob(&tx, &hash)? {
+        Some(blob) => PathBuf::from(blob.file_path),
+        None if extension.is_empty() => attachments_dir.join(&hash),
+        None => attachments_dir.join(format!("{}.{}", hash, extension)),
+    };
+    // A blob whose file went missing is written again.
+    let written = !blob_path.is_file();
+    if written {
+        fs::write(&blob_path, data)
+            .map_err(|e| format!("Failed to write attachment file: {}", e))?;
+    }
+
+    let stored = blob_path
+        .to_str()
+        .ok_or("Failed to convert path to string")
+        .map_err(str::to_string)
+        .and_then(|file_path| {
+            attachments::insert_blob(&tx, &hash, file_path, size)?;
+            attachments::insert_attachment(
+                &tx,
+                &Attachment {
+                    id: Uuid::new_v4().to_string(),
+                    note_id: note_id.to_string(),
+                    kind,
+                    original_name,
+                    extension,
+                    filename: blob_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    file_path: file_path.to_string(),
+                    size,
+                    mime_type: mime_type.to_string(),
+                    created_at: String::new(),
+                    blob_hash: Some(hash.clone()),
+                    width: decoded.as_ref().map(|decoded| decoded.width()),
+                    height: decoded.as_ref().map(|decoded| decoded.height()),
+                    original_id: original_id.map(str::to_string),
+                },
+            )
+        })
+        .and_then(|attachment| {
+            tx.commit()
+                .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            Ok(attachment)
+        });
+
+    let attachment = match stored {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            if written {
+                fs::remove_file(&blob_path).ok();
+            }
+            return Err(e);
+        }
+    };
+
+    // The upload stands without its variants; they are rendered on first use instead.
+    if let Some(decoded) = &decoded
+        && let Err(e) = render_image_variants(&conn, &location.variants_dir(), &attachment, decoded)
+    {
+        eprintln!("Failed to render image variants: {}", e);
+    }
+
+    events::attachments_changed(
+        app,
+        ChangeKind::Created,
+        Some(note_id),
+        std::slice::from_ref(&attachment.id),
+    );
+
+    Ok(attachment)
+}
+
+/** Deletes an attachment's row, and its file once no other attachment uses it.
+ *
+ * A re-encoded image takes its kept original with it.
+ */
+pub(crate) fn remove_attachment(app: &AppHandle, attachment_id: &str) -> Result<(), String> {
+    let mut conn = repository::open_connection(app)?;
+    let deleted = attachments::delete_attachment(&mut conn, attachment_id)?;
+
+    let mut ids = vec![deleted.attachment.id.clone()];
+    ids.extend(deleted.original.map(|original| original.id));
+    events::attachments_changed(
+        app,
+        ChangeKind::Deleted,
+        Some(&deleted.attachment.note_id),
+        &ids,
+    );
+
+    // The row is gone; files that fail to go are logged and left behind.
+    utils::remove_attachment_files(&deleted.file_paths);
+
+    Ok(())
+}
+
+/** Uploads a file of any type as an attachment of a note.
+ *
+ * Base64-decodes the data and stores it with its original name and
+ * extension, subject to the size limits in the `attachments` settings.
+ */
+#[command]
+pub async fn upload_attachment(
+    app: AppHandle,
+    request: UploadAttachmentRequest,
+) -> Result<Attachment, String> {
+    use base64::Engine;
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(&request.base64_data)
+        .map_err(|e| format!("Failed to decode base64: {}", e))?;
+
+    store_attachment(
+        &app,
+        &request.note_id,
+        &request.file_name,
+        &request.mime_type,
+        &data,
+        None,
+    )
+}
+
+#[command]
+pub async fn get_attachment(app: AppHandle, attachment_id: String) -> Result<Attachment, String> {
+    let conn = repository::open_connection(&app)?;
+    attachments::get_attachment(&conn, &attachment_id)
+}
+
+/** Lists a note's attachments, oldest first. */
+#[command]
+pub async fn list_attachments(app: AppHandle, note_id: String) -> Result<Vec<Attachment>, String> {
+    let conn = repository::open_connection(&app)?;
+    attachments::list_attachments(&conn, &note_id)
+}
+
+/** Deletes an attachment from both the database and the file system. */
+#[command]
+pub async fn delete_attachment(app: AppHandle, attachment_id: String) -> Result<(), String> {
+    remove_attachment(&app, &attachment_id)
+}
@@ -0,0 +1,64 @@
+use crate::backups;
+use crate::config;
+use crate::models::backups::{BackupInfo, BackupSettings, BackupVerification};
+use crate::repository;
+use crate::settings;
+use std::path::PathBuf;
+use tauri::{AppHandle, command};
+
+fn backups_dir(app: &AppHandle) -> Result<(PathBuf, BackupSettings), String> {
+    let settings: BackupSettings = settings::load(app, settings::BACKUPS_KEY);
+    let location = config::storage_location(app)?;
+    Ok((backups::resolve_backups_dir(&location, &settings), settings))
+}
+
+/** Takes a backup now and applies the rotation. */
+#[command]
+pub async fn create_backup(app: AppHandle) -> Result<BackupInfo, String> {
+    let (backups_dir, settings) = backups_dir(&app)?;
+    let conn = repository::open_connection(&app)?;
+
+    let backup = backups::create_backup(&conn, &backups_dir)?;
+    backups::rotate_backups(&backups_dir, &settings)?;
+
+    Ok(backup)
+}
+
+/** Lists backups in the configured folder, newest first. */
+#[command]
+pub async fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let (backups_dir, _) = backups_dir(&app)?;
+    backups::list_backups(&backups_dir)
+}
+
+/** Checks a backup's database and image files. */
+#[command]
+pub async fn verify_backup(
+    app: AppHandle,
+    backup_id: String,
+) -> Result<BackupVerification, String> {
+    let (backups_dir, _) = backups_dir(&app)?;
+    backups::verify_backup(&backups_dir, &backup_id)
+}
+
+/** Restores the database and images from a backup, then restarts the app.
+ *
+ * The current state is backed up first, under a `pre-restore` label so
+ * the rotation keeps it, so the restore can be undone.
+ */
+#[command]
+pub async fn restore_backup(app: AppHandle, backup_id: String) -> Result<(), String> {
+    let (backups_dir, _) = backups_dir(&app)?;
+    let mut conn = repository::open_connection(&app)?;
+
+    backups::create_labeled_backup(&conn, &backups_dir, "pre-restore")?;
+    backups::restore_backup(
+        &mut conn,
+        &backups_dir,
+        &backup_id,
+        &config::storage_location(&app)?.images_dir(),
+    )?;
+    drop(conn);
+
+    app.restart()
+}
@@ -1,24 +1,30 @@
+use super::attachments::{remove_attachment, store_attachment};
 use crate::config;
-use crate::models::images::{SaveImageRequest, SavePdfRequest, UploadImageRequest, UploadImageResponse};
+use crate::models::attachments::{Attachment, AttachmentKind, ImageVariant, ImageVariantSize};
+use crate::models::images::{
+    ImageMetadataPolicy, ImageMetadataSettings, ImageUploadSettings, SaveImageRequest,
+    SavePdfRequest, UploadImageRequest, UploadImageResponse,
+};
+use crate::repository::{self, attachments};
+use crate::settings;
+use crate::utils::image_metadata;
+use crate::utils::images::{self as image_utils, DecodedImage};
+use image::ImageFormat;
+use rusqlite::Connection;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::{AppHandle, Manager, command};
+use std::path::Path;
+use tauri::{AppHandle, command};
 use tauri_plugin_dialog::{DialogExt, FilePath};
-use uuid::Uuid;
-
-fn resolve_database_path(app: &AppHandle) -> Result<PathBuf, String> {
-    let app_data_dir = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    Ok(config::database_path(&app_data_dir))
-}
 
 /** Uploads an image file to the app's local data directory.
  *
- * Base64-decodes the image data, generates a unique filename using UUID,
- * writes it to disk, and stores metadata in the database.
+ * Base64-decodes the image data, strips metadata as the `image_metadata`
+ * settings ask, applies the upload policy from the `image_uploads`
+ * settings and stores the result as an attachment of the note; see
+ * [`store_attachment`]. When the policy re-encodes the image and keeps
+ * originals, the scrubbed upload is stored first and linked from the
+ * re-encoded one.
  */
 #[command]
 pub async fn upload_image(
@@ -30,148 +36,259 @@ pub async fn upload_image(
     let image_data = base64::engine::general_purpose::STANDARD
         .decode(&request.base64_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
+    let original_size = image_data.len() as u64;
+
+    let metadata: ImageMetadataSettings = settings::load(&app, settings::IMAGE_METADATA_KEY);
+    let image_data = scrub_image_metadata(&request.file_name, image_data, metadata.on_upload);
+
+    let policy: ImageUploadSettings = settings::load(&app, settings::IMAGE_UPLOADS_KEY);
+    let recompressed = image_utils::recompress(&image_data, &policy).unwrap_or_else(|e| {
+        eprintln!("Failed to recompress {}: {}", request.file_name, e);
+        None
+    });
+
+    let Some(recompressed) = recompressed else {
+        let attachment = store_attachment(
+            &app,
+            &request.note_id,
+            &request.file_name,
+            &request.mime_type,
+            &image_data,
+            None,
+        )?;
+
+        return Ok(UploadImageResponse {
+            id: attachment.id,
+            path: attachment.file_path,
+            size: attachment.size,
+            original_size,
+            original_id: None,
+        });
+    };
 
-    // Get app local data directory
-    let app_data_dir = app
-        .path()
-        .app_local_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
-
-    // Create images directory if it doesn't exist
-    let images_dir = app_data_dir.join("images");
-    fs::create_dir_all(&images_dir)
-        .map_err(|e| format!("Failed to create images directory: {}", e))?;
-
-    // Generate unique filename using UUID
-    let file_extension = get_extension_from_mime(&request.mime_type);
-    let unique_filename = format!("{}.{}", Uuid::new_v4(), file_extension);
-    let file_path = images_dir.join(&unique_filename);
-
-    // Write image data to file
-    fs::write(&file_path, &image_data).map_err(|e| format!("Failed to write image file: {}", e))?;
-
-    let attachment_id = Uuid::new_v4().to_string();
-    let file_path_str = file_path
-        .to_str()
-        .ok_or("Failed to convert path to string")?
-        .to_string();
+    let original = if policy.keep_original {
+        Some(store_attachment(
+            &app,
+            &request.note_id,
+            &request.file_name,
+            &request.mime_type,
+            &image_data,
+            None,
+        )?)
+    } else {
+        None
+    };
 
-    // Store metadata in database
-    store_image_metadata(
+    let file_name = Path::new(&request.file_name)
+        .with_extension(recompressed.extension)
+        .to_string_lossy()
+        .to_string();
+    let stored = store_attachment(
         &app,
-        &attachment_id,
         &request.note_id,
-        &unique_filename,
-        &file_path_str,
-        request.size,
-        &request.mime_type,
-    )
-    .await?;
+        &file_name,
+        recompressed.mime_type,
+        &recompressed.data,
+        original.as_ref().map(|original| original.id.as_str()),
+    );
+    let attachment = match stored {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            if let Some(original) = &original {
+                remove_attachment(&app, &original.id).ok();
+            }
+            return Err(e);
+        }
+    };
 
     Ok(UploadImageResponse {
-        id: attachment_id,
-        path: file_path_str,
-        size: request.size,
+        id: attachment.id,
+        path: attachment.file_path,
+        size: attachment.size,
+        original_size,
+        original_id: original.map(|original| original.id),
     })
 }
 
+/** Strips metadata from an image file as `policy` asks.
+ *
+ * Files that aren't JPEG, PNG or WebP are returned unchanged, as are
+ * files whose metadata fails to parse.
+ */
+fn scrub_image_metadata(file_name: &str, data: Vec<u8>, policy: ImageMetadataPolicy) -> Vec<u8> {
+    match image_metadata::scrub_metadata(&data, policy) {
+        Ok(Some(scrubbed)) => scrubbed,
+        Ok(None) => data,
+        Err(e) => {
+            eprintln!("Failed to strip metadata from {}: {}", file_name, e);
+            data
+        }
+    }
+}
+
 /** Retrieves the file path of an image from the database by attachment ID. */
 #[command]
 pub async fn get_image(app: AppHandle, attachment_id: String) -> Result<String, String> {
-    let db_path = resolve_database_path(&app)?;
+    let conn = repository::open_connection(&app)?;
+    let attachment = attachments::get_attachment(&conn, &attachment_id)?;
 
-    let conn = rusqlite::Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+    Ok(attachment.file_path)
+}
 
-    let mut stmt = conn
-        .prepare("SELECT file_path FROM images WHERE id = ?1")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+/** Renders the thumbnail and display variants of an image attachment.
+ *
+ * Variants already on disk are kept. They are stored in `variants_dir`
+ * under the blob's hash, so attachments sharing a file share them too.
+ */
+pub(crate) fn render_image_variants(
+    conn: &Connection,
+    variants_dir: &Path,
+    attachment: &Attachment,
+    decoded: &DecodedImage,
+) -> Result<(), String> {
+    let Some(hash) = &attachment.blob_hash else {
+        return Ok(());
+    };
 
-    let path: String = stmt
-        .query_row([&attachment_id], |row| row.get(0))
-        .map_err(|e| format!("Failed to get image: {}", e))?;
+    for size in [ImageVariantSize::Thumbnail, ImageVariantSize::Display] {
+        if attachments::get_image_variant(conn, attachment, size)?
+            .is_some_and(|variant| Path::new(&variant.file_path).is_file())
+        {
+            continue;
+        }
+        let Some(rendered) = image_utils::render_variant(decoded, size)? else {
+            continue;
+        };
+
+        fs::create_dir_all(variants_dir)
+            .map_err(|e| format!("Failed to create variants directory: {}", e))?;
+        let file_path =
+            variants_dir.join(format!("{}-{}.{}", hash, size.as_str(), rendered.extension));
+        fs::write(&file_path, &rendered.data)
+            .map_err(|e| format!("Failed to write image variant: {}", e))?;
+
+        if let Err(e) = attachments::insert_image_variant(
+            conn,
+            hash,
+            size,
+            &file_path.to_string_lossy(),
+            rendered.width,
+            rendered.height,
+            rendered.mime_type,
+        ) {
+            fs::remove_file(&file_path).ok();
+            return Err(e);
+        }
+    }
 
-    Ok(path)
+    Ok(())
 }
 
-/** Deletes an image from both the database and the file system. */
-#[command]
-pub async fn delete_image(app: AppHandle, attachment_id: String) -> Result<(), String> {
-    let db_path = resolve_database_path(&app)?;
-
-    let conn = rusqlite::Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-
-    // Get file path before deleting from DB
-    let mut stmt = conn
-        .prepare("SELECT file_path FROM images WHERE id = ?1")
-        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+/** Resolves the file to load for an image attachment at `size`.
+ *
+ * Missing variants are rendered from the original on first use, so images
+ * uploaded before variants existed get them too. Attachments that aren't
+ * decodable images, and images that already fit, resolve to the original.
+ */
+fn resolve_image_variant(
+    app: &AppHandle,
+    conn: &Connection,
+    attachment: &Attachment,
+    size: ImageVariantSize,
+) -> Result<ImageVariant, String> {
+    let original = ImageVariant {
+        attachment_id: attachment.id.clone(),
+        size: ImageVariantSize::Original,
+        file_path: attachment.file_path.clone(),
+        width: attachment.width,
+        height: attachment.height,
+        mime_type: attachment.mime_type.clone(),
+    };
 
-    let path: String = stmt
-        .query_row([&attachment_id], |row| row.get(0))
-        .map_err(|e| format!("Failed to get image: {}", e))?;
+    // Images without dimensions failed to decode when they were measured.
+    let (Some(width), Some(height)) = (attachment.width, attachment.height) else {
+        return Ok(original);
+    };
+    let format = ImageFormat::from_mime_type(&attachment.mime_type).unwrap_or(ImageFormat::Png);
+    if attachment.kind != AttachmentKind::Image
+        || attachment.blob_hash.is_none()
+        || !image_utils::needs_variant(format, width, height, size)
+    {
+        return Ok(original);
+    }
 
-    // Delete from database
-    conn.execute("DELETE FROM images WHERE id = ?1", [&attachment_id])
-        .map_err(|e| format!("Failed to delete from database: {}", e))?;
+    if let Some(variant) = attachments::get_image_variant(conn, attachment, size)?
+        && Path::new(&variant.file_path).is_file()
+    {
+        return Ok(variant);
+    }
 
-    // Delete physical file
-    fs::remove_file(&path).map_err(|e| format!("Failed to delete file: {}", e))?;
+    let data = fs::read(&attachment.file_path)
+        .map_err(|e| format!("Failed to read image: {}", e))?;
+    let Some(decoded) = image_utils::decode_image(&data) else {
+        return Ok(original);
+    };
+    let variants_dir = config::storage_location(app)?.variants_dir();
+    render_image_variants(conn, &variants_dir, attachment, &decoded)?;
 
-    Ok(())
+    Ok(attachments::get_image_variant(conn, attachment, size)?.unwrap_or(original))
 }
 
-/** Stores image metadata in the database (internal helper). */
-async fn store_image_metadata(
-    app: &AppHandle,
-    id: &str,
-    note_id: &str,
-    filename: &str,
-    file_path: &str,
-    size: u64,
-    mime_type: &str,
-) -> Result<(), String> {
-    let db_path = resolve_database_path(app)?;
-
-    let conn = rusqlite::Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
-
-    conn.execute(
-        "INSERT INTO images (id, note_id, filename, file_path, size, mime_type, created_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
-        rusqlite::params![
-            id,
-            note_id,
-            filename,
-            file_path,
-            size.to_string(),
-            mime_type
-        ],
-    )
-    .map_err(|e| format!("Failed to insert image metadata: {}", e))?;
+/** Returns the file to load an image attachment from at the given size.
+ *
+ * Lets the editor and PDF export pick a resolution suited to where the
+ * image is shown instead of always loading the original.
+ */
+#[command]
+pub async fn get_image_variant(
+    app: AppHandle,
+    attachment_id: String,
+    size: ImageVariantSize,
+) -> Result<ImageVariant, String> {
+    let conn = repository::open_connection(&app)?;
+    let attachment = attachments::get_attachment(&conn, &attachment_id)?;
 
-    Ok(())
+    resolve_image_variant(&app, &conn, &attachment, size)
 }
 
+/** Lists image attachments whose files still carry GPS coordinates or an XMP location.
+ *
+ * Meant for cleaning up images uploaded before location stripping, or
+ * with it turned off. Attachments sharing a file are all listed; files
+ * that are missing or unreadable are skipped.
+ */
+#[command]
+pub async fn list_images_with_location(app: AppHandle) -> Result<Vec<Attachment>, String> {
+    let images = {
+        let conn = repository::open_connection(&app)?;
+        attachments::list_attachments_by_kind(&conn, AttachmentKind::Image)?
+    };
 
-
-fn get_extension_from_mime(mime_type: &str) -> &str {
-    match mime_type {
-        "image/jpeg" => "jpg",
-        "image/jpg" => "jpg",
-        "image/png" => "png",
-        "image/gif" => "gif",
-        "image/webp" => "webp",
-        "image/svg+xml" => "svg",
-        "image/bmp" => "bmp",
-        "image/tiff" => "tiff",
-        _ => "jpg",
+    let mut checked: HashMap<String, bool> = HashMap::new();
+    let mut located = Vec::new();
+    for image in images {
+        let has_location = match checked.get(&image.file_path) {
+            Some(has_location) => *has_location,
+            None => {
+                let has_location = fs::read(&image.file_path)
+                    .is_ok_and(|data| image_metadata::has_location(&data));
+                checked.insert(image.file_path.clone(), has_location);
+                has_location
+            }
+        };
+        if has_location {
+            located.push(image);
+        }
     }
-}
-
-
 
+    Ok(located)
+}
 
+/** Deletes an image from both the database and the file system. */
+#[command]
+pub async fn delete_image(app: AppHandle, attachment_id: String) -> Result<(), String> {
+    remove_attachment(&app, &attachment_id)
+}
 
 /** Opens a save dialog and writes a PDF file to the selected location. */
 #[command]
@@ -205,6 +322,9 @@ pub async fn save_pdf_file(app: AppHandle, request: SavePdfRequest) -> Result<()
  * Can source the image from:
  * - An existing attachment ID (retrieves from app storage)
  * - A remote HTTPS URL (downloads the image)
+ *
+ * Metadata is stripped from the written copy as the `image_metadata`
+ * settings ask for exports.
  */
 #[command]
 pub async fn save_image_file(app: AppHandle, request: SaveImageRequest) -> Result<(), String> {
@@ -268,6 +388,10 @@ pub async fn save_image_file(app: AppHandle, request: SaveImageRequest) -> Resul
         return Err("No image source provided.".into());
     };
 
+    let metadata: ImageMetadataSettings = settings::load(&app, settings::IMAGE_METADATA_KEY);
+    let image_bytes =
+        scrub_image_metadata(&request.suggested_name, image_bytes, metadata.on_export);
+
     fs::write(&path, image_bytes).map_err(|e| format!("Failed to write image file: {}", e))?;
 
     Ok(())
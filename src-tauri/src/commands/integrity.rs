@@ -0,0 +1,70 @@
+use crate::config;
+use crate::events;
+use crate::models::events::ChangeKind;
+use crate::models::integrity::{IntegrityRepair, IntegrityReport};
+use crate::repository::{self, integrity, nodes};
+use crate::utils::quarantine_files;
+use tauri::{AppHandle, command};
+
+/** Checks the database and image files for inconsistencies. */
+#[command]
+pub async fn check_integrity(app: AppHandle) -> Result<IntegrityReport, String> {
+    let conn = repository::open_connection(&app)?;
+    integrity::check_integrity(&conn, &config::storage_location(&app)?.images_dir())
+}
+
+/** Repairs what can be fixed safely and returns a fresh report.
+ *
+ * Database rows are repaired in one transaction. Image files with no row
+ * are then moved to a timestamped folder under `quarantine` in the storage
+ * location rather than deleted.
+ */
+#[command]
+pub async fn repair_integrity(app: AppHandle) -> Result<IntegrityRepair, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let location = config::storage_location(&app)?;
+    let images_dir = location.images_dir();
+
+    let repaired = integrity::repair_integrity(&mut conn)?;
+
+    if let Some(space_id) = &repaired.recovered_space_id {
+        events::space_changed(&app, ChangeKind::Created, space_id);
+    }
+    let reparented = repaired
+        .reparented_node_ids
+        .iter()
+        .map(|id| nodes::get_node(&conn, id))
+        .collect::<Result<Vec<_>, _>>()?;
+    events::nodes_changed(&app, ChangeKind::Moved, &reparented);
+    events::attachments_changed(
+        &app,
+        ChangeKind::Deleted,
+        None,
+        &repaired.deleted_attachment_ids,
+    );
+
+    let stray_files = integrity::check_integrity(&conn, &images_dir)?.stray_files;
+    let (quarantined_files, quarantine_dir) = if stray_files.is_empty() {
+        (Vec::new(), None)
+    } else {
+        let timestamp: String = conn
+            .query_row("SELECT strftime('%Y%m%d-%H%M%S', 'now')", [], |row| {
+                row.get(0)
+            })
+            .map_err(|e| format!("Failed to read timestamp: {}", e))?;
+        let quarantine_dir = location.quarantine_dir().join(timestamp);
+
+        let (moved, _) = quarantine_files(&stray_files, &quarantine_dir);
+        (moved, Some(quarantine_dir.to_string_lossy().to_string()))
+    };
+
+    Ok(IntegrityRepair {
+        reparented_node_ids: repaired.reparented_node_ids,
+        recovered_space_id: repaired.recovered_space_id,
+        deleted_attachment_ids: repaired.deleted_attachment_ids,
+        deleted_row_count: repaired.deleted_row_count,
+        quarantined_files,
+        quarantine_dir,
+        report: integrity::check_integrity(&conn, &images_dir)?,
+    })
+}
@@ -0,0 +1,24 @@
+use crate::models::links::NodeLink;
+use crate::repository::{self, links};
+use tauri::{AppHandle, command};
+
+/** Lists links from other notes that point at a node. */
+#[command]
+pub async fn get_backlinks(app: AppHandle, node_id: String) -> Result<Vec<NodeLink>, String> {
+    let conn = repository::open_connection(&app)?;
+    links::get_backlinks(&conn, &node_id)
+}
+
+/** Lists the links written in a node, with the notes they resolve to. */
+#[command]
+pub async fn get_outgoing_links(app: AppHandle, node_id: String) -> Result<Vec<NodeLink>, String> {
+    let conn = repository::open_connection(&app)?;
+    links::get_outgoing_links(&conn, &node_id)
+}
+
+/** Lists links across all notes whose target is missing or trashed. */
+#[command]
+pub async fn find_unresolved_links(app: AppHandle) -> Result<Vec<NodeLink>, String> {
+    let conn = repository::open_connection(&app)?;
+    links::find_unresolved_links(&conn)
+}
@@ -1,7 +1,35 @@
+pub mod attachments;
+pub mod backups;
 pub mod images;
+pub mod integrity;
+pub mod links;
+pub mod nodes;
+pub mod recovery;
+pub mod revisions;
+pub mod schema;
+pub mod search;
+pub mod spaces;
+pub mod tags;
+pub mod trash;
+pub mod vaults;
 pub mod web_import;
+pub mod windows;
 pub mod fonts;
 
+pub use attachments::*;
+pub use backups::*;
 pub use images::*;
+pub use integrity::*;
+pub use links::*;
+pub use nodes::*;
+pub use recovery::*;
+pub use revisions::*;
+pub use schema::*;
+pub use search::*;
+pub use spaces::*;
+pub use tags::*;
+pub use trash::*;
+pub use vaults::*;
 pub use web_import::*;
+pub use windows::*;
 pub use fonts::*;
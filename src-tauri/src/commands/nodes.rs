@@ -0,0 +1,185 @@
+use crate::events;
+use crate::models::events::ChangeKind;
+use crate::models::nodes::{
+    CreateNodeRequest, DeleteSubtreeResponse, MoveNodeRequest, Node, SaveNodeError,
+    UpdateNodeRequest,
+};
+use crate::repository::nodes::DeletedSubtree;
+use crate::repository::{self, nodes};
+use crate::settings;
+use crate::utils::{copy_attachment_files, remove_attachment_files};
+use tauri::{AppHandle, command};
+
+/** Lists every node in a space in sibling order. */
+#[command]
+pub async fn list_nodes(app: AppHandle, space_id: String) -> Result<Vec<Node>, String> {
+    let conn = repository::open_connection(&app)?;
+    nodes::list_nodes(&conn, &space_id)
+}
+
+/** Fetches a single node by ID. */
+#[command]
+pub async fn get_node(app: AppHandle, node_id: String) -> Result<Node, String> {
+    let conn = repository::open_connection(&app)?;
+    nodes::get_node(&conn, &node_id)
+}
+
+/** Creates a node as the last child of its parent.
+ *
+ * Validates that the space and parent exist and assigns the sort order
+ * and timestamps.
+ */
+#[command]
+pub async fn create_node(app: AppHandle, request: CreateNodeRequest) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let node = nodes::create_node(&mut conn, &request)?;
+
+    events::nodes_changed(&app, ChangeKind::Created, [&node]);
+    Ok(node)
+}
+
+/** Updates a node's name, content, open or pinned state.
+ *
+ * Content changes are recorded in the node's revision history. Notes
+ * whose links were rewritten by a rename are announced along with it.
+ */
+#[command]
+pub async fn update_node(app: AppHandle, request: UpdateNodeRequest) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let revision_settings = settings::load(&app, settings::REVISIONS_KEY);
+    let updated = nodes::update_node(&mut conn, &request, &revision_settings)?;
+
+    events::nodes_changed(
+        &app,
+        ChangeKind::Updated,
+        std::iter::once(&updated.node).chain(&updated.rewritten),
+    );
+
+    Ok(updated.node)
+}
+
+/** Saves a node's content if it is still at `expected_revision`.
+ *
+ * A stale save fails with `SaveNodeError::Conflict` carrying the stored
+ * node instead of overwriting it.
+ */
+#[command]
+pub async fn save_node_content(
+    app: AppHandle,
+    node_id: String,
+    expected_revision: i64,
+    content: String,
+) -> Result<Node, SaveNodeError> {
+    let mut conn = repository::open_connection(&app)?;
+    let revision_settings = settings::load(&app, settings::REVISIONS_KEY);
+    let node = nodes::save_node_content(
+        &mut conn,
+        &node_id,
+        expected_revision,
+        &content,
+        &revision_settings,
+    )?;
+
+    events::nodes_changed(&app, ChangeKind::Updated, [&node]);
+    Ok(node)
+}
+
+/** Moves a node and its subtree under a new parent, possibly in another space. */
+#[command]
+pub async fn move_node(app: AppHandle, request: MoveNodeRequest) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let previous = nodes::get_node(&conn, &request.node_id)?;
+    let node = nodes::move_node(&mut conn, &request)?;
+
+    let mut space_ids = vec![node.space_id.clone()];
+    let node_ids = if previous.space_id != node.space_id {
+        // The whole subtree changed spaces.
+        space_ids.push(previous.space_id);
+        nodes::subtree_ids(&conn, &node.id)?
+    } else {
+        vec![node.id.clone()]
+    };
+    events::node_ids_changed(&app, ChangeKind::Moved, space_ids, &node_ids);
+
+    Ok(node)
+}
+
+/** Duplicates a node, or its whole subtree when `deep` is set, with copies of its attachments.
+ *
+ * Attachment files are copied after the rows are committed. If a copy
+ * fails the duplicate is deleted again and the error is returned.
+ */
+#[command]
+pub async fn duplicate_node(app: AppHandle, node_id: String, deep: bool) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let duplicated = nodes::duplicate_node(&mut conn, &node_id, deep)?;
+
+    if let Err(e) = copy_attachment_files(&duplicated.file_copies) {
+        let deleted = nodes::delete_subtree(&mut conn, &duplicated.node.id)?;
+        remove_attachment_files(&deleted.file_paths);
+        return Err(e);
+    }
+
+    events::node_ids_changed(
+        &app,
+        ChangeKind::Created,
+        vec![duplicated.node.space_id.clone()],
+        &duplicated.node_ids,
+    );
+    events::attachments_changed(&app, ChangeKind::Created, None, &duplicated.attachment_ids);
+
+    Ok(duplicated.node)
+}
+
+/** Deletes a node with all of its descendants.
+ *
+ * Goes through [`nodes::delete_subtree`] so their attachments, and the
+ * files no other attachment uses, are removed along with them.
+ */
+#[command]
+pub async fn delete_node(app: AppHandle, node_id: String) -> Result<(), String> {
+    let mut conn = repository::open_connection(&app)?;
+    let deleted = nodes::delete_subtree(&mut conn, &node_id)?;
+
+    remove_deleted_files(&app, deleted);
+    Ok(())
+}
+
+/** Deletes a node with all of its descendants and their image attachments.
+ *
+ * Rows are removed in a single transaction; attachment files are deleted
+ * from disk afterwards.
+ */
+#[command]
+pub async fn delete_subtree(
+    app: AppHandle,
+    node_id: String,
+) -> Result<DeleteSubtreeResponse, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let deleted = nodes::delete_subtree(&mut conn, &node_id)?;
+
+    Ok(remove_deleted_files(&app, deleted))
+}
+
+/** Removes the attachment files of committed deletions, announces them and summarizes the result. */
+pub(crate) fn remove_deleted_files(
+    app: &AppHandle,
+    deleted: DeletedSubtree,
+) -> DeleteSubtreeResponse {
+    let (removed_files, failed_files) = remove_attachment_files(&deleted.file_paths);
+
+    events::node_ids_changed(
+        app,
+        ChangeKind::Deleted,
+        deleted.space_ids,
+        &deleted.node_ids,
+    );
+    events::attachments_changed(app, ChangeKind::Deleted, None, &deleted.attachment_ids);
+
+    DeleteSubtreeResponse {
+        node_ids: deleted.node_ids,
+        attachment_ids: deleted.attachment_ids,
+        removed_files,
+        failed_files,
+    }
+}
@@ -0,0 +1,55 @@
+use crate::models::backups::BackupInfo;
+use crate::models::recovery::RecoveryStatus;
+use crate::recovery::{self, Recovery};
+use tauri::{AppHandle, Manager, command};
+use tauri_plugin_dialog::{DialogExt, FilePath};
+
+/** Returns why startup failed, or `None` when the app started normally. */
+#[command]
+pub async fn get_recovery_status(app: AppHandle) -> Result<Option<RecoveryStatus>, String> {
+    Ok(app
+        .try_state::<Recovery>()
+        .map(|recovery| recovery.status()))
+}
+
+/** Replaces the broken database with the latest verified backup and opens the app. */
+#[command]
+pub async fn recover_from_backup(app: AppHandle) -> Result<BackupInfo, String> {
+    recovery::recover_from_backup(&app)
+}
+
+/** Opens the app on the database as is, without allowing changes. */
+#[command]
+pub async fn open_read_only(app: AppHandle) -> Result<(), String> {
+    recovery::open_read_only(&app)
+}
+
+/** Opens a save dialog and exports whatever notes can still be read as JSON.
+ *
+ * Returns the path written to, or `None` if the dialog was canceled.
+ */
+#[command]
+pub async fn export_raw_content(app: AppHandle) -> Result<Option<String>, String> {
+    let file_path = app
+        .dialog()
+        .file()
+        .add_filter("JSON", &["json"])
+        .set_file_name("unfold-export.json")
+        .blocking_save_file();
+
+    let Some(chosen) = file_path else {
+        // User canceled save dialog.
+        return Ok(None);
+    };
+
+    let path = match chosen {
+        FilePath::Path(path) => path,
+        FilePath::Url(url) => url
+            .to_file_path()
+            .map_err(|_| "Failed to resolve selected file path.".to_string())?,
+    };
+
+    recovery::export_raw_content(&app, &path)?;
+
+    Ok(Some(path.to_string_lossy().to_string()))
+}
@@ -0,0 +1,63 @@
+use crate::events;
+use crate::models::events::ChangeKind;
+use crate::models::nodes::Node;
+use crate::models::revisions::{BlockDiff, NodeRevision, NodeRevisionSummary};
+use crate::repository::{self, nodes, revisions};
+use crate::settings;
+use crate::utils::tiptap;
+use tauri::{AppHandle, command};
+
+/** Lists a node's revisions, newest first. */
+#[command]
+pub async fn list_node_revisions(
+    app: AppHandle,
+    node_id: String,
+) -> Result<Vec<NodeRevisionSummary>, String> {
+    let conn = repository::open_connection(&app)?;
+    revisions::list_revisions(&conn, &node_id)
+}
+
+/** Fetches a single revision including its content. */
+#[command]
+pub async fn get_node_revision(
+    app: AppHandle,
+    revision_id: String,
+) -> Result<NodeRevision, String> {
+    let conn = repository::open_connection(&app)?;
+    revisions::get_revision(&conn, &revision_id)
+}
+
+/** Computes a block-level diff between two revisions.
+ *
+ * When `to_revision_id` is omitted the node's current content is used.
+ */
+#[command]
+pub async fn diff_node_revisions(
+    app: AppHandle,
+    from_revision_id: String,
+    to_revision_id: Option<String>,
+) -> Result<Vec<BlockDiff>, String> {
+    let conn = repository::open_connection(&app)?;
+    let from = revisions::get_revision(&conn, &from_revision_id)?;
+
+    let to_content = match to_revision_id {
+        Some(to_revision_id) => revisions::get_revision(&conn, &to_revision_id)?.content,
+        None => nodes::get_node(&conn, &from.node_id)?.content,
+    };
+
+    Ok(tiptap::diff_blocks(
+        from.content.as_deref(),
+        to_content.as_deref(),
+    ))
+}
+
+/** Restores a node to the name and content of a revision. */
+#[command]
+pub async fn restore_node_revision(app: AppHandle, revision_id: String) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let revision_settings = settings::load(&app, settings::REVISIONS_KEY);
+    let node = revisions::restore_revision(&mut conn, &revision_id, &revision_settings)?;
+
+    events::nodes_changed(&app, ChangeKind::Updated, [&node]);
+    Ok(node)
+}
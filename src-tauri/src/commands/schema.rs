@@ -0,0 +1,45 @@
+use crate::backups;
+use crate::config;
+use crate::migrations;
+use crate::models::backups::BackupSettings;
+use crate::models::schema::SchemaStatus;
+use crate::repository;
+use crate::settings;
+use tauri::{AppHandle, command};
+
+/** Reports the open database's schema version and migration history. */
+#[command]
+pub async fn get_schema_status(app: AppHandle) -> Result<SchemaStatus, String> {
+    let conn = repository::open_connection(&app)?;
+
+    Ok(SchemaStatus {
+        version: migrations::schema_version(&conn)?,
+        latest_version: migrations::latest_version(),
+        migrations: migrations::applied_migrations(&conn)?,
+    })
+}
+
+/** Reverts the schema to `target_version` so an older release can open the vault, then quits.
+ *
+ * A backup is taken first. The app exits afterwards because it needs the
+ * latest schema; starting this version again re-applies the migrations.
+ */
+#[command]
+pub async fn downgrade_schema(app: AppHandle, target_version: i64) -> Result<(), String> {
+    let mut conn = repository::open_connection(&app)?;
+    let version = migrations::check_version(&conn)?;
+    if target_version >= version {
+        return Err(format!("The database is already at schema {}.", version));
+    }
+
+    let backup_settings: BackupSettings = settings::load(&app, settings::BACKUPS_KEY);
+    let backups_dir =
+        backups::resolve_backups_dir(&config::storage_location(&app)?, &backup_settings);
+    backups::create_labeled_backup(&conn, &backups_dir, &format!("pre-downgrade-v{}", version))?;
+
+    migrations::rollback_migrations(&mut conn, target_version)?;
+    drop(conn);
+
+    app.exit(0);
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use crate::models::search::{SearchHit, SearchNotesRequest};
+use crate::repository::{self, search};
+use tauri::{AppHandle, command};
+
+/** Searches note names and content.
+ *
+ * Returns hits ranked by relevance, each with a highlighted snippet,
+ * the node's ancestor path and its space.
+ */
+#[command]
+pub async fn search_notes(
+    app: AppHandle,
+    request: SearchNotesRequest,
+) -> Result<Vec<SearchHit>, String> {
+    let conn = repository::open_connection(&app)?;
+    search::search_notes(&conn, &request)
+}
@@ -0,0 +1,48 @@
+use super::nodes::remove_deleted_files;
+use crate::events;
+use crate::models::events::ChangeKind;
+use crate::models::spaces::{CreateSpaceRequest, Space};
+use crate::repository::{self, spaces};
+use tauri::{AppHandle, command};
+
+/** Lists all spaces in display order. */
+#[command]
+pub async fn list_spaces(app: AppHandle) -> Result<Vec<Space>, String> {
+    let conn = repository::open_connection(&app)?;
+    spaces::list_spaces(&conn)
+}
+
+/** Creates a new space after the last existing one. */
+#[command]
+pub async fn create_space(app: AppHandle, request: CreateSpaceRequest) -> Result<Space, String> {
+    let conn = repository::open_connection(&app)?;
+    let space = spaces::create_space(&conn, &request)?;
+
+    events::space_changed(&app, ChangeKind::Created, &space.id);
+    Ok(space)
+}
+
+/** Renames an existing space. */
+#[command]
+pub async fn rename_space(app: AppHandle, space_id: String, name: String) -> Result<Space, String> {
+    let conn = repository::open_connection(&app)?;
+    let space = spaces::rename_space(&conn, &space_id, &name)?;
+
+    events::space_changed(&app, ChangeKind::Updated, &space.id);
+    Ok(space)
+}
+
+/** Deletes a space and every node it contains.
+ *
+ * Attachment files no other note uses are deleted from disk once the
+ * rows are gone.
+ */
+#[command]
+pub async fn delete_space(app: AppHandle, space_id: String) -> Result<(), String> {
+    let mut conn = repository::open_connection(&app)?;
+    let deleted = spaces::delete_space(&mut conn, &space_id)?;
+
+    remove_deleted_files(&app, deleted);
+    events::space_changed(&app, ChangeKind::Deleted, &space_id);
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::models::nodes::Node;
+use crate::models::tags::{ListNodesByTagsRequest, NodeTag, TagCount};
+use crate::repository::{self, tags};
+use tauri::{AppHandle, command};
+
+/** Lists a node's inline and manually added tags. */
+#[command]
+pub async fn get_node_tags(app: AppHandle, node_id: String) -> Result<Vec<NodeTag>, String> {
+    let conn = repository::open_connection(&app)?;
+    tags::get_node_tags(&conn, &node_id)
+}
+
+/** Adds a tag to a node. */
+#[command]
+pub async fn add_tag(app: AppHandle, node_id: String, tag: String) -> Result<Vec<NodeTag>, String> {
+    let mut conn = repository::open_connection(&app)?;
+    tags::add_tag(&mut conn, &node_id, &tag)
+}
+
+/** Removes a manually added tag from a node. */
+#[command]
+pub async fn remove_tag(
+    app: AppHandle,
+    node_id: String,
+    tag: String,
+) -> Result<Vec<NodeTag>, String> {
+    let mut conn = repository::open_connection(&app)?;
+    tags::remove_tag(&mut conn, &node_id, &tag)
+}
+
+/** Lists tags with node counts, optionally limited to one space. */
+#[command]
+pub async fn list_tags(app: AppHandle, space_id: Option<String>) -> Result<Vec<TagCount>, String> {
+    let conn = repository::open_connection(&app)?;
+    tags::list_tags(&conn, space_id.as_deref())
+}
+
+/** Lists nodes carrying all or any of the requested tags. */
+#[command]
+pub async fn list_nodes_by_tags(
+    app: AppHandle,
+    request: ListNodesByTagsRequest,
+) -> Result<Vec<Node>, String> {
+    let conn = repository::open_connection(&app)?;
+    tags::list_nodes_by_tags(&conn, &request)
+}
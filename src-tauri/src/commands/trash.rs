@@ -0,0 +1,57 @@
+use super::nodes::remove_deleted_files;
+use crate::events;
+use crate::models::events::ChangeKind;
+use crate::models::nodes::{DeleteSubtreeResponse, Node};
+use crate::models::trash::TrashEntry;
+use crate::repository::{self, trash};
+use tauri::{AppHandle, command};
+
+/** Moves a node and its descendants to the trash. */
+#[command]
+pub async fn trash_node(app: AppHandle, node_id: String) -> Result<TrashEntry, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let entry = trash::trash_node(&mut conn, &node_id)?;
+
+    events::node_ids_changed(
+        &app,
+        ChangeKind::Trashed,
+        vec![entry.space_id.clone()],
+        std::slice::from_ref(&entry.id),
+    );
+    Ok(entry)
+}
+
+/** Restores a trashed node under its original parent, or the space root if the parent is gone. */
+#[command]
+pub async fn restore_node(app: AppHandle, node_id: String) -> Result<Node, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let node = trash::restore_node(&mut conn, &node_id)?;
+
+    events::nodes_changed(&app, ChangeKind::Restored, [&node]);
+    Ok(node)
+}
+
+/** Lists trash entries, optionally limited to one space. */
+#[command]
+pub async fn list_trash(
+    app: AppHandle,
+    space_id: Option<String>,
+) -> Result<Vec<TrashEntry>, String> {
+    let conn = repository::open_connection(&app)?;
+    trash::list_trash(&conn, space_id.as_deref())
+}
+
+/** Permanently deletes trashed nodes and their attachments.
+ *
+ * Empties the whole trash unless `older_than_days` is given.
+ */
+#[command]
+pub async fn empty_trash(
+    app: AppHandle,
+    older_than_days: Option<u32>,
+) -> Result<DeleteSubtreeResponse, String> {
+    let mut conn = repository::open_connection(&app)?;
+    let purged = trash::purge_trash(&mut conn, older_than_days)?;
+
+    Ok(remove_deleted_files(&app, purged))
+}
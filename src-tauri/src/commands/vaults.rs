@@ -0,0 +1,54 @@
+use crate::models::vaults::{OpenVaultRequest, Vault, VaultList};
+use crate::vaults;
+use tauri::{AppHandle, command};
+use tauri_plugin_dialog::{DialogExt, FilePath};
+
+/** Lists the default vault and the vaults opened before. */
+#[command]
+pub async fn list_vaults(app: AppHandle) -> Result<VaultList, String> {
+    vaults::list_vaults(&app)
+}
+
+/** Opens a folder as a vault and switches to it.
+ *
+ * Shows a folder picker when no path is given. Returns `None` if the
+ * picker was canceled.
+ */
+#[command]
+pub async fn open_vault(
+    app: AppHandle,
+    request: OpenVaultRequest,
+) -> Result<Option<Vault>, String> {
+    let path = match request.path {
+        Some(path) => path,
+        None => {
+            let Some(chosen) = app.dialog().file().blocking_pick_folder() else {
+                // User canceled the folder picker.
+                return Ok(None);
+            };
+
+            match chosen {
+                FilePath::Path(path) => path.to_string_lossy().to_string(),
+                FilePath::Url(url) => url
+                    .to_file_path()
+                    .map_err(|_| "Failed to resolve selected folder.".to_string())?
+                    .to_string_lossy()
+                    .to_string(),
+            }
+        }
+    };
+
+    vaults::open_vault(&app, &path, request.name.as_deref()).map(Some)
+}
+
+/** Switches to a vault from the list without restarting. */
+#[command]
+pub async fn switch_vault(app: AppHandle, vault_id: String) -> Result<Vault, String> {
+    vaults::switch_vault(&app, &vault_id)
+}
+
+/** Removes a vault from the list. Its folder is left untouched. */
+#[command]
+pub async fn forget_vault(app: AppHandle, vault_id: String) -> Result<VaultList, String> {
+    vaults::forget_vault(&app, &vault_id)
+}
@@ -0,0 +1,8 @@
+use crate::windows;
+use tauri::{AppHandle, command};
+
+/** Opens a note in a window of its own, or focuses the one already showing it. */
+#[command]
+pub async fn open_note_window(app: AppHandle, node_id: String) -> Result<(), String> {
+    windows::open_note_window(&app, &node_id).map(|_| ())
+}
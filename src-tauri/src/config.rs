@@ -1,12 +1,25 @@
+use crate::repository::Database;
 use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
 
 pub const DEV_DATABASE_FILE_NAME: &str = "unfold-dev.db";
 pub const PROD_DATABASE_FILE_NAME: &str = "unfold.db";
 
+// Storage layout constants
+pub const IMAGES_DIR_NAME: &str = "images";
+pub const VARIANTS_DIR_NAME: &str = "variants";
+pub const BACKUPS_DIR_NAME: &str = "backups";
+pub const QUARANTINE_DIR_NAME: &str = "quarantine";
+
 // Web import constants
 pub const WEB_IMPORT_USER_AGENT: &str = "UnfoldReaderImport/1.0 (+https://github.com/mathangik/unfold)";
 pub const WEB_IMPORT_MAX_RESPONSE_BYTES: usize = 2 * 1024 * 1024;
 
+// Search constants
+pub const SEARCH_DEFAULT_LIMIT: u32 = 50;
+pub const SEARCH_MAX_LIMIT: u32 = 200;
+pub const SEARCH_SNIPPET_TOKENS: u32 = 16;
+
 pub fn database_file_name() -> &'static str {
     if cfg!(debug_assertions) {
         DEV_DATABASE_FILE_NAME
@@ -19,6 +32,89 @@ pub fn database_path(base_dir: &Path) -> PathBuf {
     base_dir.join(database_file_name())
 }
 
-pub fn database_url(base_dir: &Path) -> String {
-    format!("sqlite:{}", database_path(base_dir).display())
+/** Where the notes database and the files that belong to it live.
+ *
+ * Everything that touches the disk resolves its paths from here so the
+ * database, images, backups and quarantine always share one root.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageLocation {
+    root: PathBuf,
+    shared: bool,
+}
+
+impl StorageLocation {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            shared: false,
+        }
+    }
+
+    /** A user-picked folder that may live on a synced or network drive. */
+    pub fn shared(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            shared: true,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        database_path(&self.root)
+    }
+
+    pub fn images_dir(&self) -> PathBuf {
+        self.root.join(IMAGES_DIR_NAME)
+    }
+
+    pub fn variants_dir(&self) -> PathBuf {
+        self.images_dir().join(VARIANTS_DIR_NAME)
+    }
+
+    pub fn backups_dir(&self) -> PathBuf {
+        self.root.join(BACKUPS_DIR_NAME)
+    }
+
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.root.join(QUARANTINE_DIR_NAME)
+    }
+}
+
+/** Resolves the storage location of the open vault, used by every command. */
+pub fn storage_location(app: &AppHandle) -> Result<StorageLocation, String> {
+    match app.try_state::<Database>() {
+        Some(database) => Ok(database.pool().location().clone()),
+        None => default_storage_location(app),
+    }
+}
+
+/** Resolves the location of the default vault: the app data directory. */
+pub fn default_storage_location(app: &AppHandle) -> Result<StorageLocation, String> {
+    let app_data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    Ok(StorageLocation::new(app_data_dir))
+}
+
+/** Returns the app local data directory when it differs from `location`.
+ *
+ * Image commands used to store their files and metadata there, so on
+ * platforms where the two directories differ it may hold data that still
+ * has to be merged into the canonical location.
+ */
+pub fn stray_storage_root(app: &AppHandle, location: &StorageLocation) -> Option<PathBuf> {
+    let local_data_dir = app.path().app_local_data_dir().ok()?;
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    (canonical(&local_data_dir) != canonical(location.root())).then_some(local_data_dir)
 }
@@ -0,0 +1,114 @@
+use crate::models::events::{
+    AttachmentChanged, ChangeKind, ChangedNode, NodeChanged, SpaceChanged,
+};
+use crate::models::nodes::Node;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+/** Emitted to every window with a `NodeChanged` after nodes are written. */
+pub const NODE_CHANGED_EVENT: &str = "node-changed";
+
+/** Emitted to every window with a `SpaceChanged` after a space is written. */
+pub const SPACE_CHANGED_EVENT: &str = "space-changed";
+
+/** Emitted to every window with an `AttachmentChanged` after attachments are written. */
+pub const ATTACHMENT_CHANGED_EVENT: &str = "attachment-changed";
+
+/** Broadcasts `payload`, logging failures.
+ *
+ * The change is already committed when this runs, so failing the command
+ * over an undelivered event would only mislead the caller.
+ */
+fn emit<T: Serialize + Clone>(app: &AppHandle, event: &str, payload: T) {
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/** Announces changes to `nodes`, reporting their current revisions. */
+pub fn nodes_changed<'a>(
+    app: &AppHandle,
+    kind: ChangeKind,
+    nodes: impl IntoIterator<Item = &'a Node>,
+) {
+    let mut space_ids: Vec<String> = Vec::new();
+    let mut changed = Vec::new();
+    for node in nodes {
+        if !space_ids.contains(&node.space_id) {
+            space_ids.push(node.space_id.clone());
+        }
+        changed.push(ChangedNode {
+            id: node.id.clone(),
+            revision: Some(node.revision),
+        });
+    }
+
+    if changed.is_empty() {
+        return;
+    }
+
+    emit(
+        app,
+        NODE_CHANGED_EVENT,
+        NodeChanged {
+            kind,
+            space_ids,
+            nodes: changed,
+        },
+    );
+}
+
+/** Announces changes to nodes known only by ID, e.g. after they were deleted. */
+pub fn node_ids_changed(app: &AppHandle, kind: ChangeKind, space_ids: Vec<String>, ids: &[String]) {
+    if ids.is_empty() {
+        return;
+    }
+
+    emit(
+        app,
+        NODE_CHANGED_EVENT,
+        NodeChanged {
+            kind,
+            space_ids,
+            nodes: ids
+                .iter()
+                .map(|id| ChangedNode {
+                    id: id.clone(),
+                    revision: None,
+                })
+                .collect(),
+        },
+    );
+}
+
+pub fn space_changed(app: &AppHandle, kind: ChangeKind, space_id: &str) {
+    emit(
+        app,
+        SPACE_CHANGED_EVENT,
+        SpaceChanged {
+            kind,
+            space_id: space_id.to_string(),
+        },
+    );
+}
+
+pub fn attachments_changed(
+    app: &AppHandle,
+    kind: ChangeKind,
+    node_id: Option<&str>,
+    attachment_ids: &[String],
+) {
+    if attachment_ids.is_empty() {
+        return;
+    }
+
+    emit(
+        app,
+        ATTACHMENT_CHANGED_EVENT,
+        AttachmentChanged {
+            kind,
+            node_id: node_id.map(str::to_string),
+            attachment_ids: attachment_ids.to_vec(),
+        },
+    );
+}
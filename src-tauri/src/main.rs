@@ -1,10 +1,19 @@
 use tauri::Manager;
 
+mod backups;
 mod commands;
 mod config;
+mod events;
 mod migrations;
 mod models;
+mod recovery;
+mod repository;
+mod settings;
+mod storage;
 mod utils;
+mod vaults;
+mod window_state;
+mod windows;
 
 fn main() {
     tauri::Builder::default()
@@ -17,68 +26,103 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_opener::init())
+        .on_window_event(windows::handle_window_event)
         .setup(|app| {
             let start = std::time::Instant::now();
+            app.manage(window_state::WindowStateTracker::load(app.handle()));
 
-            let migrations = migrations::get_migrations();
-            let app_data_dir = app
-                .path()
-                .app_data_dir()
-                .expect("failed to get app data dir");
-
-            // Create app data dir if it doesn't exist
-            std::fs::create_dir_all(&app_data_dir).ok();
-
-            let db_path = config::database_path(&app_data_dir);
-            let db_url = config::database_url(&app_data_dir);
+            let location = vaults::startup_location(app.handle())?;
 
             // Log the database path
-            eprintln!("Using database: {}", db_url);
-            println!("Database path: {}", db_path.display());
-
-            let plugin = tauri_plugin_sql::Builder::default()
-                .add_migrations(&db_url, migrations)
-                .build();
+            println!("Database path: {}", location.database_path().display());
 
-            app.handle().plugin(plugin)?;
-
-            // Grab both windows before moving them into the background thread.
-            let splash_window = app
-                .get_webview_window("splashscreen")
-                .expect("no splashscreen window");
-            let main_window = app
-                .get_webview_window("main")
-                .expect("no main window");
-            std::thread::spawn(move || {
-                const MIN_SPLASH_MS: u128 = 2700;
-                let elapsed_ms = start.elapsed().as_millis();
-                if elapsed_ms < MIN_SPLASH_MS {
-                    std::thread::sleep(std::time::Duration::from_millis(
-                        (MIN_SPLASH_MS - elapsed_ms) as u64,
-                    ));
+            // A database that fails to open or migrate sends the app into
+            // recovery mode instead of crashing.
+            let target = match vaults::open_pool(app.handle(), location.clone()) {
+                Ok(pool) => {
+                    app.manage(repository::Database::new(pool));
+                    backups::spawn_scheduler(app.handle().clone());
+                    windows::MAIN_WINDOW
+                }
+                Err(e) => {
+                    eprintln!("Failed to open database: {}", e);
+                    recovery::enter(app.handle(), location, e)?;
+                    windows::RECOVERY_WINDOW
                 }
-                splash_window
-                    .eval("document.body.classList.add('is-exiting')")
-                    .unwrap();
-                std::thread::sleep(std::time::Duration::from_millis(550));
-                main_window.maximize().unwrap();
-                main_window.show().unwrap();
-                main_window.set_focus().unwrap();
-                splash_window.close().unwrap();
-            });
+            };
+
+            windows::dismiss_splash(app.handle().clone(), start, target);
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            commands::list_spaces,
+            commands::create_space,
+            commands::rename_space,
+            commands::delete_space,
+            commands::list_nodes,
+            commands::get_node,
+            commands::create_node,
+            commands::update_node,
+            commands::save_node_content,
+            commands::move_node,
+            commands::duplicate_node,
+            commands::delete_node,
+            commands::delete_subtree,
+            commands::trash_node,
+            commands::restore_node,
+            commands::list_trash,
+            commands::empty_trash,
+            commands::search_notes,
+            commands::get_backlinks,
+            commands::get_outgoing_links,
+            commands::find_unresolved_links,
+            commands::get_node_tags,
+            commands::add_tag,
+            commands::remove_tag,
+            commands::list_tags,
+            commands::list_nodes_by_tags,
+            commands::list_node_revisions,
+            commands::get_node_revision,
+            commands::diff_node_revisions,
+            commands::restore_node_revision,
+            commands::create_backup,
+            commands::list_backups,
+            commands::verify_backup,
+            commands::restore_backup,
+            commands::list_vaults,
+            commands::open_vault,
+            commands::switch_vault,
+            commands::forget_vault,
+            commands::get_schema_status,
+            commands::downgrade_schema,
+            commands::get_recovery_status,
+            commands::recover_from_backup,
+            commands::open_read_only,
+            commands::export_raw_content,
+            commands::check_integrity,
+            commands::repair_integrity,
+            commands::upload_attachment,
+            commands::get_attachment,
+            commands::list_attachments,
+            commands::delete_attachment,
             commands::upload_image,
             commands::get_image,
+            commands::get_image_variant,
+            commands::list_images_with_location,
             commands::delete_image,
             commands::save_pdf_file,
             commands::save_image_file,
             commands::fetch_website_html,
             commands::open_external_url,
+            commands::open_note_window,
             commands::get_system_fonts,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                window_state::save(app);
+            }
+        });
 }
@@ -1,4 +1,25 @@
-use tauri_plugin_sql::{Migration, MigrationKind};
+use crate::backups;
+use crate::models::schema::{AppliedMigration, SchemaError};
+use crate::repository::{attachments, links, tags};
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+/** Rust code that populates existing rows for a migration. */
+pub type Backfill = fn(&Connection) -> Result<(), String>;
+
+/** A schema change applied once, in version order, by [`run_migrations`].
+ *
+ * `backfill` runs in the same transaction after `sql` for changes that
+ * need Rust code to populate existing rows. `down` reverts `sql` for
+ * [`rollback_migrations`].
+ */
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    pub backfill: Option<Backfill>,
+    pub down: &'static str,
+}
 
 pub fn get_migrations() -> Vec<Migration> {
     vec![
@@ -11,7 +32,8 @@ pub fn get_migrations() -> Vec<Migration> {
                 name TEXT NOT NULL,
                 sort_order INTEGER NOT NULL DEFAULT 0
             );",
-            kind: MigrationKind::Up,
+            backfill: None,
+            down: "DROP TABLE IF EXISTS spaces;",
         },
         // Create nodes table
         Migration {
@@ -31,7 +53,8 @@ pub fn get_migrations() -> Vec<Migration> {
             );
             CREATE INDEX IF NOT EXISTS idx_nodes_space_id ON nodes(space_id);
             CREATE INDEX IF NOT EXISTS idx_nodes_parent_id ON nodes(parent_id);",
-            kind: MigrationKind::Up,
+            backfill: None,
+            down: "DROP TABLE IF EXISTS nodes;",
         },
         // Create images table
         Migration {
@@ -48,14 +71,17 @@ pub fn get_migrations() -> Vec<Migration> {
             );
             CREATE INDEX IF NOT EXISTS idx_images_note_id ON images(note_id);
             CREATE INDEX IF NOT EXISTS idx_images_created_at ON images(created_at);",
-            kind: MigrationKind::Up,
+            backfill: None,
+            down: "DROP TABLE IF EXISTS images;",
         },
         // Insert default space
         Migration {
             version: 4,
             description: "insert_default_space",
             sql: "INSERT OR IGNORE INTO spaces (id, name, sort_order) VALUES ('default-space-mine', 'mine', 0);",
-            kind: MigrationKind::Up,
+            backfill: None,
+            // The default space may hold notes by now, so it is left in place.
+            down: "",
         },
         // Add node edit timestamps
         Migration {
@@ -84,7 +110,740 @@ pub fn get_migrations() -> Vec<Migration> {
                 SET updated_at = datetime('now')
                 WHERE id = NEW.id;
             END;",
-            kind: MigrationKind::Up,
+            backfill: None,
+            down: "DROP TRIGGER IF EXISTS nodes_touch_updated_at;
+            DROP TRIGGER IF EXISTS nodes_set_timestamps_on_insert;
+            ALTER TABLE nodes DROP COLUMN updated_at;
+            ALTER TABLE nodes DROP COLUMN created_at;",
+        },
+        // Full-text search index over node names and content
+        Migration {
+            version: 6,
+            description: "create_nodes_fts",
+            sql: "CREATE VIRTUAL TABLE IF NOT EXISTS nodes_fts USING fts5(
+                node_id UNINDEXED,
+                name,
+                body,
+                tokenize = 'unicode61 remove_diacritics 2',
+                prefix = '2 3'
+            );
+            INSERT INTO nodes_fts (node_id, name, body)
+            SELECT id, name, tiptap_text(content) FROM nodes;
+            CREATE TRIGGER IF NOT EXISTS nodes_fts_after_insert
+            AFTER INSERT ON nodes
+            FOR EACH ROW
+            BEGIN
+                INSERT INTO nodes_fts (node_id, name, body)
+                VALUES (NEW.id, NEW.name, tiptap_text(NEW.content));
+            END;
+            CREATE TRIGGER IF NOT EXISTS nodes_fts_after_update
+            AFTER UPDATE OF name, content ON nodes
+            FOR EACH ROW
+            BEGIN
+                DELETE FROM nodes_fts WHERE node_id = OLD.id;
+                INSERT INTO nodes_fts (node_id, name, body)
+                VALUES (NEW.id, NEW.name, tiptap_text(NEW.content));
+            END;
+            CREATE TRIGGER IF NOT EXISTS nodes_fts_after_delete
+            AFTER DELETE ON nodes
+            FOR EACH ROW
+            BEGIN
+                DELETE FROM nodes_fts WHERE node_id = OLD.id;
+            END;",
+            backfill: None,
+            down: "DROP TRIGGER IF EXISTS nodes_fts_after_delete;
+            DROP TRIGGER IF EXISTS nodes_fts_after_update;
+            DROP TRIGGER IF EXISTS nodes_fts_after_insert;
+            DROP TABLE IF EXISTS nodes_fts;",
+        },
+        // Create node revision history
+        Migration {
+            version: 7,
+            description: "create_node_revisions_table",
+            sql: "CREATE TABLE IF NOT EXISTS node_revisions (
+                id TEXT PRIMARY KEY NOT NULL,
+                node_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                content TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_node_revisions_node_id
+                ON node_revisions(node_id, updated_at);",
+            backfill: None,
+            down: "DROP TABLE IF EXISTS node_revisions;",
+        },
+        // Add soft delete for the trash bin
+        Migration {
+            version: 8,
+            description: "add_node_deleted_at",
+            sql: "ALTER TABLE nodes ADD COLUMN deleted_at TEXT;
+            ALTER TABLE nodes ADD COLUMN deleted_root_id TEXT;
+            CREATE INDEX IF NOT EXISTS idx_nodes_deleted_root_id ON nodes(deleted_root_id);",
+            backfill: None,
+            down: "DROP INDEX IF EXISTS idx_nodes_deleted_root_id;
+            ALTER TABLE nodes DROP COLUMN deleted_root_id;
+            ALTER TABLE nodes DROP COLUMN deleted_at;",
+        },
+        // Create the link index between notes
+        Migration {
+            version: 9,
+            description: "create_node_links_table",
+            sql: "CREATE TABLE IF NOT EXISTS node_links (
+                source_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                label TEXT NOT NULL,
+                href TEXT,
+                target_id TEXT,
+                target_name TEXT,
+                FOREIGN KEY (source_id) REFERENCES nodes(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_node_links_source_id ON node_links(source_id);
+            CREATE INDEX IF NOT EXISTS idx_node_links_target_id ON node_links(target_id);
+            CREATE INDEX IF NOT EXISTS idx_node_links_target_name
+                ON node_links(target_name COLLATE NOCASE);",
+            backfill: Some(links::rebuild_links),
+            down: "DROP TABLE IF EXISTS node_links;",
+        },
+        // Create tags tables
+        Migration {
+            version: 10,
+            description: "create_tags_tables",
+            sql: "CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS node_tags (
+                node_id TEXT NOT NULL,
+                tag_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                PRIMARY KEY (node_id, tag_id, source),
+                FOREIGN KEY (node_id) REFERENCES nodes(id) ON DELETE CASCADE,
+                FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+            );
+            CREATE INDEX IF NOT EXISTS idx_node_tags_tag_id ON node_tags(tag_id);",
+            backfill: Some(tags::rebuild_tags),
+            down: "DROP TABLE IF EXISTS node_tags;
+            DROP TABLE IF EXISTS tags;",
+        },
+        // Revision counter for detecting stale saves
+        Migration {
+            version: 11,
+            description: "add_node_revision",
+            sql: "ALTER TABLE nodes ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;
+            CREATE TRIGGER IF NOT EXISTS nodes_bump_revision
+            AFTER UPDATE OF name, content ON nodes
+            FOR EACH ROW
+            WHEN NEW.name IS NOT OLD.name OR NEW.content IS NOT OLD.content
+            BEGIN
+                UPDATE nodes
+                SET revision = OLD.revision + 1
+                WHERE id = NEW.id;
+            END;",
+            backfill: None,
+            down: "DROP TRIGGER IF EXISTS nodes_bump_revision;
+            ALTER TABLE nodes DROP COLUMN revision;",
+        },
+        // Move image rows into a table for files of any type
+        Migration {
+            version: 12,
+            description: "create_attachments_table",
+            sql: "CREATE TABLE IF NOT EXISTS attachments (
+                id TEXT PRIMARY KEY NOT NULL,
+                note_id TEXT NOT NULL,
+                kind TEXT NOT NULL DEFAULT 'file',
+                original_name TEXT NOT NULL,
+                extension TEXT NOT NULL DEFAULT '',
+                filename TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                mime_type TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            INSERT INTO attachments
+                (id, note_id, original_name, filename, file_path, size, mime_type, created_at)
+            SELECT id, note_id, filename, filename, file_path, CAST(size AS INTEGER), mime_type,
+                created_at
+            FROM images;
+            DROP TABLE images;
+            CREATE INDEX IF NOT EXISTS idx_attachments_note_id ON attachments(note_id);
+            CREATE INDEX IF NOT EXISTS idx_attachments_created_at ON attachments(created_at);",
+            backfill: Some(attachments::backfill_attachment_metadata),
+            down: "CREATE TABLE IF NOT EXISTS images (
+                id TEXT PRIMARY KEY NOT NULL,
+                note_id TEXT NOT NULL,
+                filename TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                size TEXT NOT NULL,
+                mime_type TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            INSERT INTO images (id, note_id, filename, file_path, size, mime_type, created_at)
+            SELECT id, note_id, filename, file_path, CAST(size AS TEXT), mime_type, created_at
+            FROM attachments;
+            DROP TABLE attachments;
+            CREATE INDEX IF NOT EXISTS idx_images_note_id ON images(note_id);
+            CREATE INDEX IF NOT EXISTS idx_images_created_at ON images(created_at);",
+        },
+        // Content-addressed attachment files; existing ones are hashed when the vault opens
+        Migration {
+            version: 13,
+            description: "create_attachment_blobs_table",
+            sql: "CREATE TABLE IF NOT EXISTS attachment_blobs (
+                hash TEXT PRIMARY KEY NOT NULL,
+                file_path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                ref_count INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL
+            );
+            ALTER TABLE attachments ADD COLUMN blob_hash TEXT;
+            CREATE INDEX IF NOT EXISTS idx_attachments_blob_hash ON attachments(blob_hash);
+            CREATE TRIGGER IF NOT EXISTS attachments_blob_ref_insert
+            AFTER INSERT ON attachments
+            FOR EACH ROW
+            WHEN NEW.blob_hash IS NOT NULL
+            BEGIN
+                UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+            END;
+            CREATE TRIGGER IF NOT EXISTS attachments_blob_ref_delete
+            AFTER DELETE ON attachments
+            FOR EACH ROW
+            WHEN OLD.blob_hash IS NOT NULL
+            BEGIN
+                UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+            END;
+            CREATE TRIGGER IF NOT EXISTS attachments_blob_ref_update
+            AFTER UPDATE OF blob_hash ON attachments
+            FOR EACH ROW
+            WHEN NEW.blob_hash IS NOT OLD.blob_hash
+            BEGIN
+                UPDATE attachment_blobs SET ref_count = ref_count - 1 WHERE hash = OLD.blob_hash;
+                UPDATE attachment_blobs SET ref_count = ref_count + 1 WHERE hash = NEW.blob_hash;
+            END;",
+            backfill: None,
+            down: "DROP TRIGGER IF EXISTS attachments_blob_ref_update;
+            DROP TRIGGER IF EXISTS attachments_blob_ref_delete;
+            DROP TRIGGER IF EXISTS attachments_blob_ref_insert;
+            DROP INDEX IF EXISTS idx_attachments_blob_hash;
+            ALTER TABLE attachments DROP COLUMN blob_hash;
+            DROP TABLE IF EXISTS attachment_blobs;",
+        },
+        // Image dimensions, and resized copies of images shared like their blobs
+        Migration {
+            version: 14,
+            description: "create_attachment_variants_table",
+            sql: "ALTER TABLE attachments ADD COLUMN width INTEGER;
+            ALTER TABLE attachments ADD COLUMN height INTEGER;
+            CREATE TABLE IF NOT EXISTS attachment_variants (
+                blob_hash TEXT NOT NULL REFERENCES attachment_blobs(hash) ON DELETE CASCADE,
+                size TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                width INTEGER NOT NULL,
+                height INTEGER NOT NULL,
+                mime_type TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (blob_hash, size)
+            );",
+            backfill: Some(attachments::backfill_image_dimensions),
+            down: "DROP TABLE IF EXISTS attachment_variants;
+            ALTER TABLE attachments DROP COLUMN height;
+            ALTER TABLE attachments DROP COLUMN width;",
+        },
+        // Links an image re-encoded on upload to the kept original
+        Migration {
+            version: 15,
+            description: "add_attachment_original_id",
+            sql: "ALTER TABLE attachments ADD COLUMN original_id TEXT;
+            CREATE INDEX IF NOT EXISTS idx_attachments_original_id ON attachments(original_id);
+            CREATE TRIGGER IF NOT EXISTS attachments_original_delete
+            AFTER DELETE ON attachments
+            FOR EACH ROW
+            BEGIN
+                UPDATE attachments SET original_id = NULL WHERE original_id = OLD.id;
+            END;",
+            backfill: None,
+            down: "DROP TRIGGER IF EXISTS attachments_original_delete;
+            DROP INDEX IF EXISTS idx_attachments_original_id;
+            ALTER TABLE attachments DROP COLUMN original_id;",
         },
     ]
 }
+
+/** Newest schema version this build knows how to apply. */
+pub fn latest_version() -> i64 {
+    get_migrations()
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn has_table(conn: &Connection, name: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [name],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to inspect schema: {}", e))
+}
+
+/** Returns the schema version the database is currently at.
+ *
+ * Applied versions are recorded in `schema_migrations`. Databases from
+ * before that ledger existed were tracked in `PRAGMA user_version`, or by
+ * tauri-plugin-sql in `_sqlx_migrations`, which are consulted as fallbacks.
+ */
+pub fn schema_version(conn: &Connection) -> Result<i64, String> {
+    if has_table(conn, "schema_migrations")? {
+        return conn
+            .query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read schema version: {}", e));
+    }
+
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    if version > 0 || !has_table(conn, "_sqlx_migrations")? {
+        return Ok(version);
+    }
+
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read legacy schema version: {}", e))
+}
+
+/** Returns the database's schema version, refusing versions this build doesn't know.
+ *
+ * A newer version means the database was last opened by a newer release;
+ * running older code against it could silently corrupt notes.
+ */
+pub fn check_version(conn: &Connection) -> Result<i64, SchemaError> {
+    let version = schema_version(conn)?;
+    let latest_version = latest_version();
+
+    if version > latest_version {
+        return Err(SchemaError::TooNew {
+            version,
+            latest_version,
+        });
+    }
+
+    Ok(version)
+}
+
+/** Creates the `schema_migrations` ledger, seeding it for databases migrated before it existed. */
+fn ensure_ledger(conn: &Connection, version: i64) -> Result<(), String> {
+    if has_table(conn, "schema_migrations")? {
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE schema_migrations (
+            version INTEGER PRIMARY KEY NOT NULL,
+            description TEXT NOT NULL,
+            applied_at TEXT
+        );",
+    )
+    .map_err(|e| format!("Failed to create migration ledger: {}", e))?;
+
+    // When these were applied is unknown, so `applied_at` stays NULL.
+    for migration in get_migrations()
+        .iter()
+        .filter(|migration| migration.version <= version)
+    {
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES (?1, ?2)",
+            params![migration.version, migration.description],
+        )
+        .map_err(|e| format!("Failed to seed migration ledger: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/** Lists the migrations recorded as applied, oldest first. */
+pub fn applied_migrations(conn: &Connection) -> Result<Vec<AppliedMigration>, String> {
+    if !has_table(conn, "schema_migrations")? {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT version, description, applied_at FROM schema_migrations ORDER BY version")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let migrations = stmt
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                description: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list migrations: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read migration: {}", e))?;
+
+    Ok(migrations)
+}
+
+/** Applies every pending migration, each in its own transaction.
+ *
+ * Refuses databases from a newer app version. When `backups_dir` is set
+ * and an existing database is about to change, a backup is taken first so
+ * a failed or faulty migration can be undone by restoring it. Returns the
+ * versions that were applied.
+ */
+pub fn run_migrations(
+    conn: &mut Connection,
+    backups_dir: Option<&Path>,
+) -> Result<Vec<i64>, String> {
+    let current = check_version(conn)?;
+    let pending: Vec<Migration> = get_migrations()
+        .into_iter()
+        .filter(|migration| migration.version > current)
+        .collect();
+
+    if let Some(backups_dir) = backups_dir
+        && current > 0
+        && !pending.is_empty()
+    {
+        backups::create_labeled_backup(conn, backups_dir, &format!("pre-migration-v{}", current))
+            .map_err(|e| format!("Failed to back up before migrating: {}", e))?;
+    }
+
+    ensure_ledger(conn, current)?;
+
+    let mut applied = Vec::new();
+    for migration in pending {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        tx.execute_batch(migration.sql).map_err(|e| {
+            format!(
+                "Failed to apply migration {} ({}): {}",
+                migration.version, migration.description, e
+            )
+        })?;
+
+        if let Some(backfill) = migration.backfill {
+            backfill(&tx).map_err(|e| {
+                format!(
+                    "Failed to backfill migration {} ({}): {}",
+                    migration.version, migration.description, e
+                )
+            })?;
+        }
+
+        tx.execute(
+            "INSERT INTO schema_migrations (version, description, applied_at)
+             VALUES (?1, ?2, datetime('now'))",
+            params![migration.version, migration.description],
+        )
+        .map_err(|e| format!("Failed to record migration: {}", e))?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| format!("Failed to record schema version: {}", e))?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit migration: {}", e))?;
+
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/** Reverts applied migrations newer than `target_version`, newest first.
+ *
+ * Each down migration runs in its own transaction together with its
+ * ledger update. Returns the versions that were reverted.
+ */
+pub fn rollback_migrations(conn: &mut Connection, target_version: i64) -> Result<Vec<i64>, String> {
+    let current = check_version(conn)?;
+    if target_version < 0 {
+        return Err(format!("Invalid schema version: {}", target_version));
+    }
+
+    ensure_ledger(conn, current)?;
+
+    let mut migrations: Vec<Migration> = get_migrations()
+        .into_iter()
+        .filter(|migration| migration.version > target_version && migration.version <= current)
+        .collect();
+    migrations.sort_by_key(|migration| std::cmp::Reverse(migration.version));
+
+    let mut reverted = Vec::new();
+    for migration in migrations {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        tx.execute_batch(migration.down).map_err(|e| {
+            format!(
+                "Failed to revert migration {} ({}): {}",
+                migration.version, migration.description, e
+            )
+        })?;
+
+        tx.execute(
+            "DELETE FROM schema_migrations WHERE version = ?1",
+            [migration.version],
+        )
+        .map_err(|e| format!("Failed to record migration: {}", e))?;
+        let version: i64 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read schema version: {}", e))?;
+        tx.pragma_update(None, "user_version", version)
+            .map_err(|e| format!("Failed to record schema version: {}", e))?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit migration: {}", e))?;
+
+        reverted.push(migration.version);
+    }
+
+    Ok(reverted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository;
+    use std::fs;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn open_memory() -> Connection {
+        repository::open_database(Path::new(":memory:"), false).unwrap()
+    }
+
+    /** Applies migrations up to `version` without the ledger, as a reference schema. */
+    fn migrate_without_ledger(conn: &Connection, version: i64) {
+        for migration in get_migrations()
+            .iter()
+            .filter(|migration| migration.version <= version)
+        {
+            conn.execute_batch(migration.sql).unwrap();
+            if let Some(backfill) = migration.backfill {
+                backfill(conn).unwrap();
+            }
+        }
+    }
+
+    /** Tables with their columns, and indexes and triggers with their SQL. */
+    fn schema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT type, name, sql FROM sqlite_master
+                 WHERE name NOT LIKE 'sqlite_%' AND name != 'schema_migrations'
+                 ORDER BY type, name",
+            )
+            .unwrap();
+        let objects = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let mut schema = Vec::new();
+        for (kind, name, sql) in objects {
+            if kind != "table" {
+                schema.push(format!("{} {}: {}", kind, name, sql.unwrap_or_default()));
+                continue;
+            }
+
+            // ALTER TABLE rewrites the stored CREATE statement, so tables
+            // are compared by their columns.
+            let mut stmt = conn
+                .prepare(
+                    "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1)",
+                )
+                .unwrap();
+            let columns = stmt
+                .query_map([&name], |row| {
+                    Ok(format!(
+                        "{} {} {} {:?} {}",
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, bool>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, i64>(4)?
+                    ))
+                })
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            schema.push(format!("table {}: {}", name, columns.join(", ")));
+        }
+
+        schema
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("unfold-migrations-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn applies_every_migration_to_an_empty_database() {
+        let mut conn = open_memory();
+
+        let applied = run_migrations(&mut conn, None).unwrap();
+
+        let expected: Vec<i64> = get_migrations()
+            .iter()
+            .map(|migration| migration.version)
+            .collect();
+        assert_eq!(applied, expected);
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        assert_eq!(applied_migrations(&conn).unwrap().len(), expected.len());
+        assert!(run_migrations(&mut conn, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn each_down_migration_restores_the_previous_schema() {
+        let mut conn = open_memory();
+        run_migrations(&mut conn, None).unwrap();
+
+        for version in (1..=latest_version()).rev() {
+            let reverted = rollback_migrations(&mut conn, version - 1).unwrap();
+            assert_eq!(reverted, vec![version]);
+            assert_eq!(schema_version(&conn).unwrap(), version - 1);
+
+            let reference = open_memory();
+            migrate_without_ledger(&reference, version - 1);
+            assert_eq!(
+                schema(&conn),
+                schema(&reference),
+                "down migration {} did not restore schema {}",
+                version,
+                version - 1
+            );
+        }
+
+        assert_eq!(
+            run_migrations(&mut conn, None).unwrap().len() as i64,
+            latest_version()
+        );
+    }
+
+    #[test]
+    fn refuses_a_database_from_a_newer_version() {
+        let mut conn = open_memory();
+        run_migrations(&mut conn, None).unwrap();
+        let newer = latest_version() + 1;
+        conn.execute(
+            "INSERT INTO schema_migrations (version, description) VALUES (?1, 'from_the_future')",
+            [newer],
+        )
+        .unwrap();
+
+        match check_version(&conn) {
+            Err(SchemaError::TooNew {
+                version,
+                latest_version: latest,
+            }) => {
+                assert_eq!(version, newer);
+                assert_eq!(latest, latest_version());
+            }
+            other => panic!("expected TooNew, got {:?}", other),
+        }
+        assert!(run_migrations(&mut conn, None).is_err());
+        assert!(rollback_migrations(&mut conn, 0).is_err());
+    }
+
+    #[test]
+    fn adopts_a_database_migrated_by_the_sql_plugin() {
+        let mut conn = open_memory();
+        migrate_without_ledger(&conn, 5);
+        conn.execute_batch(
+            "CREATE TABLE _sqlx_migrations (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                success BOOLEAN NOT NULL,
+                checksum BLOB NOT NULL,
+                execution_time BIGINT NOT NULL
+            );",
+        )
+        .unwrap();
+        for version in 1..=5 {
+            conn.execute(
+                "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                 VALUES (?1, 'legacy', 1, x'00', 0)",
+                [version],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(schema_version(&conn).unwrap(), 5);
+        let applied = run_migrations(&mut conn, None).unwrap();
+
+        assert_eq!(applied.first(), Some(&6));
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        let ledger = applied_migrations(&conn).unwrap();
+        assert!(
+            ledger
+                .iter()
+                .filter(|migration| migration.version <= 5)
+                .all(|migration| migration.applied_at.is_none())
+        );
+        assert!(
+            ledger
+                .iter()
+                .filter(|migration| migration.version > 5)
+                .all(|migration| migration.applied_at.is_some())
+        );
+    }
+
+    #[test]
+    fn backs_up_an_existing_database_before_migrating() {
+        let backups_dir = temp_dir();
+        let mut conn = open_memory();
+
+        // A new database has nothing worth backing up.
+        run_migrations(&mut conn, Some(&backups_dir)).unwrap();
+        assert_eq!(fs::read_dir(&backups_dir).unwrap().count(), 0);
+
+        rollback_migrations(&mut conn, 5).unwrap();
+        run_migrations(&mut conn, Some(&backups_dir)).unwrap();
+
+        let backups: Vec<String> = fs::read_dir(&backups_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].ends_with("pre-migration-v5"));
+
+        let backup = Connection::open(
+            backups_dir
+                .join(&backups[0])
+                .join(crate::config::database_file_name()),
+        )
+        .unwrap();
+        assert_eq!(schema_version(&backup).unwrap(), 5);
+
+        fs::remove_dir_all(&backups_dir).ok();
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+/** What an attachment holds, which decides its size limit. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Image,
+    Pdf,
+    Audio,
+    Video,
+    Archive,
+    /// Anything not recognized as one of the above.
+    File,
+}
+
+impl AttachmentKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AttachmentKind::Image => "image",
+            AttachmentKind::Pdf => "pdf",
+            AttachmentKind::Audio => "audio",
+            AttachmentKind::Video => "video",
+            AttachmentKind::Archive => "archive",
+            AttachmentKind::File => "file",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "image" => Some(AttachmentKind::Image),
+            "pdf" => Some(AttachmentKind::Pdf),
+            "audio" => Some(AttachmentKind::Audio),
+            "video" => Some(AttachmentKind::Video),
+            "archive" => Some(AttachmentKind::Archive),
+            "file" => Some(AttachmentKind::File),
+            _ => None,
+        }
+    }
+}
+
+/** Attachment upload limits, stored under the `attachments` settings key.
+ *
+ * Each field is the largest file of that kind accepted, in megabytes;
+ * `None` accepts files of any size.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentSettings {
+    pub max_image_mb: Option<u64>,
+    pub max_pdf_mb: Option<u64>,
+    pub max_audio_mb: Option<u64>,
+    pub max_video_mb: Option<u64>,
+    pub max_archive_mb: Option<u64>,
+    pub max_file_mb: Option<u64>,
+}
+
+impl Default for AttachmentSettings {
+    fn default() -> Self {
+        Self {
+            max_image_mb: Some(25),
+            max_pdf_mb: Some(100),
+            max_audio_mb: Some(200),
+            max_video_mb: Some(1024),
+            max_archive_mb: Some(500),
+            max_file_mb: Some(100),
+        }
+    }
+}
+
+impl AttachmentSettings {
+    /** Largest accepted file of `kind` in megabytes, if limited. */
+    pub fn max_mb(&self, kind: AttachmentKind) -> Option<u64> {
+        match kind {
+            AttachmentKind::Image => self.max_image_mb,
+            AttachmentKind::Pdf => self.max_pdf_mb,
+            AttachmentKind::Audio => self.max_audio_mb,
+            AttachmentKind::Video => self.max_video_mb,
+            AttachmentKind::Archive => self.max_archive_mb,
+            AttachmentKind::File => self.max_file_mb,
+        }
+    }
+}
+
+/** A file attached to a note.
+ *
+ * `original_name` and `extension` are the name the file was uploaded
+ * with; `filename` is the name it is stored under at `file_path`, which
+ * attachments with the same content share. `extension` is empty for
+ * files uploaded without one. `blob_hash` is the SHA-256 of the content,
+ * or `None` until a file from before content addressing is hashed.
+ * `width` and `height` are the pixel size of images that could be decoded.
+ * `original_id` points at the untouched upload kept alongside an image the
+ * upload policy re-encoded.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub note_id: String,
+    pub kind: AttachmentKind,
+    pub original_name: String,
+    pub extension: String,
+    pub filename: String,
+    pub file_path: String,
+    pub size: u64,
+    pub mime_type: String,
+    pub created_at: String,
+    pub blob_hash: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub original_id: Option<String>,
+}
+
+/** A stored file, shared by every attachment with the same content.
+ *
+ * `hash` is the SHA-256 of the content. `ref_count` is the number of
+ * attachment rows pointing at it and is kept by triggers on
+ * `attachments`; the file is removed once it drops to zero.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentBlob {
+    pub hash: String,
+    pub file_path: String,
+    pub size: u64,
+    pub ref_count: i64,
+}
+
+/** Resolution of an image attachment to load.
+ *
+ * `Thumbnail` fits within a small square for lists and previews;
+ * `Display` is capped at the width notes are shown at; `Original` is the
+ * uploaded file.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageVariantSize {
+    Thumbnail,
+    Display,
+    Original,
+}
+
+impl ImageVariantSize {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageVariantSize::Thumbnail => "thumbnail",
+            ImageVariantSize::Display => "display",
+            ImageVariantSize::Original => "original",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "thumbnail" => Some(ImageVariantSize::Thumbnail),
+            "display" => Some(ImageVariantSize::Display),
+            "original" => Some(ImageVariantSize::Original),
+            _ => None,
+        }
+    }
+}
+
+/** A file to load for an image attachment at a given size.
+ *
+ * Images already smaller than the requested size, and files that aren't
+ * decodable images, resolve to the original file.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageVariant {
+    pub attachment_id: String,
+    pub size: ImageVariantSize,
+    pub file_path: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub mime_type: String,
+}
+
+/** Request to upload a file of any type.
+ *
+ * Contains base64-encoded file data that will be decoded, checked against
+ * the size limit for its kind, stored on disk and registered in the
+ * database.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadAttachmentRequest {
+    pub note_id: String,
+    pub file_name: String,
+    pub base64_data: String,
+    pub mime_type: String,
+}
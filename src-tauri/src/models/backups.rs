@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/** Backup settings, stored under the `backups` settings key.
+ *
+ * - `folder`: where backups are written; defaults to `backups` in the
+ *   app data directory
+ * - `interval_minutes`: minimum time between automatic backups
+ * - `keep_hourly`, `keep_daily`, `keep_weekly`: how many of the most
+ *   recent hours, days and weeks keep their newest backup
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub folder: Option<String>,
+    pub interval_minutes: u32,
+    pub keep_hourly: u32,
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            folder: None,
+            interval_minutes: 60,
+            keep_hourly: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/** An attachment file bundled with a backup.
+ *
+ * Backups list these under `images`, the name they had before
+ * attachments other than images existed.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupImage {
+    pub id: String,
+    pub file_name: String,
+    pub size: u64,
+}
+
+/** Contents of a backup's `manifest.json`.
+ *
+ * `missing_images` lists attachment rows whose file was already gone when the
+ * backup was taken.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: String,
+    pub schema_version: i64,
+    pub database_file: String,
+    pub images: Vec<BackupImage>,
+    pub missing_images: Vec<String>,
+}
+
+/** A backup as listed to the frontend. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub id: String,
+    pub path: String,
+    pub created_at: String,
+    pub schema_version: i64,
+    pub image_count: usize,
+    pub size_bytes: u64,
+}
+
+/** Result of verifying a backup; `problems` is empty for a usable backup. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub id: String,
+    pub ok: bool,
+    pub problems: Vec<String>,
+}
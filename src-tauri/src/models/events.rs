@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/** What happened to the rows named in a change event. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Moved,
+    /** Moved to the trash along with its descendants. */
+    Trashed,
+    /** Restored from the trash along with its descendants. */
+    Restored,
+    Deleted,
+}
+
+/** A node named in a `node-changed` event.
+ *
+ * `revision` is the node's revision after the change, when it is known;
+ * deleted nodes have none.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedNode {
+    pub id: String,
+    pub revision: Option<i64>,
+}
+
+/** Payload of the `node-changed` event.
+ *
+ * `space_ids` lists every space whose tree is affected, i.e. both spaces
+ * when a node moves between them.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeChanged {
+    pub kind: ChangeKind,
+    pub space_ids: Vec<String>,
+    pub nodes: Vec<ChangedNode>,
+}
+
+/** Payload of the `space-changed` event. Deleting a space deletes its nodes too. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceChanged {
+    pub kind: ChangeKind,
+    pub space_id: String,
+}
+
+/** Payload of the `attachment-changed` event. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentChanged {
+    pub kind: ChangeKind,
+    pub node_id: Option<String>,
+    pub attachment_ids: Vec<String>,
+}
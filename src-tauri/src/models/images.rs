@@ -16,13 +16,64 @@ pub struct UploadImageRequest {
 
 /** Response after successfully uploading an image.
  *
- * Contains the unique attachment ID, file path, and size.
+ * Contains the unique attachment ID, file path, and stored size.
+ * `original_size` is the size of the uploaded file, which differs when
+ * the upload policy re-encoded it or metadata was stripped; `original_id`
+ * is the attachment holding the upload as it was before re-encoding, when
+ * the policy keeps it.
  */
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UploadImageResponse {
     pub id: String,
     pub path: String,
     pub size: u64,
+    pub original_size: u64,
+    pub original_id: Option<String>,
+}
+
+/** Format images are converted to by the upload policy.
+ *
+ * JPEG is the only lossy target: the WebP encoder available is lossless
+ * only, so it could not honour `jpeg_quality`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageUploadFormat {
+    /// Keep the uploaded format; the image is only re-encoded when resized or turned.
+    Original,
+    Jpeg,
+}
+
+/** Image upload policy, stored under the `image_uploads` settings key.
+ *
+ * When `enabled`, images passed to `upload_image` are downscaled so
+ * neither side exceeds `max_dimension` pixels, converted to `format`
+ * (JPEGs at `jpeg_quality`, 1-100) and, with `auto_orient`, turned
+ * upright according to their EXIF orientation. `keep_original` also
+ * stores the untouched upload as a separate attachment.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageUploadSettings {
+    pub enabled: bool,
+    pub max_dimension: Option<u32>,
+    pub format: ImageUploadFormat,
+    pub jpeg_quality: u8,
+    pub keep_original: bool,
+    pub auto_orient: bool,
+}
+
+impl Default for ImageUploadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_dimension: Some(2560),
+            format: ImageUploadFormat::Jpeg,
+            jpeg_quality: 82,
+            keep_original: false,
+            auto_orient: true,
+        }
+    }
 }
 
 /** Request to save a PDF file via the system's save dialog.
@@ -46,3 +97,36 @@ pub struct SaveImageRequest {
     pub source_url: Option<String>,
     pub attachment_id: Option<String>,
 }
+
+/** Which metadata to remove from an image file. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageMetadataPolicy {
+    /// Remove EXIF and XMP, keeping only the orientation needed to display the image.
+    StripAll,
+    /// Remove GPS data, along with XMP blocks that mention a location.
+    StripLocation,
+    Keep,
+}
+
+/** Image metadata settings, stored under the `image_metadata` settings key.
+ *
+ * `on_upload` applies to images passed to `upload_image` and `on_export`
+ * to images written by `save_image_file`. Only JPEG, PNG and WebP files
+ * are scrubbed; other formats are stored and saved as they are.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageMetadataSettings {
+    pub on_upload: ImageMetadataPolicy,
+    pub on_export: ImageMetadataPolicy,
+}
+
+impl Default for ImageMetadataSettings {
+    fn default() -> Self {
+        Self {
+            on_upload: ImageMetadataPolicy::StripLocation,
+            on_export: ImageMetadataPolicy::StripLocation,
+        }
+    }
+}
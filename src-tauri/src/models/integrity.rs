@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/** A node found by an integrity check. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeIssue {
+    pub id: String,
+    pub space_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+}
+
+/** An attachment row found by an integrity check. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentIssue {
+    pub id: String,
+    pub note_id: String,
+    pub file_path: String,
+}
+
+/** A row reported by `PRAGMA foreign_key_check`. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub row_id: Option<i64>,
+    pub parent_table: String,
+}
+
+/** Number of rows in a table that reference a missing node. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingRows {
+    pub table: String,
+    pub count: i64,
+}
+
+/** Result of `check_integrity`.
+ *
+ * - `sqlite_errors`: messages from `PRAGMA integrity_check`
+ * - `orphaned_nodes`: nodes whose parent does not exist
+ * - `cross_space_nodes`: nodes whose parent is in another space
+ * - `missing_space_nodes`: nodes whose space does not exist
+ * - `cyclic_nodes`: nodes whose parent chain loops back on itself
+ * - `dangling_attachments`: attachment rows whose node does not exist
+ * - `missing_files`: attachment rows whose file is gone
+ * - `dangling_rows`: revision, link and tag rows per table whose node
+ *   does not exist
+ * - `stray_files`: files in the images directory with no attachment row
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub healthy: bool,
+    pub sqlite_errors: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    pub orphaned_nodes: Vec<NodeIssue>,
+    pub cross_space_nodes: Vec<NodeIssue>,
+    pub missing_space_nodes: Vec<NodeIssue>,
+    pub cyclic_nodes: Vec<NodeIssue>,
+    pub dangling_attachments: Vec<AttachmentIssue>,
+    pub missing_files: Vec<AttachmentIssue>,
+    pub dangling_rows: Vec<DanglingRows>,
+    pub stray_files: Vec<String>,
+}
+
+/** Result of `repair_integrity`.
+ *
+ * `report` is a fresh check taken after the repair, listing anything
+ * that could not be fixed automatically.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityRepair {
+    pub reparented_node_ids: Vec<String>,
+    pub recovered_space_id: Option<String>,
+    pub deleted_attachment_ids: Vec<String>,
+    pub deleted_row_count: i64,
+    pub quarantined_files: Vec<String>,
+    pub quarantine_dir: Option<String>,
+    pub report: IntegrityReport,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+/** How a link between notes was written. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    /// A link mark whose href points at another note.
+    Link,
+    /// A `[[Note Name]]` reference in the note text.
+    Wiki,
+}
+
+impl LinkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::Link => "link",
+            LinkKind::Wiki => "wiki",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "link" => Some(LinkKind::Link),
+            "wiki" => Some(LinkKind::Wiki),
+            _ => None,
+        }
+    }
+}
+
+/** A link from one note to another.
+ *
+ * `target_id` is the note the link currently resolves to, or `None`
+ * when the target does not exist or is in the trash. Wiki links keep the
+ * referenced name in `target_name`.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeLink {
+    pub source_id: String,
+    pub source_name: String,
+    pub kind: LinkKind,
+    pub label: String,
+    pub href: Option<String>,
+    pub target_id: Option<String>,
+    pub target_name: Option<String>,
+}
@@ -1,5 +1,19 @@
+pub mod attachments;
+pub mod backups;
+pub mod events;
 pub mod images;
+pub mod integrity;
+pub mod links;
+pub mod nodes;
+pub mod recovery;
+pub mod revisions;
+pub mod schema;
+pub mod search;
+pub mod spaces;
+pub mod tags;
+pub mod trash;
+pub mod vaults;
 pub mod web_import;
+pub mod windows;
 
 pub use web_import::WebsiteHtmlResponse;
-
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/** A page in a space's tree.
+ *
+ * `content` holds the serialized TipTap JSON document. `deleted_at` is
+ * set while the node is in the trash. `revision` goes up by one whenever
+ * the name or content changes.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub id: String,
+    pub space_id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub content: Option<String>,
+    pub is_open: bool,
+    pub is_pinned: bool,
+    pub sort_order: i64,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub deleted_at: Option<String>,
+    pub revision: i64,
+}
+
+/** Request to create a new node.
+ *
+ * The ID may be supplied by the caller (for optimistic UI updates);
+ * a UUID is generated when it is omitted. The node is appended after
+ * its last sibling.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateNodeRequest {
+    pub id: Option<String>,
+    pub space_id: String,
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    pub content: Option<String>,
+    #[serde(default)]
+    pub is_open: bool,
+    #[serde(default)]
+    pub is_pinned: bool,
+}
+
+/** Partial update of a node's editable fields.
+ *
+ * Fields left as `None` are not modified. When `rewrite_links` is set
+ * and the name changes, `[[Old Name]]` references in other notes are
+ * rewritten to the new name.
+ */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateNodeRequest {
+    pub id: String,
+    pub name: Option<String>,
+    pub content: Option<String>,
+    pub is_open: Option<bool>,
+    pub is_pinned: Option<bool>,
+    #[serde(default)]
+    pub rewrite_links: bool,
+}
+
+/** Request to move a node under a new parent.
+ *
+ * A `None` parent moves the node to the space root. A `None` space keeps
+ * the node in its current space. A `None` position appends it after the
+ * last sibling.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveNodeRequest {
+    pub node_id: String,
+    pub new_parent_id: Option<String>,
+    #[serde(default)]
+    pub new_space_id: Option<String>,
+    pub position: Option<i64>,
+}
+
+/** Summary of a permanent deletion by `delete_subtree` or `empty_trash`.
+ *
+ * Lists every deleted node and attachment. Files that could not be
+ * removed from disk are reported in `failed_files` rather than failing
+ * the whole operation, since the database rows are already gone.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteSubtreeResponse {
+    pub node_ids: Vec<String>,
+    pub attachment_ids: Vec<String>,
+    pub removed_files: Vec<String>,
+    pub failed_files: Vec<String>,
+}
+
+/** Error returned by `save_node_content`.
+ *
+ * `Conflict` means the node changed since the revision the caller edited;
+ * `current` is the stored copy, so the UI can merge or overwrite.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SaveNodeError {
+    Conflict { current: Box<Node> },
+    Failed { message: String },
+}
+
+impl From<String> for SaveNodeError {
+    fn from(message: String) -> Self {
+        SaveNodeError::Failed { message }
+    }
+}
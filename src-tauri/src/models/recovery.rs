@@ -0,0 +1,15 @@
+use super::backups::BackupInfo;
+use serde::{Deserialize, Serialize};
+
+/** Why the database could not be opened at startup and what can be done about it. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryStatus {
+    pub error: String,
+    pub database_path: String,
+    /** File the failure details were written to, for bug reports. */
+    pub log_path: Option<String>,
+    /** Newest backup that passed verification, offered for restoring. */
+    pub latest_backup: Option<BackupInfo>,
+    /** Set once the user chose to continue with a read-only database. */
+    pub read_only: bool,
+}
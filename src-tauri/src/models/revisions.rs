@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/** Revision history settings, stored under the `revisions` settings key.
+ *
+ * - `coalesce_seconds`: saves within this window of the latest revision
+ *   update it instead of creating a new one
+ * - `keep_last`: maximum number of revisions kept per node
+ * - `daily_after_days`: revisions older than this are thinned to the
+ *   last one of each day
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RevisionSettings {
+    pub coalesce_seconds: u32,
+    pub keep_last: Option<u32>,
+    pub daily_after_days: Option<u32>,
+}
+
+impl Default for RevisionSettings {
+    fn default() -> Self {
+        Self {
+            coalesce_seconds: 300,
+            keep_last: Some(100),
+            daily_after_days: Some(7),
+        }
+    }
+}
+
+/** A stored revision without its content, for history listings. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeRevisionSummary {
+    pub id: String,
+    pub node_id: String,
+    pub name: String,
+    pub size: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/** A full snapshot of a node's name and content. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeRevision {
+    pub id: String,
+    pub node_id: String,
+    pub name: String,
+    pub content: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/** A single entry of a block-level diff between two TipTap documents. */
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BlockDiff {
+    Unchanged { block: Value },
+    Added { block: Value },
+    Removed { block: Value },
+    Modified { before: Value, after: Value },
+}
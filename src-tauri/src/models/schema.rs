@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+/** A migration recorded in the `schema_migrations` ledger. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    /** `None` for migrations applied before the ledger existed. */
+    pub applied_at: Option<String>,
+}
+
+/** Schema version of the open database and what this build supports. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    pub version: i64,
+    pub latest_version: i64,
+    pub migrations: Vec<AppliedMigration>,
+}
+
+/** Error returned when this build can't use a database as it is.
+ *
+ * `TooNew` means the database was last opened by a newer release, whose
+ * schema this build doesn't know.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaError {
+    TooNew { version: i64, latest_version: i64 },
+    Failed { message: String },
+}
+
+impl From<String> for SchemaError {
+    fn from(message: String) -> Self {
+        SchemaError::Failed { message }
+    }
+}
+
+impl From<SchemaError> for String {
+    fn from(error: SchemaError) -> Self {
+        match error {
+            SchemaError::TooNew {
+                version,
+                latest_version,
+            } => format!(
+                "This database was created by a newer version of Unfold (schema {}, this version supports up to {}). Update Unfold to open it.",
+                version, latest_version
+            ),
+            SchemaError::Failed { message } => message,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/** Request to search note names and content.
+ *
+ * `query` accepts bare words (all must match), `"quoted phrases"` and
+ * `prefix*` terms. Results are restricted to `space_id` when provided.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchNotesRequest {
+    pub query: String,
+    pub space_id: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/** A piece of a search snippet; `highlighted` marks matched terms. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnippetSegment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+/** A single ranked search result.
+ *
+ * `path` holds the names of the node's ancestors, root first.
+ * Higher `score` means a better match.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub node_id: String,
+    pub space_id: String,
+    pub space_name: String,
+    pub name: String,
+    pub path: Vec<String>,
+    pub snippet: Vec<SnippetSegment>,
+    pub score: f64,
+}
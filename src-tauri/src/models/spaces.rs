@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/** A top-level space that owns a tree of nodes. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Space {
+    pub id: String,
+    pub name: String,
+    pub sort_order: i64,
+}
+
+/** Request to create a new space.
+ *
+ * The ID may be supplied by the caller (for optimistic UI updates);
+ * a UUID is generated when it is omitted. Sort order is always
+ * assigned by the backend.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSpaceRequest {
+    pub id: Option<String>,
+    pub name: String,
+}
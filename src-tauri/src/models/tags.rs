@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/** A tag on a node and how it was applied.
+ *
+ * - `inline`: written as `#tag` in the note text
+ * - `manual`: added explicitly with `add_tag`
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeTag {
+    pub name: String,
+    pub inline: bool,
+    pub manual: bool,
+}
+
+/** A tag with usage counts, for the tag browser.
+ *
+ * `node_count` counts nodes with exactly this tag; `total_count` also
+ * includes nodes tagged with a descendant such as `project/alpha` for
+ * `project`.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub name: String,
+    pub parent: Option<String>,
+    pub node_count: i64,
+    pub total_count: i64,
+}
+
+/** Whether nodes must carry every requested tag or any of them. */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    All,
+    #[default]
+    Any,
+}
+
+/** Request to list nodes by tag.
+ *
+ * A tag also matches its descendants, so `project` matches nodes tagged
+ * `project/alpha`.
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListNodesByTagsRequest {
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub mode: TagMatch,
+    pub space_id: Option<String>,
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/** Trash settings, stored under the `trash` settings key.
+ *
+ * Trashed nodes older than `purge_after_days` are permanently deleted
+ * at startup. `None` disables automatic purging.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashSettings {
+    pub purge_after_days: Option<u32>,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            purge_after_days: Some(30),
+        }
+    }
+}
+
+/** A node that was moved to the trash, with the descendants trashed alongside it. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub id: String,
+    pub space_id: String,
+    pub space_name: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub deleted_at: String,
+    pub descendant_count: i64,
+}
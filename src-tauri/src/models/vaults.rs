@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/** A folder holding a notes database and its attachments. */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vault {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub last_opened_at: Option<String>,
+}
+
+/** Vaults the user has opened, stored under the `vaults` settings key.
+ *
+ * The default vault in the app data directory is implied and never
+ * stored. `active_id` is `None` while the default vault is open.
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultRegistry {
+    pub active_id: Option<String>,
+    pub vaults: Vec<Vault>,
+}
+
+/** Known vaults, most recently opened first, with the open one marked. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultList {
+    pub active_id: String,
+    pub vaults: Vec<Vault>,
+}
+
+/** Opens a folder as a vault. The folder picker is shown when `path` is omitted. */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenVaultRequest {
+    pub path: Option<String>,
+    pub name: Option<String>,
+}
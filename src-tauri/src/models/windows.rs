@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/** Notes open in their own windows, stored under the `note_windows` settings key.
+ *
+ * `node_ids` are keyed by the storage root of the vault the notes belong
+ * to, and only the open vault's are reopened at launch. IDs of notes that
+ * were deleted or trashed in the meantime are dropped.
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteWindows {
+    pub node_ids: HashMap<String, Vec<String>>,
+}
+
+/** Where a window was last placed, in physical pixels.
+ *
+ * `x`, `y`, `width` and `height` are the bounds the window had when it
+ * was last neither maximized nor fullscreen, so un-maximizing after a
+ * restore returns to them. `monitor` names the monitor it was on.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub monitor: Option<String>,
+}
+
+/** Geometry of the main and note windows by window label, stored under the `window_state` settings key. */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowStates {
+    pub windows: HashMap<String, WindowGeometry>,
+}
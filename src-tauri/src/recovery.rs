@@ -0,0 +1,301 @@
+use crate::backups;
+use crate::config::StorageLocation;
+use crate::migrations;
+use crate::models::backups::{BackupInfo, BackupSettings};
+use crate::models::recovery::RecoveryStatus;
+use crate::repository::{self, Database, DbPool};
+use crate::settings;
+use crate::utils::tiptap;
+use crate::vaults;
+use crate::windows;
+use serde_json::{Value, json};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, WindowEvent};
+
+/** File in the app log directory that startup failures are appended to. */
+pub const FAILURE_LOG_FILE: &str = "startup-failures.log";
+
+/** Recovery mode state, managed when the database failed to open at startup. */
+pub struct Recovery {
+    location: StorageLocation,
+    status: Mutex<RecoveryStatus>,
+    resolved: AtomicBool,
+}
+
+impl Recovery {
+    pub fn status(&self) -> RecoveryStatus {
+        match self.status.lock() {
+            Ok(status) => status.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+fn backups_dir(app: &AppHandle, location: &StorageLocation) -> PathBuf {
+    let settings: BackupSettings = settings::load(app, settings::BACKUPS_KEY);
+    backups::resolve_backups_dir(location, &settings)
+}
+
+/** Enters recovery mode after the database at `location` failed to open.
+ *
+ * The failure is logged to a file, and a recovery window is prepared that
+ * offers to restore the latest backup, open the database read-only or
+ * export its raw content. Closing that window quits the app.
+ */
+pub fn enter(app: &AppHandle, location: StorageLocation, error: String) -> Result<(), String> {
+    let log_path = match log_failure(app, &location, &error) {
+        Ok(path) => Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            eprintln!("Failed to write the startup failure log: {}", e);
+            None
+        }
+    };
+
+    let latest_backup = backups::latest_verified_backup(&backups_dir(app, &location))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to look for backups: {}", e);
+            None
+        });
+
+    app.manage(Recovery {
+        status: Mutex::new(RecoveryStatus {
+            error,
+            database_path: location.database_path().to_string_lossy().to_string(),
+            log_path,
+            latest_backup,
+            read_only: false,
+        }),
+        location,
+        resolved: AtomicBool::new(false),
+    });
+
+    let window = windows::create_recovery_window(app)?;
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        let resolved = handle
+            .try_state::<Recovery>()
+            .is_some_and(|recovery| recovery.resolved.load(Ordering::SeqCst));
+        if matches!(event, WindowEvent::Destroyed) && !resolved {
+            handle.exit(0);
+        }
+    });
+
+    Ok(())
+}
+
+/** Appends the failure details to the startup failure log and returns its path. */
+fn log_failure(
+    app: &AppHandle,
+    location: &StorageLocation,
+    error: &str,
+) -> Result<PathBuf, String> {
+    let log_dir = app
+        .path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to get log directory: {}", e))?;
+    fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {}", e))?;
+
+    let database_path = location.database_path();
+    let (schema, integrity) = match repository::open_database_read_only(&database_path) {
+        Ok(conn) => (
+            migrations::schema_version(&conn)
+                .map(|version| version.to_string())
+                .unwrap_or_else(|e| e),
+            conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0))
+                .unwrap_or_else(|e| e.to_string()),
+        ),
+        Err(e) => (e.clone(), e),
+    };
+
+    let entry = format!(
+        "[{}] Unfold {} on {} {}\nDatabase: {}\nSchema version: {} (this version supports up to {})\nIntegrity check: {}\nError: {}\n\n",
+        backups::timestamp_id(),
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        database_path.display(),
+        schema,
+        migrations::latest_version(),
+        integrity,
+        error
+    );
+
+    let path = log_dir.join(FAILURE_LOG_FILE);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(entry.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(path)
+}
+
+fn recovery(app: &AppHandle) -> Result<tauri::State<'_, Recovery>, String> {
+    app.try_state::<Recovery>()
+        .filter(|recovery| !recovery.resolved.load(Ordering::SeqCst))
+        .ok_or_else(|| "The app is not in recovery mode.".to_string())
+}
+
+/** Leaves the recovery window for the main window, now backed by `pool`. */
+fn finish(app: &AppHandle, recovery: &Recovery, pool: Arc<DbPool>) {
+    match app.try_state::<Database>() {
+        Some(database) => database.replace(pool),
+        None => {
+            app.manage(Database::new(pool));
+        }
+    }
+
+    recovery.resolved.store(true, Ordering::SeqCst);
+    if let Some(window) = app.get_webview_window(windows::MAIN_WINDOW) {
+        // The main window loaded while no database was open; start it over.
+        if let Err(e) = window.eval("window.location.reload()") {
+            eprintln!("Failed to reload the main window: {}", e);
+        }
+    }
+    windows::show_main_window(app);
+    if let Some(window) = app.get_webview_window(windows::RECOVERY_WINDOW)
+        && let Err(e) = window.close()
+    {
+        eprintln!("Failed to close the recovery window: {}", e);
+    }
+}
+
+/** Database file and its journals, which move together. */
+fn database_files(path: &Path) -> Vec<PathBuf> {
+    ["", "-wal", "-shm", "-journal"]
+        .iter()
+        .map(|suffix| PathBuf::from(format!("{}{}", path.display(), suffix)))
+        .collect()
+}
+
+/** Restores the newest verified backup and starts normally.
+ *
+ * The broken database is kept next to the new one with a `.broken-<time>`
+ * suffix, and put back if the restore fails.
+ */
+pub fn recover_from_backup(app: &AppHandle) -> Result<BackupInfo, String> {
+    let recovery = recovery(app)?;
+    let location = recovery.location.clone();
+    let backups_dir = backups_dir(app, &location);
+    let backup = backups::latest_verified_backup(&backups_dir)?
+        .ok_or_else(|| "No backup that passes verification was found.".to_string())?;
+
+    let database_path = location.database_path();
+    let suffix = format!(".broken-{}", backups::timestamp_id());
+    let mut set_aside = Vec::new();
+    for path in database_files(&database_path) {
+        if path.exists() {
+            let target = PathBuf::from(format!("{}{}", path.display(), suffix));
+            fs::rename(&path, &target)
+                .map_err(|e| format!("Failed to move the broken database aside: {}", e))?;
+            set_aside.push((path, target));
+        }
+    }
+
+    let restored = repository::open_database(&database_path, !location.is_shared())
+        .and_then(|mut conn| {
+            backups::restore_backup(&mut conn, &backups_dir, &backup.id, &location.images_dir())
+        })
+        .and_then(|()| vaults::open_pool(app, location));
+
+    let pool = match restored {
+        Ok(pool) => pool,
+        Err(e) => {
+            for path in database_files(&database_path) {
+                fs::remove_file(path).ok();
+            }
+            for (path, target) in &set_aside {
+                fs::rename(target, path).ok();
+            }
+            return Err(e);
+        }
+    };
+
+    finish(app, &recovery, pool);
+    backups::spawn_scheduler(app.clone());
+
+    Ok(backup)
+}
+
+/** Opens the database without migrating or writing to it and starts the app.
+ *
+ * Whatever can still be read is shown; every change fails until the
+ * database is repaired or restored.
+ */
+pub fn open_read_only(app: &AppHandle) -> Result<(), String> {
+    let recovery = recovery(app)?;
+    let pool = DbPool::open_read_only(recovery.location.clone())?;
+
+    if let Ok(mut status) = recovery.status.lock() {
+        status.read_only = true;
+    }
+    finish(app, &recovery, pool);
+
+    Ok(())
+}
+
+/** Writes every space and note that can still be read to a JSON file at `path`.
+ *
+ * Rows that fail to read are listed under `errors` instead of aborting
+ * the export, so as much as possible is salvaged from a damaged file.
+ */
+pub fn export_raw_content(app: &AppHandle, path: &Path) -> Result<(), String> {
+    let recovery = recovery(app)?;
+    let database_path = recovery.location.database_path();
+    let conn = repository::open_database_read_only(&database_path)?;
+
+    let mut errors: Vec<String> = Vec::new();
+    let mut read_rows = |sql: &str, to_json: fn(&rusqlite::Row) -> rusqlite::Result<Value>| {
+        let mut rows = Vec::new();
+        match conn.prepare(sql) {
+            Ok(mut stmt) => match stmt.query_map([], to_json) {
+                Ok(results) => {
+                    for result in results {
+                        match result {
+                            Ok(row) => rows.push(row),
+                            Err(e) => errors.push(e.to_string()),
+                        }
+                    }
+                }
+                Err(e) => errors.push(e.to_string()),
+            },
+            Err(e) => errors.push(e.to_string()),
+        }
+        rows
+    };
+
+    let spaces = read_rows("SELECT id, name FROM spaces", |row| {
+        Ok(json!({ "id": row.get::<_, String>(0)?, "name": row.get::<_, String>(1)? }))
+    });
+    let nodes = read_rows(
+        "SELECT id, space_id, parent_id, name, content FROM nodes",
+        |row| {
+            let content: Option<String> = row.get(4)?;
+            Ok(json!({
+                "id": row.get::<_, String>(0)?,
+                "space_id": row.get::<_, String>(1)?,
+                "parent_id": row.get::<_, Option<String>>(2)?,
+                "name": row.get::<_, String>(3)?,
+                "text": content.as_deref().map(tiptap::extract_text),
+                "content": content,
+            }))
+        },
+    );
+
+    let export = json!({
+        "exported_at": backups::timestamp_id(),
+        "database_path": database_path.to_string_lossy(),
+        "spaces": spaces,
+        "nodes": nodes,
+        "errors": errors,
+    });
+    let bytes = serde_json::to_vec_pretty(&export)
+        .map_err(|e| format!("Failed to serialize export: {}", e))?;
+
+    fs::write(path, bytes).map_err(|e| format!("Failed to write export: {}", e))
+}
@@ -0,0 +1,892 @@
+use crate::models::attachments::{
+    Attachment, AttachmentBlob, AttachmentKind, ImageVariant, ImageVariantSize,
+};
+use crate::utils::{self, tiptap};
+use rusqlite::{Connection, OptionalExtension, Row, TransactionBehavior, params};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub(crate) const ATTACHMENT_COLUMNS: &str = "id, note_id, kind, original_name, extension, filename, \
+                                  file_path, size, mime_type, created_at, blob_hash, width, \
+                                  height, original_id";
+
+pub(crate) fn attachment_from_row(row: &Row) -> rusqlite::Result<Attachment> {
+    let kind: String = row.get("kind")?;
+
+    Ok(Attachment {
+        id: row.get("id")?,
+        note_id: row.get("note_id")?,
+        kind: AttachmentKind::parse(&kind).unwrap_or(AttachmentKind::File),
+        original_name: row.get("original_name")?,
+        extension: row.get("extension")?,
+        filename: row.get("filename")?,
+        file_path: row.get("file_path")?,
+        size: row.get::<_, i64>("size")?.max(0) as u64,
+        mime_type: row.get("mime_type")?,
+        created_at: row.get("created_at")?,
+        blob_hash: row.get("blob_hash")?,
+        width: row.get("width")?,
+        height: row.get("height")?,
+        original_id: row.get("original_id")?,
+    })
+}
+
+fn blob_from_row(row: &Row) -> rusqlite::Result<AttachmentBlob> {
+    Ok(AttachmentBlob {
+        hash: row.get("hash")?,
+        file_path: row.get("file_path")?,
+        size: row.get::<_, i64>("size")?.max(0) as u64,
+        ref_count: row.get("ref_count")?,
+    })
+}
+
+/** Usual extension for `mime_type`, for files uploaded without one. */
+pub fn extension_from_mime(mime_type: &str) -> Option<&'static str> {
+    let extension = match mime_type {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "application/pdf" => "pdf",
+        "audio/mpeg" => "mp3",
+        "audio/wav" | "audio/x-wav" => "wav",
+        "audio/ogg" => "ogg",
+        "audio/mp4" | "audio/x-m4a" => "m4a",
+        "audio/flac" => "flac",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        "video/quicktime" => "mov",
+        "application/zip" => "zip",
+        "application/gzip" => "gz",
+        "application/x-tar" => "tar",
+        "application/x-7z-compressed" => "7z",
+        "application/vnd.rar" | "application/x-rar-compressed" => "rar",
+        "text/plain" => "txt",
+        _ => return None,
+    };
+
+    Some(extension)
+}
+
+const ARCHIVE_EXTENSIONS: [&str; 8] = ["zip", "gz", "tgz", "tar", "7z", "rar", "bz2", "xz"];
+
+/** Sorts a file into a kind by its MIME type, or by its extension when the type is unspecific. */
+pub fn classify(mime_type: &str, extension: &str) -> AttachmentKind {
+    let mime_type = mime_type.to_ascii_lowercase();
+    let extension = extension.to_ascii_lowercase();
+
+    if mime_type.starts_with("image/") {
+        AttachmentKind::Image
+    } else if mime_type.starts_with("audio/") {
+        AttachmentKind::Audio
+    } else if mime_type.starts_with("video/") {
+        AttachmentKind::Video
+    } else if mime_type == "application/pdf" || extension == "pdf" {
+        AttachmentKind::Pdf
+    } else if mime_type.contains("zip")
+        || mime_type.contains("compressed")
+        || mime_type.contains("rar")
+        || mime_type == "application/x-tar"
+        || ARCHIVE_EXTENSIONS.contains(&extension.as_str())
+    {
+        AttachmentKind::Archive
+    } else {
+        AttachmentKind::File
+    }
+}
+
+/** Splits an uploaded file name into the name to keep and its extension.
+ *
+ * Any directory part is dropped. A name without an extension takes the
+ * usual one for `mime_type`, if there is one; otherwise the extension is
+ * empty.
+ */
+pub fn split_file_name(file_name: &str, mime_type: &str) -> (String, String) {
+    let name = Path::new(file_name.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "attachment".to_string());
+
+    let extension = Path::new(&name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_string())
+        .or_else(|| extension_from_mime(mime_type).map(str::to_string))
+        .unwrap_or_default();
+
+    (name, extension)
+}
+
+/** Registers an attachment and returns the stored row; its `created_at` is set to now. */
+pub fn insert_attachment(conn: &Connection, attachment: &Attachment) -> Result<Attachment, String> {
+    conn.execute(
+        "INSERT INTO attachments
+            (id, note_id, kind, original_name, extension, filename, file_path, size, mime_type,
+             created_at, blob_hash, width, height, original_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, datetime('now'), ?10, ?11, ?12, ?13)",
+        params![
+            attachment.id,
+            attachment.note_id,
+            attachment.kind.as_str(),
+            attachment.original_name,
+            attachment.extension,
+            attachment.filename,
+            attachment.file_path,
+            attachment.size as i64,
+            attachment.mime_type,
+            attachment.blob_hash,
+            attachment.width,
+            attachment.height,
+            attachment.original_id
+        ],
+    )
+    .map_err(|e| format!("Failed to insert attachment metadata: {}", e))?;
+
+    get_attachment(conn, &attachment.id)
+}
+
+pub fn get_attachment(conn: &Connection, id: &str) -> Result<Attachment, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM attachments WHERE id = ?1",
+            ATTACHMENT_COLUMNS
+        ),
+        [id],
+        attachment_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get attachment: {}", e))?
+    .ok_or_else(|| format!("Attachment not found: {}", id))
+}
+
+/** Lists a note's attachments, oldest first. */
+pub fn list_attachments(conn: &Connection, note_id: &str) -> Result<Vec<Attachment>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM attachments WHERE note_id = ?1 ORDER BY created_at, id",
+            ATTACHMENT_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let attachments = stmt
+        .query_map([note_id], attachment_from_row)
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment: {}", e))?;
+
+    Ok(attachments)
+}
+
+/** Lists every attachment of `kind` across all notes, oldest first. */
+pub fn list_attachments_by_kind(
+    conn: &Connection,
+    kind: AttachmentKind,
+) -> Result<Vec<Attachment>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM attachments WHERE kind = ?1 ORDER BY created_at, id",
+            ATTACHMENT_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let attachments = stmt
+        .query_map([kind.as_str()], attachment_from_row)
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment: {}", e))?;
+
+    Ok(attachments)
+}
+
+/** An attachment removed by [`delete_attachment`].
+ *
+ * `original` is the kept original of a re-encoded image, removed along
+ * with it. `file_paths` lists the files no longer used by any attachment;
+ * the caller removes them once the transaction has committed.
+ */
+#[derive(Debug)]
+pub struct DeletedAttachment {
+    pub attachment: Attachment,
+    pub original: Option<Attachment>,
+    pub file_paths: Vec<String>,
+}
+
+/** Deletes an attachment's row, and its kept original, releasing files nothing else uses. */
+pub fn delete_attachment(conn: &mut Connection, id: &str) -> Result<DeletedAttachment, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let attachment = get_attachment(&tx, id)?;
+    let original = match &attachment.original_id {
+        Some(original_id) => tx
+            .query_row(
+                &format!(
+                    "SELECT {} FROM attachments WHERE id = ?1",
+                    ATTACHMENT_COLUMNS
+                ),
+                [original_id],
+                attachment_from_row,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to get original attachment: {}", e))?,
+        None => None,
+    };
+
+    let mut candidates = vec![attachment.file_path.clone()];
+    tx.execute("DELETE FROM attachments WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete attachment: {}", e))?;
+    if let Some(original) = &original {
+        tx.execute("DELETE FROM attachments WHERE id = ?1", [&original.id])
+            .map_err(|e| format!("Failed to delete original attachment: {}", e))?;
+        candidates.push(original.file_path.clone());
+    }
+    let file_paths = release_unreferenced_files(&tx, candidates)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(DeletedAttachment {
+        attachment,
+        original,
+        file_paths,
+    })
+}
+
+pub fn get_blob(conn: &Connection, hash: &str) -> Result<Option<AttachmentBlob>, String> {
+    conn.query_row(
+        "SELECT hash, file_path, size, ref_count FROM attachment_blobs WHERE hash = ?1",
+        [hash],
+        blob_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get attachment blob: {}", e))
+}
+
+/** Registers the file stored for `hash`, unless one already is. */
+pub fn insert_blob(
+    conn: &Connection,
+    hash: &str,
+    file_path: &str,
+    size: u64,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO attachment_blobs (hash, file_path, size, ref_count, created_at)
+         VALUES (?1, ?2, ?3, 0, datetime('now'))",
+        params![hash, file_path, size as i64],
+    )
+    .map_err(|e| format!("Failed to insert attachment blob: {}", e))?;
+
+    Ok(())
+}
+
+/** Returns the stored `size` variant of an attachment's image, if one was rendered. */
+pub fn get_image_variant(
+    conn: &Connection,
+    attachment: &Attachment,
+    size: ImageVariantSize,
+) -> Result<Option<ImageVariant>, String> {
+    let Some(hash) = &attachment.blob_hash else {
+        return Ok(None);
+    };
+
+    conn.query_row(
+        "SELECT file_path, width, height, mime_type FROM attachment_variants
+         WHERE blob_hash = ?1 AND size = ?2",
+        params![hash, size.as_str()],
+        |row| {
+            Ok(ImageVariant {
+                attachment_id: attachment.id.clone(),
+                size,
+                file_path: row.get("file_path")?,
+                width: row.get("width")?,
+                height: row.get("height")?,
+                mime_type: row.get("mime_type")?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get image variant: {}", e))
+}
+
+/** Records the `size` variant rendered for the blob `hash`, replacing an older one. */
+pub fn insert_image_variant(
+    conn: &Connection,
+    hash: &str,
+    size: ImageVariantSize,
+    file_path: &str,
+    width: u32,
+    height: u32,
+    mime_type: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO attachment_variants
+            (blob_hash, size, file_path, width, height, mime_type, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+        params![hash, size.as_str(), file_path, width, height, mime_type],
+    )
+    .map_err(|e| format!("Failed to insert image variant: {}", e))?;
+
+    Ok(())
+}
+
+/** Drops blobs nothing refers to anymore and returns the files that can go.
+ *
+ * `candidates` are files of attachment rows just deleted or moved; they
+ * are returned too unless another attachment or blob still points at
+ * them. Runs on the caller's connection, so the files must only be removed
+ * after its transaction commits.
+ */
+pub fn release_unreferenced_files(
+    conn: &Connection,
+    candidates: Vec<String>,
+) -> Result<Vec<String>, String> {
+    // Variants go with their blob through the foreign key.
+    let released = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT file_path FROM attachment_blobs WHERE ref_count <= 0
+                 UNION ALL
+                 SELECT file_path FROM attachment_variants
+                 WHERE blob_hash IN (SELECT hash FROM attachment_blobs WHERE ref_count <= 0)",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to list unused blobs: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read unused blob: {}", e))?
+    };
+    conn.execute("DELETE FROM attachment_blobs WHERE ref_count <= 0", [])
+        .map_err(|e| format!("Failed to delete unused blobs: {}", e))?;
+
+    let mut file_paths: Vec<String> = Vec::new();
+    for path in released.into_iter().chain(candidates) {
+        if file_paths.contains(&path) {
+            continue;
+        }
+
+        let referenced: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM attachments WHERE file_path = ?1)
+                     OR EXISTS(SELECT 1 FROM attachment_blobs WHERE file_path = ?1)",
+                [&path],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check attachment file use: {}", e))?;
+        if !referenced {
+            file_paths.push(path);
+        }
+    }
+
+    Ok(file_paths)
+}
+
+/** Points attachment nodes in note content at files that moved.
+ *
+ * `moves` maps an attachment ID to its `(old_path, new_path)`. Returns
+ * the number of notes rewritten.
+ */
+pub fn rewrite_moved_files(
+    conn: &Connection,
+    moves: &HashMap<String, (String, String)>,
+) -> Result<usize, String> {
+    if moves.is_empty() {
+        return Ok(0);
+    }
+
+    let notes = {
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM nodes WHERE content IS NOT NULL")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to list nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?
+    };
+
+    let mut rewritten_notes = 0;
+    for (id, content) in notes {
+        if let Some(content) = tiptap::rewrite_attachment_paths(&content, moves) {
+            conn.execute(
+                "UPDATE nodes SET content = ?1 WHERE id = ?2",
+                params![content, id],
+            )
+            .map_err(|e| format!("Failed to update attachment references: {}", e))?;
+            rewritten_notes += 1;
+        }
+    }
+
+    Ok(rewritten_notes)
+}
+
+/** Outcome of [`dedupe_attachments`].
+ *
+ * `merged_rows` counts attachments moved onto another file with the same
+ * content. `file_paths` lists the duplicate files left unused; the caller
+ * removes them.
+ */
+#[derive(Debug, Default)]
+pub struct BlobDedupe {
+    pub hashed_rows: usize,
+    pub merged_rows: usize,
+    pub rewritten_notes: usize,
+    pub file_paths: Vec<String>,
+}
+
+/** Moves attachments that have no blob yet onto content-addressed blobs.
+ *
+ * Each file is hashed; the first file seen with a given content becomes
+ * the blob, and later attachments with the same content are pointed at
+ * it, with note content rewritten to match. Attachments whose file is
+ * missing are left for the integrity check. Only rows from before
+ * content addressing, or merged in from elsewhere, have no blob, so this
+ * does nothing once they are done.
+ */
+pub fn dedupe_attachments(conn: &mut Connection) -> Result<BlobDedupe, String> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let rows = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT id, file_path FROM attachments WHERE blob_hash IS NULL
+                 ORDER BY created_at, id",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to list unhashed attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment row: {}", e))?
+    };
+
+    let mut dedupe = BlobDedupe::default();
+    if rows.is_empty() {
+        return Ok(dedupe);
+    }
+
+    let mut moves = HashMap::new();
+    let mut candidates = Vec::new();
+    for (id, file_path) in rows {
+        let path = Path::new(&file_path);
+        let (Ok(hash), Ok(metadata)) = (utils::hash_file(path), fs::metadata(path)) else {
+            continue;
+        };
+
+        let blob_path = match get_blob(&tx, &hash)? {
+            Some(blob) if Path::new(&blob.file_path).is_file() => blob.file_path,
+            Some(_) => {
+                // The blob's own file went missing; this copy takes its place.
+                tx.execute(
+                    "UPDATE attachment_blobs SET file_path = ?1 WHERE hash = ?2",
+                    params![file_path, hash],
+                )
+                .map_err(|e| format!("Failed to update attachment blob: {}", e))?;
+                file_path.clone()
+            }
+            None => {
+                insert_blob(&tx, &hash, &file_path, metadata.len())?;
+                file_path.clone()
+            }
+        };
+
+        let filename = Path::new(&blob_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        tx.execute(
+            "UPDATE attachments SET blob_hash = ?1, file_path = ?2, filename = ?3 WHERE id = ?4",
+            params![hash, blob_path, filename, id],
+        )
+        .map_err(|e| format!("Failed to link attachment to blob: {}", e))?;
+        dedupe.hashed_rows += 1;
+
+        if blob_path != file_path {
+            moves.insert(id, (file_path.clone(), blob_path));
+            candidates.push(file_path);
+            dedupe.merged_rows += 1;
+        }
+    }
+
+    dedupe.rewritten_notes = rewrite_moved_files(&tx, &moves)?;
+    dedupe.file_paths = release_unreferenced_files(&tx, candidates)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(dedupe)
+}
+
+/** Fills in the kind and extension of rows carried over from the `images` table.
+ *
+ * Those rows only kept the stored file name, so it stands in for the
+ * original name too.
+ */
+pub fn backfill_attachment_metadata(conn: &Connection) -> Result<(), String> {
+    let rows = {
+        let mut stmt = conn
+            .prepare("SELECT id, filename, mime_type FROM attachments")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment: {}", e))?
+    };
+
+    for (id, filename, mime_type) in rows {
+        let (_, extension) = split_file_name(&filename, &mime_type);
+        conn.execute(
+            "UPDATE attachments SET kind = ?1, extension = ?2 WHERE id = ?3",
+            params![classify(&mime_type, &extension).as_str(), extension, id],
+        )
+        .map_err(|e| format!("Failed to backfill attachment: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/** Fills in the width and height of image attachments that have none.
+ *
+ * Only the file headers are read. Files that are missing or in a format
+ * that isn't decoded keep no dimensions.
+ */
+pub fn backfill_image_dimensions(conn: &Connection) -> Result<(), String> {
+    let rows = {
+        let mut stmt = conn
+            .prepare("SELECT id, file_path FROM attachments WHERE kind = 'image' AND width IS NULL")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to list image attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment: {}", e))?
+    };
+
+    for (id, file_path) in rows {
+        let Some((width, height)) = utils::images::image_dimensions(Path::new(&file_path)) else {
+            continue;
+        };
+        conn.execute(
+            "UPDATE attachments SET width = ?1, height = ?2 WHERE id = ?3",
+            params![width, height, id],
+        )
+        .map_err(|e| format!("Failed to backfill image dimensions: {}", e))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database,
+    };
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn ref_count(conn: &Connection, hash: &str) -> Option<i64> {
+        get_blob(conn, hash).unwrap().map(|blob| blob.ref_count)
+    }
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("attachments-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn classify_uses_the_extension_for_unspecific_types() {
+        assert_eq!(classify("image/PNG", ""), AttachmentKind::Image);
+        assert_eq!(classify("audio/ogg", "ogg"), AttachmentKind::Audio);
+        assert_eq!(
+            classify("application/octet-stream", "PDF"),
+            AttachmentKind::Pdf
+        );
+        assert_eq!(
+            classify("application/x-7z-compressed", ""),
+            AttachmentKind::Archive
+        );
+        assert_eq!(
+            classify("application/octet-stream", "tgz"),
+            AttachmentKind::Archive
+        );
+        assert_eq!(
+            classify("application/octet-stream", "bin"),
+            AttachmentKind::File
+        );
+    }
+
+    #[test]
+    fn split_file_name_drops_directories_and_fills_in_extensions() {
+        assert_eq!(
+            split_file_name("../notes/report.final.PDF", "application/pdf"),
+            ("report.final.PDF".to_string(), "PDF".to_string())
+        );
+        assert_eq!(
+            split_file_name("scan", "image/jpeg"),
+            ("scan".to_string(), "jpg".to_string())
+        );
+        assert_eq!(
+            split_file_name("  ", "application/x-unknown"),
+            ("attachment".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn blob_ref_counts_follow_attachment_rows() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let first = insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        insert_blob(&conn, "b", "/images/b.png", 4).unwrap();
+        assert_eq!(ref_count(&conn, "a"), Some(2));
+
+        conn.execute(
+            "UPDATE attachments SET blob_hash = 'b' WHERE id = ?1",
+            [&first.id],
+        )
+        .unwrap();
+        assert_eq!(ref_count(&conn, "a"), Some(1));
+        assert_eq!(ref_count(&conn, "b"), Some(1));
+
+        // Registering a known blob again keeps its count.
+        insert_blob(&conn, "b", "/images/elsewhere.png", 4).unwrap();
+        assert_eq!(
+            get_blob(&conn, "b").unwrap().unwrap().file_path,
+            "/images/b.png"
+        );
+        assert_eq!(ref_count(&conn, "b"), Some(1));
+    }
+
+    #[test]
+    fn delete_attachment_releases_files_once_unused() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let first = insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        let second = insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        let legacy = insert_test_attachment(&conn, &note.id, "/images/legacy.png", None);
+
+        let deleted = delete_attachment(&mut conn, &first.id).unwrap();
+        assert!(deleted.file_paths.is_empty());
+        assert_eq!(ref_count(&conn, "a"), Some(1));
+
+        let deleted = delete_attachment(&mut conn, &second.id).unwrap();
+        assert_eq!(deleted.file_paths, vec!["/images/a.png".to_string()]);
+        assert_eq!(ref_count(&conn, "a"), None);
+
+        let deleted = delete_attachment(&mut conn, &legacy.id).unwrap();
+        assert_eq!(deleted.file_paths, vec!["/images/legacy.png".to_string()]);
+        assert!(get_attachment(&conn, &legacy.id).is_err());
+    }
+
+    #[test]
+    fn dedupe_attachments_merges_files_with_the_same_content() {
+        let dir = temp_dir();
+        let (a, b, c) = (dir.join("a.png"), dir.join("b.png"), dir.join("c.png"));
+        fs::write(&a, b"same").unwrap();
+        fs::write(&b, b"same").unwrap();
+        fs::write(&c, b"other").unwrap();
+        let path = |file: &std::path::Path| file.to_string_lossy().to_string();
+
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let first = insert_test_attachment(&conn, &note.id, &path(&a), None);
+        let second = insert_test_attachment(&conn, &note.id, &path(&b), None);
+        let other = insert_test_attachment(&conn, &note.id, &path(&c), None);
+        let missing = insert_test_attachment(&conn, &note.id, &path(&dir.join("gone.png")), None);
+        // The oldest file with a given content becomes the blob.
+        conn.execute(
+            "UPDATE attachments SET created_at = '2000-01-01 00:00:00' WHERE id = ?1",
+            [&first.id],
+        )
+        .unwrap();
+        let src = |file: &str| format!("asset://localhost/{}", file.replace('/', "%2F"));
+        let content = json!({ "type": "doc", "content": [
+            { "type": "image", "attrs": { "attachmentId": second.id, "src": src(&path(&b)) } }
+        ] })
+        .to_string();
+        conn.execute(
+            "UPDATE nodes SET content = ?1 WHERE id = ?2",
+            params![content, note.id],
+        )
+        .unwrap();
+
+        let dedupe = dedupe_attachments(&mut conn).unwrap();
+
+        assert_eq!(dedupe.hashed_rows, 3);
+        assert_eq!(dedupe.merged_rows, 1);
+        assert_eq!(dedupe.rewritten_notes, 1);
+        assert_eq!(dedupe.file_paths, vec![path(&b)]);
+        let second = get_attachment(&conn, &second.id).unwrap();
+        assert_eq!(second.file_path, path(&a));
+        assert_eq!(second.filename, "a.png");
+        let hash = second.blob_hash.unwrap();
+        assert_eq!(
+            get_attachment(&conn, &first.id).unwrap().blob_hash,
+            Some(hash.clone())
+        );
+        assert_eq!(ref_count(&conn, &hash), Some(2));
+        assert!(
+            get_attachment(&conn, &other.id)
+                .unwrap()
+                .blob_hash
+                .is_some()
+        );
+        assert!(
+            get_attachment(&conn, &missing.id)
+                .unwrap()
+                .blob_hash
+                .is_none()
+        );
+        let stored: String = conn
+            .query_row(
+                "SELECT content FROM nodes WHERE id = ?1",
+                [&note.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(stored.contains(&src(&path(&a))));
+
+        // Everything is hashed now.
+        assert_eq!(dedupe_attachments(&mut conn).unwrap().hashed_rows, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn image_variants_are_shared_and_released_with_their_blob() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let first = insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        let second = insert_test_attachment(&conn, &note.id, "/images/a.png", Some("a"));
+        let legacy = insert_test_attachment(&conn, &note.id, "/images/legacy.png", None);
+        let thumbnail = ImageVariantSize::Thumbnail;
+        insert_image_variant(
+            &conn,
+            "a",
+            thumbnail,
+            "/images/old.webp",
+            1,
+            1,
+            "image/webp",
+        )
+        .unwrap();
+        insert_image_variant(
+            &conn,
+            "a",
+            thumbnail,
+            "/images/a-thumb.webp",
+            320,
+            160,
+            "image/webp",
+        )
+        .unwrap();
+
+        let variant = get_image_variant(&conn, &second, thumbnail)
+            .unwrap()
+            .unwrap();
+        assert_eq!(variant.attachment_id, second.id);
+        assert_eq!(variant.file_path, "/images/a-thumb.webp");
+        assert_eq!((variant.width, variant.height), (Some(320), Some(160)));
+        assert!(
+            get_image_variant(&conn, &first, ImageVariantSize::Display)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            get_image_variant(&conn, &legacy, thumbnail)
+                .unwrap()
+                .is_none()
+        );
+
+        delete_attachment(&mut conn, &first.id).unwrap();
+        assert!(
+            get_image_variant(&conn, &second, thumbnail)
+                .unwrap()
+                .is_some()
+        );
+
+        let deleted = delete_attachment(&mut conn, &second.id).unwrap();
+        assert_eq!(
+            deleted.file_paths,
+            vec![
+                "/images/a.png".to_string(),
+                "/images/a-thumb.webp".to_string()
+            ]
+        );
+        let variants: i64 = conn
+            .query_row("SELECT COUNT(*) FROM attachment_variants", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(variants, 0);
+    }
+
+    #[test]
+    fn delete_attachment_removes_the_kept_original() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let original = insert_test_attachment(&conn, &note.id, "/images/original.png", Some("o"));
+        let encoded = insert_test_attachment(&conn, &note.id, "/images/encoded.png", Some("e"));
+        conn.execute(
+            "UPDATE attachments SET original_id = ?1 WHERE id = ?2",
+            params![original.id, encoded.id],
+        )
+        .unwrap();
+
+        let mut deleted = delete_attachment(&mut conn, &encoded.id).unwrap();
+        deleted.file_paths.sort();
+
+        assert_eq!(
+            deleted.original.map(|original| original.id),
+            Some(original.id.clone())
+        );
+        assert_eq!(
+            deleted.file_paths,
+            vec![
+                "/images/encoded.png".to_string(),
+                "/images/original.png".to_string()
+            ]
+        );
+        assert!(get_attachment(&conn, &original.id).is_err());
+    }
+
+    #[test]
+    fn deleting_an_original_unlinks_its_encoded_copy() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let original = insert_test_attachment(&conn, &note.id, "/images/original.png", Some("o"));
+        let encoded = insert_test_attachment(&conn, &note.id, "/images/encoded.png", Some("e"));
+        conn.execute(
+            "UPDATE attachments SET original_id = ?1 WHERE id = ?2",
+            params![original.id, encoded.id],
+        )
+        .unwrap();
+
+        delete_attachment(&mut conn, &original.id).unwrap();
+
+        assert_eq!(
+            get_attachment(&conn, &encoded.id).unwrap().original_id,
+            None
+        );
+    }
+}
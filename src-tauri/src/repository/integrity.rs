@@ -0,0 +1,483 @@
+use super::{attachments, nodes, spaces, tags};
+use crate::models::integrity::{
+    AttachmentIssue, DanglingRows, ForeignKeyViolation, IntegrityReport, NodeIssue,
+};
+use crate::models::spaces::CreateSpaceRequest;
+use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/** Name of the space that nodes without a valid space are moved into. */
+const RECOVERED_SPACE_NAME: &str = "Recovered";
+
+/** Tables holding per-node rows, with the column naming the node. */
+const NODE_ROW_TABLES: [(&str, &str); 3] = [
+    ("node_revisions", "node_id"),
+    ("node_links", "source_id"),
+    ("node_tags", "node_id"),
+];
+
+/** Structural problems in the node tree. */
+#[derive(Debug, Default)]
+struct NodeFindings {
+    orphaned: Vec<NodeIssue>,
+    cross_space: Vec<NodeIssue>,
+    missing_space: Vec<NodeIssue>,
+    /// Each cycle's members, in parent-chain order.
+    cycles: Vec<Vec<NodeIssue>>,
+}
+
+fn analyze_nodes(conn: &Connection) -> Result<NodeFindings, String> {
+    let space_ids: HashSet<String> = spaces::list_spaces(conn)?
+        .into_iter()
+        .map(|space| space.id)
+        .collect();
+
+    let all_nodes = {
+        let mut stmt = conn
+            .prepare("SELECT id, space_id, parent_id, name FROM nodes ORDER BY id")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok(NodeIssue {
+                id: row.get(0)?,
+                space_id: row.get(1)?,
+                parent_id: row.get(2)?,
+                name: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?
+    };
+    let by_id: HashMap<&str, &NodeIssue> = all_nodes
+        .iter()
+        .map(|node| (node.id.as_str(), node))
+        .collect();
+
+    let mut findings = NodeFindings::default();
+    let mut in_cycle: HashSet<&str> = HashSet::new();
+
+    for node in &all_nodes {
+        if !space_ids.contains(&node.space_id) {
+            findings.missing_space.push(node.clone());
+        }
+
+        let Some(parent_id) = node.parent_id.as_deref() else {
+            continue;
+        };
+        match by_id.get(parent_id) {
+            None => findings.orphaned.push(node.clone()),
+            Some(parent) if parent.space_id != node.space_id => {
+                findings.cross_space.push(node.clone())
+            }
+            Some(_) => {}
+        }
+
+        if in_cycle.contains(node.id.as_str()) {
+            continue;
+        }
+
+        // Walk up the parent chain; revisiting the start node means it
+        // sits on a cycle.
+        let mut chain = vec![node];
+        let mut seen = HashSet::from([node.id.as_str()]);
+        let mut current = node;
+        while let Some(parent) = current
+            .parent_id
+            .as_deref()
+            .and_then(|parent_id| by_id.get(parent_id))
+        {
+            if parent.id == node.id {
+                in_cycle.extend(chain.iter().map(|member| member.id.as_str()));
+                findings
+                    .cycles
+                    .push(chain.iter().map(|member| (*member).clone()).collect());
+                break;
+            }
+            if !seen.insert(parent.id.as_str()) {
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+    }
+
+    Ok(findings)
+}
+
+fn list_attachments(conn: &Connection, filter: &str) -> Result<Vec<AttachmentIssue>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, note_id, file_path FROM attachments WHERE {} ORDER BY id",
+            filter
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let attachments = stmt
+        .query_map([], |row| {
+            Ok(AttachmentIssue {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                file_path: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment row: {}", e))?;
+
+    Ok(attachments)
+}
+
+/** Lists files in `images_dir` that no attachment row points at. */
+fn find_stray_files(conn: &Connection, images_dir: &Path) -> Result<Vec<String>, String> {
+    let entries = match fs::read_dir(images_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read images directory: {}", e)),
+    };
+
+    let known: HashSet<String> = list_attachments(conn, "1")?
+        .into_iter()
+        .map(|attachment| attachment.file_path)
+        .collect();
+
+    let mut stray = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read images directory: {}", e))?;
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let path = path.to_string_lossy().to_string();
+        if !known.contains(&path) {
+            stray.push(path);
+        }
+    }
+    stray.sort();
+
+    Ok(stray)
+}
+
+/** Checks the database and the images directory for inconsistencies. */
+pub fn check_integrity(conn: &Connection, images_dir: &Path) -> Result<IntegrityReport, String> {
+    let sqlite_errors = {
+        let mut stmt = conn
+            .prepare("PRAGMA integrity_check")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to run integrity check: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read integrity check: {}", e))?
+            .into_iter()
+            .filter(|message| message != "ok")
+            .collect::<Vec<_>>()
+    };
+
+    let foreign_key_violations = {
+        let mut stmt = conn
+            .prepare("PRAGMA foreign_key_check")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                row_id: row.get(1)?,
+                parent_table: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to run foreign key check: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read foreign key check: {}", e))?
+    };
+
+    let findings = analyze_nodes(conn)?;
+
+    let dangling_attachments = list_attachments(conn, "note_id NOT IN (SELECT id FROM nodes)")?;
+    let missing_files = list_attachments(conn, "1")?
+        .into_iter()
+        .filter(|attachment| !Path::new(&attachment.file_path).exists())
+        .collect::<Vec<_>>();
+
+    let mut dangling_rows = Vec::new();
+    for (table, column) in NODE_ROW_TABLES {
+        let count: i64 = conn
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {} WHERE {} NOT IN (SELECT id FROM nodes)",
+                    table, column
+                ),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to count dangling {} rows: {}", table, e))?;
+        if count > 0 {
+            dangling_rows.push(DanglingRows {
+                table: table.to_string(),
+                count,
+            });
+        }
+    }
+
+    let stray_files = find_stray_files(conn, images_dir)?;
+
+    let mut report = IntegrityReport {
+        healthy: false,
+        sqlite_errors,
+        foreign_key_violations,
+        orphaned_nodes: findings.orphaned,
+        cross_space_nodes: findings.cross_space,
+        missing_space_nodes: findings.missing_space,
+        cyclic_nodes: findings.cycles.into_iter().flatten().collect(),
+        dangling_attachments,
+        missing_files,
+        dangling_rows,
+        stray_files,
+    };
+    report.healthy = report.sqlite_errors.is_empty()
+        && report.foreign_key_violations.is_empty()
+        && report.orphaned_nodes.is_empty()
+        && report.cross_space_nodes.is_empty()
+        && report.missing_space_nodes.is_empty()
+        && report.cyclic_nodes.is_empty()
+        && report.dangling_attachments.is_empty()
+        && report.missing_files.is_empty()
+        && report.dangling_rows.is_empty()
+        && report.stray_files.is_empty();
+
+    Ok(report)
+}
+
+/** Database rows changed by [`repair_integrity`]. */
+#[derive(Debug, Default)]
+pub struct RepairedRows {
+    pub reparented_node_ids: Vec<String>,
+    pub recovered_space_id: Option<String>,
+    pub deleted_attachment_ids: Vec<String>,
+    pub deleted_row_count: i64,
+}
+
+fn move_to_space_root(conn: &Connection, node: &NodeIssue) -> Result<(), String> {
+    let sort_order = nodes::next_sort_order(conn, &node.space_id, None)?;
+    conn.execute(
+        "UPDATE nodes SET parent_id = NULL, sort_order = ?1 WHERE id = ?2",
+        params![sort_order, node.id],
+    )
+    .map_err(|e| format!("Failed to reparent node: {}", e))?;
+
+    Ok(())
+}
+
+/** Fixes the database problems that can be repaired without losing notes.
+ *
+ * - nodes without a valid space move into a new "Recovered" space
+ * - orphaned and cross-space nodes move to their space root
+ * - each parent cycle is broken by moving one member to the space root
+ * - attachment rows whose node or file is gone are deleted
+ * - revision, link and tag rows whose node is gone are deleted
+ *
+ * Files are not touched; stray files are left for the caller.
+ */
+pub fn repair_integrity(conn: &mut Connection) -> Result<RepairedRows, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut repaired = RepairedRows::default();
+
+    let findings = analyze_nodes(&tx)?;
+    if !findings.missing_space.is_empty() {
+        let space = spaces::create_space(
+            &tx,
+            &CreateSpaceRequest {
+                id: None,
+                name: RECOVERED_SPACE_NAME.to_string(),
+            },
+        )?;
+        tx.execute(
+            "UPDATE nodes SET space_id = ?1 WHERE space_id NOT IN (SELECT id FROM spaces)",
+            [&space.id],
+        )
+        .map_err(|e| format!("Failed to recover nodes: {}", e))?;
+        repaired.recovered_space_id = Some(space.id);
+    }
+
+    let findings = analyze_nodes(&tx)?;
+    for node in findings.orphaned.iter().chain(&findings.cross_space) {
+        move_to_space_root(&tx, node)?;
+        repaired.reparented_node_ids.push(node.id.clone());
+    }
+
+    let findings = analyze_nodes(&tx)?;
+    for cycle in &findings.cycles {
+        if let Some(node) = cycle.iter().min_by(|a, b| a.id.cmp(&b.id)) {
+            move_to_space_root(&tx, node)?;
+            repaired.reparented_node_ids.push(node.id.clone());
+        }
+    }
+
+    let broken_attachments = list_attachments(&tx, "note_id NOT IN (SELECT id FROM nodes)")?
+        .into_iter()
+        .chain(
+            list_attachments(&tx, "1")?
+                .into_iter()
+                .filter(|attachment| !Path::new(&attachment.file_path).exists()),
+        )
+        .map(|attachment| attachment.id)
+        .collect::<HashSet<_>>();
+    for attachment_id in broken_attachments {
+        tx.execute("DELETE FROM attachments WHERE id = ?1", [&attachment_id])
+            .map_err(|e| format!("Failed to delete attachment row: {}", e))?;
+        repaired.deleted_attachment_ids.push(attachment_id);
+    }
+    repaired.deleted_attachment_ids.sort();
+    // Blobs left unused are dropped; their files show up as stray files.
+    attachments::release_unreferenced_files(&tx, Vec::new())?;
+
+    for (table, column) in NODE_ROW_TABLES {
+        repaired.deleted_row_count += tx
+            .execute(
+                &format!(
+                    "DELETE FROM {} WHERE {} NOT IN (SELECT id FROM nodes)",
+                    table, column
+                ),
+                [],
+            )
+            .map_err(|e| format!("Failed to delete dangling {} rows: {}", table, e))?
+            as i64;
+    }
+    tags::prune_unused_tags(&tx)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database,
+    };
+    use uuid::Uuid;
+
+    fn ids(nodes: &[NodeIssue]) -> Vec<&str> {
+        let mut ids: Vec<&str> = nodes.iter().map(|node| node.id.as_str()).collect();
+        ids.sort();
+        ids
+    }
+
+    fn set_parent(conn: &Connection, id: &str, parent_id: &str) {
+        conn.execute(
+            "UPDATE nodes SET parent_id = ?1 WHERE id = ?2",
+            params![parent_id, id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn check_integrity_reports_a_fresh_database_as_healthy() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&note.id), "child", None);
+
+        let images_dir = std::env::temp_dir().join(format!("integrity-test-{}", Uuid::new_v4()));
+        let report = check_integrity(&conn, &images_dir).unwrap();
+
+        assert!(report.healthy, "{:?}", report);
+    }
+
+    #[test]
+    fn repair_integrity_fixes_broken_rows() {
+        let images_dir = std::env::temp_dir().join(format!("integrity-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&images_dir).unwrap();
+        let kept = images_dir.join("kept.png").to_string_lossy().to_string();
+        let stray = images_dir.join("stray.png").to_string_lossy().to_string();
+        fs::write(&kept, b"kept").unwrap();
+        fs::write(&stray, b"stray").unwrap();
+
+        let mut conn = open_test_database();
+        let other = spaces::create_space(
+            &conn,
+            &CreateSpaceRequest {
+                id: None,
+                name: "other".to_string(),
+            },
+        )
+        .unwrap();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        let host = create_test_node(&mut conn, TEST_SPACE_ID, None, "host", None);
+        let orphan = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "orphan", None);
+        let cross = create_test_node(&mut conn, &other.id, None, "cross", None);
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let b = create_test_node(&mut conn, TEST_SPACE_ID, Some(&a.id), "b", None);
+        let gone = create_test_node(&mut conn, TEST_SPACE_ID, None, "gone", None);
+        let lost = create_test_node(&mut conn, TEST_SPACE_ID, None, "lost", None);
+        tags::add_tag(&mut conn, &gone.id, "tag").unwrap();
+        insert_test_attachment(&conn, &root.id, &kept, None);
+        insert_test_attachment(&conn, &host.id, &kept, None);
+        let dangling = insert_test_attachment(&conn, &gone.id, &kept, None);
+        let missing = insert_test_attachment(&conn, &root.id, "/nowhere/missing.png", None);
+
+        conn.pragma_update(None, "foreign_keys", false).unwrap();
+        set_parent(&conn, &cross.id, &host.id);
+        set_parent(&conn, &a.id, &b.id);
+        conn.execute(
+            "UPDATE nodes SET space_id = 'no-such-space' WHERE id = ?1",
+            [&lost.id],
+        )
+        .unwrap();
+        conn.execute(
+            "DELETE FROM nodes WHERE id IN (?1, ?2)",
+            params![root.id, gone.id],
+        )
+        .unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+
+        let report = check_integrity(&conn, &images_dir).unwrap();
+        assert!(!report.healthy);
+        assert_eq!(ids(&report.orphaned_nodes), vec![orphan.id.as_str()]);
+        assert_eq!(ids(&report.cross_space_nodes), vec![cross.id.as_str()]);
+        assert_eq!(ids(&report.missing_space_nodes), vec![lost.id.as_str()]);
+        let mut cycle = vec![a.id.as_str(), b.id.as_str()];
+        cycle.sort();
+        assert_eq!(ids(&report.cyclic_nodes), cycle);
+        assert_eq!(report.dangling_attachments.len(), 3);
+        assert_eq!(report.missing_files.len(), 1);
+        assert_eq!(report.missing_files[0].id, missing.id);
+        assert_eq!(report.stray_files, vec![stray.clone()]);
+        assert!(
+            report
+                .dangling_rows
+                .iter()
+                .any(|rows| rows.table == "node_tags")
+        );
+
+        let repaired = repair_integrity(&mut conn).unwrap();
+
+        let recovered = repaired.recovered_space_id.unwrap();
+        assert_eq!(
+            nodes::get_node(&conn, &lost.id).unwrap().space_id,
+            recovered
+        );
+        assert_eq!(repaired.reparented_node_ids.len(), 3);
+        for id in [&orphan.id, &cross.id] {
+            assert_eq!(nodes::get_node(&conn, id).unwrap().parent_id, None);
+        }
+        assert_eq!(nodes::get_node(&conn, cycle[0]).unwrap().parent_id, None);
+        assert!(repaired.deleted_attachment_ids.contains(&dangling.id));
+        assert!(repaired.deleted_attachment_ids.contains(&missing.id));
+        assert!(repaired.deleted_row_count > 0);
+
+        let report = check_integrity(&conn, &images_dir).unwrap();
+        assert_eq!(report.stray_files, vec![stray]);
+        assert!(report.orphaned_nodes.is_empty() && report.cyclic_nodes.is_empty());
+        assert!(report.dangling_attachments.is_empty() && report.missing_files.is_empty());
+        assert!(report.dangling_rows.is_empty() && report.foreign_key_violations.is_empty());
+        fs::remove_dir_all(images_dir).unwrap();
+    }
+}
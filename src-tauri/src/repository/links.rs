@@ -0,0 +1,326 @@
+use super::{nodes, revisions};
+use crate::models::links::{LinkKind, NodeLink};
+use crate::models::revisions::RevisionSettings;
+use crate::utils::tiptap;
+use rusqlite::{Connection, Params, Row, params};
+
+// Every indexed link with the note it currently resolves to. Link marks
+// resolve by ID; wiki links resolve by name, preferring a note in the
+// source's own space. Trashed notes are neither sources nor targets.
+const RESOLVED_LINKS_QUERY: &str = "SELECT * FROM (
+        SELECT l.rowid AS link_order, l.source_id, src.name AS source_name, l.kind,
+               l.label, l.href, l.target_name,
+               CASE WHEN l.target_id IS NOT NULL THEN (
+                   SELECT t.id FROM nodes t
+                   WHERE t.id = l.target_id AND t.deleted_at IS NULL
+               ) ELSE COALESCE((
+                   SELECT t.id FROM nodes t
+                   WHERE t.name = l.target_name COLLATE NOCASE AND t.deleted_at IS NULL
+                     AND t.space_id = src.space_id
+                   ORDER BY t.created_at, t.id
+                   LIMIT 1
+               ), (
+                   SELECT t.id FROM nodes t
+                   WHERE t.name = l.target_name COLLATE NOCASE AND t.deleted_at IS NULL
+                   ORDER BY t.created_at, t.id
+                   LIMIT 1
+               )) END AS target_id
+        FROM node_links l
+        JOIN nodes src ON src.id = l.source_id
+        WHERE src.deleted_at IS NULL
+    )";
+
+fn link_from_row(row: &Row) -> rusqlite::Result<NodeLink> {
+    let kind: String = row.get("kind")?;
+
+    Ok(NodeLink {
+        source_id: row.get("source_id")?,
+        source_name: row.get("source_name")?,
+        kind: LinkKind::parse(&kind).unwrap_or(LinkKind::Link),
+        label: row.get("label")?,
+        href: row.get("href")?,
+        target_id: row.get("target_id")?,
+        target_name: row.get("target_name")?,
+    })
+}
+
+fn query_links(
+    conn: &Connection,
+    filter: &str,
+    params: impl Params,
+) -> Result<Vec<NodeLink>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE {} ORDER BY source_name, source_id, link_order",
+            RESOLVED_LINKS_QUERY, filter
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let links = stmt
+        .query_map(params, link_from_row)
+        .map_err(|e| format!("Failed to list links: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read link: {}", e))?;
+
+    Ok(links)
+}
+
+/** Replaces the indexed links of a node with those found in `content`. */
+pub fn index_links(conn: &Connection, node_id: &str, content: Option<&str>) -> Result<(), String> {
+    conn.execute("DELETE FROM node_links WHERE source_id = ?1", [node_id])
+        .map_err(|e| format!("Failed to clear node links: {}", e))?;
+
+    let links = content.map(tiptap::extract_links).unwrap_or_default();
+    for link in links {
+        conn.execute(
+            "INSERT INTO node_links (source_id, kind, label, href, target_id, target_name)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                node_id,
+                link.kind.as_str(),
+                link.label,
+                link.href,
+                link.target_id,
+                link.target_name
+            ],
+        )
+        .map_err(|e| format!("Failed to index node link: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/** Rebuilds the link index for every node.
+ *
+ * Only reads `id` and `content` so it can run as a migration backfill
+ * before later columns exist.
+ */
+pub fn rebuild_links(conn: &Connection) -> Result<(), String> {
+    let rows = {
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM nodes")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(|e| format!("Failed to list nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?
+    };
+
+    for (id, content) in rows {
+        index_links(conn, &id, content.as_deref())?;
+    }
+
+    Ok(())
+}
+
+/** Lists links from other notes that resolve to `node_id`. */
+pub fn get_backlinks(conn: &Connection, node_id: &str) -> Result<Vec<NodeLink>, String> {
+    query_links(conn, "target_id = ?1", [node_id])
+}
+
+/** Lists the links written in `node_id`, resolved or not. */
+pub fn get_outgoing_links(conn: &Connection, node_id: &str) -> Result<Vec<NodeLink>, String> {
+    query_links(conn, "source_id = ?1", [node_id])
+}
+
+/** Lists links whose target does not exist or is in the trash. */
+pub fn find_unresolved_links(conn: &Connection) -> Result<Vec<NodeLink>, String> {
+    query_links(conn, "target_id IS NULL", [])
+}
+
+/** Returns the IDs of notes with a wiki reference resolving to `node_id`. */
+pub fn wiki_referrer_ids(conn: &Connection, node_id: &str) -> Result<Vec<String>, String> {
+    let mut ids: Vec<String> = get_backlinks(conn, node_id)?
+        .into_iter()
+        .filter(|link| link.kind == LinkKind::Wiki)
+        .map(|link| link.source_id)
+        .collect();
+    ids.dedup();
+
+    Ok(ids)
+}
+
+/** Rewrites `[[old_name]]` references in the given notes to `new_name`.
+ *
+ * Each rewritten note gets a revision and refreshed content indexes.
+ * Returns the IDs of the notes that changed.
+ */
+pub fn rewrite_references(
+    conn: &Connection,
+    source_ids: &[String],
+    old_name: &str,
+    new_name: &str,
+    revision_settings: &RevisionSettings,
+) -> Result<Vec<String>, String> {
+    let mut rewritten = Vec::new();
+
+    for source_id in source_ids {
+        let previous = nodes::get_node(conn, source_id)?;
+        let Some(content) = previous
+            .content
+            .as_deref()
+            .and_then(|content| tiptap::rewrite_wiki_links(content, old_name, new_name))
+        else {
+            continue;
+        };
+
+        conn.execute(
+            "UPDATE nodes SET content = ?1 WHERE id = ?2",
+            params![content, source_id],
+        )
+        .map_err(|e| format!("Failed to rewrite note links: {}", e))?;
+
+        let node = nodes::get_node(conn, source_id)?;
+        let snapshotted = revisions::snapshot_untracked(conn, &previous)?;
+        revisions::record_revision(conn, &node, revision_settings, !snapshotted)?;
+        nodes::index_content(conn, source_id, node.content.as_deref())?;
+
+        rewritten.push(source_id.clone());
+    }
+
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::nodes::UpdateNodeRequest;
+    use crate::models::spaces::CreateSpaceRequest;
+    use crate::repository::{TEST_SPACE_ID, create_test_node, open_test_database, spaces, trash};
+    use serde_json::{Value, json};
+
+    fn text_doc(text: &str) -> String {
+        json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [{ "type": "text", "text": text }] }
+        ] })
+        .to_string()
+    }
+
+    fn link_doc(label: &str, href: &str) -> String {
+        json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [{
+                "type": "text",
+                "text": label,
+                "marks": [{ "type": "link", "attrs": { "href": href } }]
+            }] }
+        ] })
+        .to_string()
+    }
+
+    fn sources(links: &[NodeLink]) -> Vec<&str> {
+        links.iter().map(|link| link.source_name.as_str()).collect()
+    }
+
+    #[test]
+    fn backlinks_resolve_link_marks_and_wiki_references() {
+        let mut conn = open_test_database();
+        let target = create_test_node(&mut conn, TEST_SPACE_ID, None, "Target", None);
+        let href = format!("/spaces/{}/files/{}", TEST_SPACE_ID, target.id);
+        create_test_node(
+            &mut conn,
+            TEST_SPACE_ID,
+            None,
+            "by id",
+            Some(&link_doc("see", &href)),
+        );
+        let wiki = text_doc("see [[target|it]]");
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "by name", Some(&wiki));
+
+        let backlinks = get_backlinks(&conn, &target.id).unwrap();
+
+        assert_eq!(sources(&backlinks), vec!["by id", "by name"]);
+        assert_eq!(backlinks[0].kind, LinkKind::Link);
+        assert_eq!(backlinks[1].kind, LinkKind::Wiki);
+        assert_eq!(backlinks[1].label, "it");
+        assert!(find_unresolved_links(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn links_to_trashed_or_missing_notes_are_unresolved() {
+        let mut conn = open_test_database();
+        let target = create_test_node(&mut conn, TEST_SPACE_ID, None, "Target", None);
+        let content = text_doc("[[Target]] and [[Nowhere]]");
+        let source = create_test_node(&mut conn, TEST_SPACE_ID, None, "source", Some(&content));
+        trash::trash_node(&mut conn, &target.id).unwrap();
+
+        let unresolved = find_unresolved_links(&conn).unwrap();
+
+        let names: Vec<_> = unresolved
+            .iter()
+            .map(|link| link.target_name.as_deref())
+            .collect();
+        assert_eq!(names, vec![Some("Target"), Some("Nowhere")]);
+        assert_eq!(get_outgoing_links(&conn, &source.id).unwrap().len(), 2);
+
+        trash::restore_node(&mut conn, &target.id).unwrap();
+        assert_eq!(find_unresolved_links(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn wiki_references_prefer_a_note_in_the_same_space() {
+        let mut conn = open_test_database();
+        let other = spaces::create_space(
+            &conn,
+            &CreateSpaceRequest {
+                id: None,
+                name: "other".to_string(),
+            },
+        )
+        .unwrap();
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "Shared", None);
+        let local = create_test_node(&mut conn, &other.id, None, "shared", None);
+        let source = create_test_node(
+            &mut conn,
+            &other.id,
+            None,
+            "source",
+            Some(&text_doc("[[Shared]]")),
+        );
+
+        let links = get_outgoing_links(&conn, &source.id).unwrap();
+
+        assert_eq!(links[0].target_id.as_deref(), Some(local.id.as_str()));
+    }
+
+    #[test]
+    fn renaming_a_note_can_rewrite_references_to_it() {
+        let mut conn = open_test_database();
+        let target = create_test_node(&mut conn, TEST_SPACE_ID, None, "Old", None);
+        let source = create_test_node(
+            &mut conn,
+            TEST_SPACE_ID,
+            None,
+            "source",
+            Some(&text_doc("[[old]] [[Older]]")),
+        );
+
+        let updated = nodes::update_node(
+            &mut conn,
+            &UpdateNodeRequest {
+                id: target.id.clone(),
+                name: Some("New".to_string()),
+                content: None,
+                is_open: None,
+                is_pinned: None,
+                rewrite_links: true,
+            },
+            &RevisionSettings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(updated.rewritten.len(), 1);
+        let content: Value =
+            serde_json::from_str(updated.rewritten[0].content.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            content["content"][0]["content"][0]["text"],
+            "[[New]] [[Older]]"
+        );
+        assert_eq!(
+            wiki_referrer_ids(&conn, &target.id).unwrap(),
+            vec![source.id]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+pub mod attachments;
+pub mod integrity;
+pub mod links;
+pub mod nodes;
+pub mod pool;
+pub mod revisions;
+pub mod search;
+pub mod spaces;
+pub mod tags;
+pub mod trash;
+
+pub use pool::{Database, DbPool, PooledConnection};
+
+use crate::utils::tiptap;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{Connection, OpenFlags};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/** How long a connection waits for another writer before failing with `database is locked`. */
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/** Opens a connection to the database at `path` with the app's SQL functions registered.
+ *
+ * Triggers created by the migrations call these functions, so every
+ * connection that writes to `nodes` must be opened through here. The
+ * connection enforces foreign keys so `ON DELETE CASCADE` applies and waits
+ * out short locks. With `wal` set it uses WAL journaling so readers don't
+ * block writers; otherwise it keeps a rollback journal, which leaves a
+ * single consistent file for folders synced between devices.
+ */
+pub fn open_database(path: &Path, wal: bool) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| format!("Failed to open database: {}", e))?;
+
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    conn.pragma_update(None, "journal_mode", if wal { "WAL" } else { "DELETE" })
+        .map_err(|e| format!("Failed to set journal mode: {}", e))?;
+    conn.pragma_update(None, "foreign_keys", true)
+        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+
+    register_functions(&conn)?;
+    Ok(conn)
+}
+
+/** Opens a connection that cannot write to the database at `path`.
+ *
+ * Used when the database failed to open normally, so it neither changes
+ * the journal mode nor creates the file if it is missing.
+ */
+pub fn open_database_read_only(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    conn.pragma_update(None, "query_only", true)
+        .map_err(|e| format!("Failed to make database read-only: {}", e))?;
+
+    register_functions(&conn)?;
+    Ok(conn)
+}
+
+/** Borrows a connection from the open vault's database pool. */
+pub fn open_connection(app: &AppHandle) -> Result<PooledConnection, String> {
+    app.try_state::<Database>()
+        .ok_or_else(|| "Database is not open.".to_string())?
+        .pool()
+        .get()
+}
+
+/** Registers the scalar functions used by triggers and queries.
+ *
+ * - `tiptap_text(content)`: plain text of a TipTap JSON document
+ */
+fn register_functions(conn: &Connection) -> Result<(), String> {
+    conn.create_scalar_function(
+        "tiptap_text",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let content: Option<String> = ctx.get(0)?;
+            Ok(content
+                .as_deref()
+                .map(tiptap::extract_text)
+                .unwrap_or_default())
+        },
+    )
+    .map_err(|e| format!("Failed to register SQL functions: {}", e))
+}
+
+/** Returns the trimmed caller-supplied ID, or a fresh UUID when none was given. */
+fn resolve_id(id: Option<&str>) -> Result<String, String> {
+    match id.map(str::trim) {
+        Some("") => Err("ID cannot be empty.".to_string()),
+        Some(id) => Ok(id.to_string()),
+        None => Ok(Uuid::new_v4().to_string()),
+    }
+}
+
+/** Space seeded by the migrations, which tests create their nodes in. */
+#[cfg(test)]
+pub(crate) const TEST_SPACE_ID: &str = "default-space-mine";
+
+/** Opens an in-memory database with every migration applied. */
+#[cfg(test)]
+pub(crate) fn open_test_database() -> Connection {
+    let mut conn = open_database(Path::new(":memory:"), false).unwrap();
+    crate::migrations::run_migrations(&mut conn, None).unwrap();
+    conn
+}
+
+/** Creates a node with the given content as the last child of `parent_id`. */
+#[cfg(test)]
+pub(crate) fn create_test_node(
+    conn: &mut Connection,
+    space_id: &str,
+    parent_id: Option<&str>,
+    name: &str,
+    content: Option<&str>,
+) -> crate::models::nodes::Node {
+    nodes::create_node(
+        conn,
+        &crate::models::nodes::CreateNodeRequest {
+            id: None,
+            space_id: space_id.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            name: name.to_string(),
+            content: content.map(str::to_string),
+            is_open: false,
+            is_pinned: false,
+        },
+    )
+    .unwrap()
+}
+
+/** Registers an attachment of `note_id` stored at `file_path`, sharing the blob `hash` if given. */
+#[cfg(test)]
+pub(crate) fn insert_test_attachment(
+    conn: &Connection,
+    note_id: &str,
+    file_path: &str,
+    hash: Option<&str>,
+) -> crate::models::attachments::Attachment {
+    if let Some(hash) = hash {
+        attachments::insert_blob(conn, hash, file_path, 4).unwrap();
+    }
+    let filename = Path::new(file_path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    attachments::insert_attachment(
+        conn,
+        &crate::models::attachments::Attachment {
+            id: Uuid::new_v4().to_string(),
+            note_id: note_id.to_string(),
+            kind: crate::models::attachments::AttachmentKind::Image,
+            original_name: filename.clone(),
+            extension: "png".to_string(),
+            filename,
+            file_path: file_path.to_string(),
+            size: 4,
+            mime_type: "image/png".to_string(),
+            created_at: String::new(),
+            blob_hash: hash.map(str::to_string),
+            width: None,
+            height: None,
+            original_id: None,
+        },
+    )
+    .unwrap()
+}
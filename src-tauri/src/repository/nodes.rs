@@ -0,0 +1,1253 @@
+use super::{attachments, links, resolve_id, revisions, spaces, tags};
+use crate::models::nodes::{
+    CreateNodeRequest, MoveNodeRequest, Node, SaveNodeError, UpdateNodeRequest,
+};
+use crate::models::revisions::RevisionSettings;
+use crate::utils::tiptap::{self, AttachmentRewrite};
+use rusqlite::{
+    Connection, OptionalExtension, Row, ToSql, TransactionBehavior, params, params_from_iter,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+/** Recursive CTE selecting the IDs of node `?1` and all of its descendants. */
+pub(crate) const SUBTREE_CTE: &str = "WITH RECURSIVE subtree(id) AS (
+        SELECT id FROM nodes WHERE id = ?1
+        UNION
+        SELECT n.id FROM nodes n JOIN subtree s ON n.parent_id = s.id
+    )";
+
+pub(crate) const NODE_COLUMNS: &str = "id, space_id, parent_id, name, content, is_open, is_pinned, \
+                            sort_order, created_at, updated_at, deleted_at, revision";
+
+pub(crate) fn node_from_row(row: &Row) -> rusqlite::Result<Node> {
+    Ok(Node {
+        id: row.get("id")?,
+        space_id: row.get("space_id")?,
+        parent_id: row.get("parent_id")?,
+        name: row.get("name")?,
+        content: row.get("content")?,
+        is_open: row.get("is_open")?,
+        is_pinned: row.get("is_pinned")?,
+        sort_order: row.get("sort_order")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        deleted_at: row.get("deleted_at")?,
+        revision: row.get("revision")?,
+    })
+}
+
+/** Refreshes the link and inline tag indexes derived from a node's content. */
+pub(crate) fn index_content(
+    conn: &Connection,
+    node_id: &str,
+    content: Option<&str>,
+) -> Result<(), String> {
+    links::index_links(conn, node_id, content)?;
+    tags::index_inline_tags(conn, node_id, content)
+}
+
+/** Returns the sort order that places a node after its last sibling. */
+pub(crate) fn next_sort_order(
+    conn: &Connection,
+    space_id: &str,
+    parent_id: Option<&str>,
+) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM nodes
+         WHERE space_id = ?1 AND parent_id IS ?2 AND deleted_at IS NULL",
+        params![space_id, parent_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to compute sort order: {}", e))
+}
+
+/** Returns true if `candidate_id` is `node_id` or one of its ancestors. */
+fn is_ancestor_or_self(
+    conn: &Connection,
+    candidate_id: &str,
+    node_id: &str,
+) -> Result<bool, String> {
+    conn.query_row(
+        "WITH RECURSIVE ancestors(id, parent_id) AS (
+            SELECT id, parent_id FROM nodes WHERE id = ?1
+            UNION
+            SELECT n.id, n.parent_id FROM nodes n
+            JOIN ancestors a ON n.id = a.parent_id
+         )
+         SELECT EXISTS(SELECT 1 FROM ancestors WHERE id = ?2)",
+        params![node_id, candidate_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to walk node ancestors: {}", e))
+}
+
+/** Ensures `parent_id` names an existing, non-trashed node in `space_id`. */
+fn validate_parent(conn: &Connection, space_id: &str, parent_id: &str) -> Result<(), String> {
+    let parent = get_node(conn, parent_id)?;
+    if parent.space_id != space_id {
+        return Err("Parent node belongs to a different space.".to_string());
+    }
+    if parent.deleted_at.is_some() {
+        return Err("Parent node is in the trash.".to_string());
+    }
+
+    Ok(())
+}
+
+/** Lists every node in a space in sibling order, excluding trashed nodes. */
+pub fn list_nodes(conn: &Connection, space_id: &str) -> Result<Vec<Node>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM nodes
+             WHERE space_id = ?1 AND deleted_at IS NULL
+             ORDER BY sort_order",
+            NODE_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let nodes = stmt
+        .query_map([space_id], node_from_row)
+        .map_err(|e| format!("Failed to list nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?;
+
+    Ok(nodes)
+}
+
+/** Fetches a single node by ID. */
+pub fn get_node(conn: &Connection, id: &str) -> Result<Node, String> {
+    find_node(conn, id)?.ok_or_else(|| format!("Node not found: {}", id))
+}
+
+/** Fetches a single node by ID, returning `None` if it doesn't exist. */
+pub fn find_node(conn: &Connection, id: &str) -> Result<Option<Node>, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM nodes WHERE id = ?1", NODE_COLUMNS),
+        [id],
+        node_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get node: {}", e))
+}
+
+/** Returns the names of a node's ancestors, root first. */
+pub fn node_path(conn: &Connection, id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "WITH RECURSIVE ancestors(id, parent_id, name, depth) AS (
+                SELECT id, parent_id, name, 0 FROM nodes WHERE id = ?1
+                UNION
+                SELECT n.id, n.parent_id, n.name, a.depth + 1 FROM nodes n
+                JOIN ancestors a ON n.id = a.parent_id
+                WHERE a.depth < 256
+             )
+             SELECT name FROM ancestors WHERE depth > 0 ORDER BY depth DESC",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let names = stmt
+        .query_map([id], |row| row.get(0))
+        .map_err(|e| format!("Failed to walk node ancestors: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node ancestor: {}", e))?;
+
+    Ok(names)
+}
+
+/** Creates a node as the last child of its parent (or of the space root). */
+pub fn create_node(conn: &mut Connection, request: &CreateNodeRequest) -> Result<Node, String> {
+    let id = resolve_id(request.id.as_deref())?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    spaces::get_space(&tx, &request.space_id)?;
+    if let Some(parent_id) = request.parent_id.as_deref() {
+        validate_parent(&tx, &request.space_id, parent_id)?;
+    }
+
+    let sort_order = next_sort_order(&tx, &request.space_id, request.parent_id.as_deref())?;
+
+    tx.execute(
+        "INSERT INTO nodes
+            (id, space_id, parent_id, name, content, is_open, is_pinned, sort_order,
+             created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'), datetime('now'))",
+        params![
+            id,
+            request.space_id,
+            request.parent_id,
+            request.name.trim(),
+            request.content,
+            request.is_open,
+            request.is_pinned,
+            sort_order
+        ],
+    )
+    .map_err(|e| format!("Failed to create node: {}", e))?;
+
+    let node = get_node(&tx, &id)?;
+    index_content(&tx, &node.id, node.content.as_deref())?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(node)
+}
+
+/** Result of [`update_node`].
+ *
+ * `rewritten` holds the other nodes whose wiki links were rewritten by a
+ * rename, as stored afterwards.
+ */
+#[derive(Debug)]
+pub struct UpdatedNode {
+    pub node: Node,
+    pub rewritten: Vec<Node>,
+}
+
+/** Applies a partial update to a node and returns the stored result.
+ *
+ * Content changes are recorded in the node's revision history and
+ * reindexed for links and tags. A rename with `rewrite_links` set also rewrites
+ * wiki references to the node in other notes.
+ */
+pub fn update_node(
+    conn: &mut Connection,
+    request: &UpdateNodeRequest,
+    revision_settings: &RevisionSettings,
+) -> Result<UpdatedNode, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let previous = get_node(&tx, &request.id)?;
+    let updated = apply_update(&tx, &previous, request, revision_settings)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(updated)
+}
+
+/** Saves a node's content unless it changed since `expected_revision`.
+ *
+ * The check and the write happen under one write lock, so two windows
+ * saving the same node cannot both succeed against the same revision.
+ */
+pub fn save_node_content(
+    conn: &mut Connection,
+    node_id: &str,
+    expected_revision: i64,
+    content: &str,
+    revision_settings: &RevisionSettings,
+) -> Result<Node, SaveNodeError> {
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let previous = get_node(&tx, node_id)?;
+    if previous.revision != expected_revision {
+        return Err(SaveNodeError::Conflict {
+            current: Box::new(previous),
+        });
+    }
+
+    let request = UpdateNodeRequest {
+        id: node_id.to_string(),
+        content: Some(content.to_string()),
+        ..Default::default()
+    };
+    let updated = apply_update(&tx, &previous, &request, revision_settings)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(updated.node)
+}
+
+/** Applies `request` to the `previous` state of its node, within the caller's transaction. */
+fn apply_update(
+    conn: &Connection,
+    previous: &Node,
+    request: &UpdateNodeRequest,
+    revision_settings: &RevisionSettings,
+) -> Result<UpdatedNode, String> {
+    if previous.deleted_at.is_some() {
+        return Err("Cannot edit a node that is in the trash.".to_string());
+    }
+
+    let new_name = request.name.as_deref().map(str::trim);
+
+    // Referrers must be found before the rename changes how names resolve.
+    let referrer_ids = match new_name {
+        Some(name) if request.rewrite_links && name != previous.name => {
+            links::wiki_referrer_ids(conn, &previous.id)?
+        }
+        _ => Vec::new(),
+    };
+
+    // Only supplied columns are set: naming `name` or `content` fires the
+    // timestamp and search index triggers even when the value is unchanged.
+    let mut columns: Vec<(&str, &dyn ToSql)> = Vec::new();
+    if let Some(name) = &new_name {
+        columns.push(("name", name));
+    }
+    if let Some(content) = &request.content {
+        columns.push(("content", content));
+    }
+    if let Some(is_open) = &request.is_open {
+        columns.push(("is_open", is_open));
+    }
+    if let Some(is_pinned) = &request.is_pinned {
+        columns.push(("is_pinned", is_pinned));
+    }
+
+    if !columns.is_empty() {
+        let assignments = columns
+            .iter()
+            .enumerate()
+            .map(|(index, (column, _))| format!("{} = ?{}", column, index + 2))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let values = std::iter::once(&request.id as &dyn ToSql)
+            .chain(columns.iter().map(|(_, value)| *value));
+        conn.execute(
+            &format!("UPDATE nodes SET {} WHERE id = ?1", assignments),
+            params_from_iter(values),
+        )
+        .map_err(|e| format!("Failed to update node: {}", e))?;
+    }
+
+    let node = get_node(conn, &request.id)?;
+
+    if node.content != previous.content {
+        // Never coalesce into a snapshot of the pre-update content.
+        let snapshotted = revisions::snapshot_untracked(conn, previous)?;
+        revisions::record_revision(conn, &node, revision_settings, !snapshotted)?;
+        index_content(conn, &node.id, node.content.as_deref())?;
+    }
+
+    let rewritten_ids = links::rewrite_references(
+        conn,
+        &referrer_ids,
+        &previous.name,
+        &node.name,
+        revision_settings,
+    )?;
+
+    let mut updated = UpdatedNode {
+        // The node may have referenced itself.
+        node: get_node(conn, &node.id)?,
+        rewritten: Vec::new(),
+    };
+    for id in rewritten_ids.iter().filter(|id| **id != node.id) {
+        updated.rewritten.push(get_node(conn, id)?);
+    }
+
+    Ok(updated)
+}
+
+/** Lists the IDs of a sibling list in sort order, excluding trashed nodes and `exclude_id`. */
+fn sibling_ids(
+    conn: &Connection,
+    space_id: &str,
+    parent_id: Option<&str>,
+    exclude_id: &str,
+) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM nodes
+             WHERE space_id = ?1 AND parent_id IS ?2 AND id != ?3
+               AND deleted_at IS NULL
+             ORDER BY sort_order",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let ids = stmt
+        .query_map(params![space_id, parent_id, exclude_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to list sibling nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read sibling node: {}", e))?;
+
+    Ok(ids)
+}
+
+/** Assigns consecutive sort orders to `ids` in the given order. */
+fn renumber(conn: &Connection, ids: &[String]) -> Result<(), String> {
+    for (index, id) in ids.iter().enumerate() {
+        conn.execute(
+            "UPDATE nodes SET sort_order = ?1 WHERE id = ?2",
+            params![index as i64, id],
+        )
+        .map_err(|e| format!("Failed to reorder nodes: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/** Moves a node under a new parent, optionally in another space.
+ *
+ * Moving to another space carries the whole subtree along. Both the old
+ * and the new sibling lists are renumbered so the node lands at the
+ * requested position without gaps left behind.
+ */
+pub fn move_node(conn: &mut Connection, request: &MoveNodeRequest) -> Result<Node, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let node = get_node(&tx, &request.node_id)?;
+    if node.deleted_at.is_some() {
+        return Err("Cannot move a node that is in the trash.".to_string());
+    }
+
+    let space_id = request.new_space_id.as_deref().unwrap_or(&node.space_id);
+    if space_id != node.space_id {
+        spaces::get_space(&tx, space_id)?;
+    }
+
+    if let Some(parent_id) = request.new_parent_id.as_deref() {
+        validate_parent(&tx, space_id, parent_id)?;
+        if is_ancestor_or_self(&tx, &node.id, parent_id)? {
+            return Err("Cannot move a node into itself or one of its descendants.".to_string());
+        }
+    }
+
+    let mut new_siblings = sibling_ids(&tx, space_id, request.new_parent_id.as_deref(), &node.id)?;
+    let position = request
+        .position
+        .map(|position| position.clamp(0, new_siblings.len() as i64) as usize)
+        .unwrap_or(new_siblings.len());
+    new_siblings.insert(position, node.id.clone());
+
+    if space_id != node.space_id {
+        tx.execute(
+            &format!(
+                "{} UPDATE nodes SET space_id = ?2 WHERE id IN (SELECT id FROM subtree)",
+                SUBTREE_CTE
+            ),
+            params![node.id, space_id],
+        )
+        .map_err(|e| format!("Failed to move subtree to space: {}", e))?;
+    }
+
+    tx.execute(
+        "UPDATE nodes SET parent_id = ?1 WHERE id = ?2",
+        params![request.new_parent_id, node.id],
+    )
+    .map_err(|e| format!("Failed to move node: {}", e))?;
+
+    if space_id != node.space_id || request.new_parent_id != node.parent_id {
+        let old_siblings = sibling_ids(&tx, &node.space_id, node.parent_id.as_deref(), &node.id)?;
+        renumber(&tx, &old_siblings)?;
+    }
+    renumber(&tx, &new_siblings)?;
+
+    let node = get_node(&tx, &node.id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(node)
+}
+
+/** Result of [`duplicate_node`].
+ *
+ * Attachment files are not touched; the caller copies each
+ * `(source, destination)` pair in `file_copies` once the transaction
+ * has committed.
+ */
+#[derive(Debug)]
+pub struct DuplicatedSubtree {
+    pub node: Node,
+    pub node_ids: Vec<String>,
+    pub attachment_ids: Vec<String>,
+    pub file_copies: Vec<(String, String)>,
+}
+
+/** Copies a node, and its non-trashed descendants when `deep` is set.
+ *
+ * Copies get fresh IDs, their manual tags and copies of their
+ * attachments, with attachment references in the content rewritten to
+ * the copies. The copied root is named "<name> (copy)" and placed right
+ * after the original.
+ */
+pub fn duplicate_node(
+    conn: &mut Connection,
+    id: &str,
+    deep: bool,
+) -> Result<DuplicatedSubtree, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let source = get_node(&tx, id)?;
+    if source.deleted_at.is_some() {
+        return Err("Cannot duplicate a node that is in the trash.".to_string());
+    }
+
+    // Parents come before their children so every copy's parent exists.
+    let originals = if deep {
+        let mut stmt = tx
+            .prepare(&format!(
+                "WITH RECURSIVE subtree(id, depth) AS (
+                    SELECT id, 0 FROM nodes WHERE id = ?1
+                    UNION
+                    SELECT n.id, s.depth + 1 FROM nodes n
+                    JOIN subtree s ON n.parent_id = s.id
+                    WHERE n.deleted_at IS NULL
+                 )
+                 SELECT {} FROM nodes JOIN subtree USING (id) ORDER BY subtree.depth",
+                NODE_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([id], node_from_row)
+            .map_err(|e| format!("Failed to walk node subtree: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read node subtree: {}", e))?
+    } else {
+        vec![source.clone()]
+    };
+
+    let new_ids: HashMap<String, String> = originals
+        .iter()
+        .map(|node| (node.id.clone(), Uuid::new_v4().to_string()))
+        .collect();
+
+    let mut duplicated = DuplicatedSubtree {
+        node: source.clone(),
+        node_ids: Vec::new(),
+        attachment_ids: Vec::new(),
+        file_copies: Vec::new(),
+    };
+
+    for original in &originals {
+        let new_id = &new_ids[&original.id];
+        let (name, parent_id, sort_order) = if original.id == source.id {
+            (
+                format!("{} (copy)", source.name),
+                source.parent_id.clone(),
+                source.sort_order,
+            )
+        } else {
+            (
+                original.name.clone(),
+                original
+                    .parent_id
+                    .as_ref()
+                    .and_then(|parent_id| new_ids.get(parent_id))
+                    .cloned(),
+                original.sort_order,
+            )
+        };
+
+        let rewrites = copy_attachments(&tx, &original.id, new_id, &mut duplicated.file_copies)?;
+        duplicated
+            .attachment_ids
+            .extend(rewrites.values().map(|rewrite| rewrite.id.clone()));
+        let content = match original.content.as_deref() {
+            Some(content) if !rewrites.is_empty() => Some(
+                tiptap::rewrite_attachments(content, &rewrites)
+                    .unwrap_or_else(|| content.to_string()),
+            ),
+            content => content.map(str::to_string),
+        };
+
+        tx.execute(
+            "INSERT INTO nodes
+                (id, space_id, parent_id, name, content, is_open, is_pinned, sort_order,
+                 created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'), datetime('now'))",
+            params![
+                new_id,
+                original.space_id,
+                parent_id,
+                name,
+                content,
+                original.is_open,
+                original.is_pinned,
+                sort_order
+            ],
+        )
+        .map_err(|e| format!("Failed to duplicate node: {}", e))?;
+
+        tx.execute(
+            "INSERT INTO node_tags (node_id, tag_id, source)
+             SELECT ?1, tag_id, source FROM node_tags WHERE node_id = ?2 AND source = 'manual'",
+            params![new_id, original.id],
+        )
+        .map_err(|e| format!("Failed to copy node tags: {}", e))?;
+
+        index_content(&tx, new_id, content.as_deref())?;
+        duplicated.node_ids.push(new_id.clone());
+    }
+
+    let root_id = &new_ids[&source.id];
+    let mut siblings = sibling_ids(&tx, &source.space_id, source.parent_id.as_deref(), root_id)?;
+    let position = siblings
+        .iter()
+        .position(|sibling_id| sibling_id == &source.id)
+        .map_or(siblings.len(), |index| index + 1);
+    siblings.insert(position, root_id.clone());
+    renumber(&tx, &siblings)?;
+
+    duplicated.node = get_node(&tx, root_id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(duplicated)
+}
+
+/** Inserts copies of a node's attachment rows for `new_node_id`.
+ *
+ * Copies get a new ID and share the original's blob, so no file is
+ * copied. Attachments from before content addressing get a new file name
+ * next to the original file instead, with the planned file copies
+ * appended to `file_copies`. Returns the attachment rewrites keyed by
+ * original attachment ID.
+ */
+fn copy_attachments(
+    conn: &Connection,
+    node_id: &str,
+    new_node_id: &str,
+    file_copies: &mut Vec<(String, String)>,
+) -> Result<HashMap<String, AttachmentRewrite>, String> {
+    let attachments = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, filename, file_path, blob_hash FROM attachments WHERE note_id = ?1",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([node_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to list node attachments: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read attachment row: {}", e))?
+    };
+
+    let mut rewrites = HashMap::new();
+    for (attachment_id, filename, file_path, blob_hash) in attachments {
+        let new_id = Uuid::new_v4().to_string();
+        let (new_filename, new_path) = if blob_hash.is_some() {
+            (filename.clone(), file_path.clone())
+        } else {
+            let new_filename = match Path::new(&filename).extension() {
+                Some(extension) => format!("{}.{}", Uuid::new_v4(), extension.to_string_lossy()),
+                None => Uuid::new_v4().to_string(),
+            };
+            let new_path = Path::new(&file_path).with_file_name(&new_filename);
+            let new_path = new_path
+                .to_str()
+                .ok_or("Failed to convert path to string")?
+                .to_string();
+            file_copies.push((file_path, new_path.clone()));
+            (new_filename, new_path)
+        };
+
+        conn.execute(
+            "INSERT INTO attachments
+                (id, note_id, kind, original_name, extension, filename, file_path, size,
+                 mime_type, created_at, blob_hash, width, height, original_id)
+             SELECT ?1, ?2, kind, original_name, extension, ?3, ?4, size, mime_type,
+                 datetime('now'), blob_hash, width, height, original_id
+             FROM attachments WHERE id = ?5",
+            params![new_id, new_node_id, new_filename, new_path, attachment_id],
+        )
+        .map_err(|e| format!("Failed to copy attachment metadata: {}", e))?;
+
+        rewrites.insert(
+            attachment_id,
+            AttachmentRewrite {
+                id: new_id,
+                old_filename: filename,
+                new_filename,
+            },
+        );
+    }
+
+    // Kept originals are copied along with their re-encoded images.
+    for (attachment_id, rewrite) in &rewrites {
+        conn.execute(
+            "UPDATE attachments SET original_id = ?1 WHERE note_id = ?2 AND original_id = ?3",
+            params![rewrite.id, new_node_id, attachment_id],
+        )
+        .map_err(|e| format!("Failed to link copied original: {}", e))?;
+    }
+
+    Ok(rewrites)
+}
+
+/** Rows removed by [`delete_subtree`].
+ *
+ * Attachment files are not touched; `file_paths` lists those no
+ * remaining attachment uses, for the caller to remove once the
+ * transaction has committed.
+ */
+#[derive(Debug, Default)]
+pub struct DeletedSubtree {
+    pub space_ids: Vec<String>,
+    pub node_ids: Vec<String>,
+    pub attachment_ids: Vec<String>,
+    pub file_paths: Vec<String>,
+}
+
+impl DeletedSubtree {
+    pub fn extend(&mut self, other: DeletedSubtree) {
+        for space_id in other.space_ids {
+            if !self.space_ids.contains(&space_id) {
+                self.space_ids.push(space_id);
+            }
+        }
+        self.node_ids.extend(other.node_ids);
+        self.attachment_ids.extend(other.attachment_ids);
+        self.file_paths.extend(other.file_paths);
+    }
+}
+
+/** Returns the IDs of a node and all of its descendants, root first. */
+pub fn subtree_ids(conn: &Connection, id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("{} SELECT id FROM subtree", SUBTREE_CTE))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let ids = stmt
+        .query_map([id], |row| row.get(0))
+        .map_err(|e| format!("Failed to walk node subtree: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node subtree: {}", e))?;
+
+    Ok(ids)
+}
+
+/** Deletes a node, every descendant and their attachment rows in one transaction. */
+pub fn delete_subtree(conn: &mut Connection, id: &str) -> Result<DeletedSubtree, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let deleted = remove_subtree(&tx, id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(deleted)
+}
+
+/** Deletes a subtree and its attachment, revision, link and tag rows.
+ *
+ * Runs on the caller's connection so it can be combined with other
+ * work in a single transaction.
+ */
+pub fn remove_subtree(conn: &Connection, id: &str) -> Result<DeletedSubtree, String> {
+    let node_ids = subtree_ids(conn, id)?;
+    if node_ids.is_empty() {
+        return Err(format!("Node not found: {}", id));
+    }
+
+    let mut deleted = DeletedSubtree {
+        space_ids: vec![get_node(conn, id)?.space_id],
+        node_ids,
+        ..Default::default()
+    };
+
+    {
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} SELECT id, file_path FROM attachments WHERE note_id IN (SELECT id FROM subtree)",
+                SUBTREE_CTE
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt
+            .query_map([id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| format!("Failed to list subtree attachments: {}", e))?;
+
+        for row in rows {
+            let (attachment_id, file_path) =
+                row.map_err(|e| format!("Failed to read attachment row: {}", e))?;
+            deleted.attachment_ids.push(attachment_id);
+            deleted.file_paths.push(file_path);
+        }
+    }
+
+    conn.execute(
+        &format!(
+            "{} DELETE FROM attachments WHERE note_id IN (SELECT id FROM subtree)",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to delete subtree attachments: {}", e))?;
+    // Files shared with attachments outside the subtree stay.
+    deleted.file_paths =
+        attachments::release_unreferenced_files(conn, std::mem::take(&mut deleted.file_paths))?;
+
+    conn.execute(
+        &format!(
+            "{} DELETE FROM node_revisions WHERE node_id IN (SELECT id FROM subtree)",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to delete subtree revisions: {}", e))?;
+
+    conn.execute(
+        &format!(
+            "{} DELETE FROM node_links WHERE source_id IN (SELECT id FROM subtree)",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to delete subtree links: {}", e))?;
+
+    conn.execute(
+        &format!(
+            "{} DELETE FROM node_tags WHERE node_id IN (SELECT id FROM subtree)",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to delete subtree tags: {}", e))?;
+
+    conn.execute(
+        &format!(
+            "{} DELETE FROM nodes WHERE id IN (SELECT id FROM subtree)",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to delete subtree: {}", e))?;
+
+    tags::prune_unused_tags(conn)?;
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::spaces::CreateSpaceRequest;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database, trash,
+    };
+    use serde_json::json;
+
+    fn move_request(
+        node_id: &str,
+        parent_id: Option<&str>,
+        position: Option<i64>,
+    ) -> MoveNodeRequest {
+        MoveNodeRequest {
+            node_id: node_id.to_string(),
+            new_parent_id: parent_id.map(str::to_string),
+            new_space_id: None,
+            position,
+        }
+    }
+
+    /** Names of a sibling list in sort order, with their sort orders. */
+    fn children(conn: &Connection, parent_id: Option<&str>) -> Vec<(String, i64)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, sort_order FROM nodes
+                 WHERE parent_id IS ?1 AND deleted_at IS NULL ORDER BY sort_order",
+            )
+            .unwrap();
+        stmt.query_map([parent_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    fn names(siblings: &[(String, i64)]) -> Vec<&str> {
+        siblings.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn create_node_appends_to_its_sibling_list() {
+        let mut conn = open_test_database();
+        let parent = create_test_node(&mut conn, TEST_SPACE_ID, None, " Parent ", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&parent.id), "a", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&parent.id), "b", None);
+
+        assert_eq!(parent.name, "Parent");
+        assert_eq!(
+            children(&conn, Some(&parent.id)),
+            vec![("a".to_string(), 0), ("b".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn create_node_rejects_a_parent_from_another_space() {
+        let mut conn = open_test_database();
+        let other = spaces::create_space(
+            &conn,
+            &CreateSpaceRequest {
+                id: None,
+                name: "other".to_string(),
+            },
+        )
+        .unwrap();
+        let parent = create_test_node(&mut conn, &other.id, None, "parent", None);
+
+        let result = create_node(
+            &mut conn,
+            &CreateNodeRequest {
+                id: None,
+                space_id: TEST_SPACE_ID.to_string(),
+                parent_id: Some(parent.id),
+                name: "child".to_string(),
+                content: None,
+                is_open: false,
+                is_pinned: false,
+            },
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn update_node_only_touches_supplied_columns() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some("text"));
+        conn.execute(
+            "UPDATE nodes SET updated_at = '2000-01-01 00:00:00' WHERE id = ?1",
+            [&note.id],
+        )
+        .unwrap();
+
+        let updated = update_node(
+            &mut conn,
+            &UpdateNodeRequest {
+                id: note.id.clone(),
+                is_open: Some(true),
+                ..Default::default()
+            },
+            &RevisionSettings::default(),
+        )
+        .unwrap()
+        .node;
+
+        assert!(updated.is_open);
+        assert_eq!(updated.updated_at.as_deref(), Some("2000-01-01 00:00:00"));
+        assert_eq!(updated.content.as_deref(), Some("text"));
+    }
+
+    #[test]
+    fn delete_subtree_removes_descendants_and_their_attachments() {
+        let mut conn = open_test_database();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "child", None);
+        let sibling = create_test_node(&mut conn, TEST_SPACE_ID, None, "sibling", None);
+        let own = insert_test_attachment(&conn, &child.id, "/images/own.png", Some("own"));
+        insert_test_attachment(&conn, &child.id, "/images/shared.png", Some("shared"));
+        insert_test_attachment(&conn, &sibling.id, "/images/shared.png", Some("shared"));
+
+        let deleted = delete_subtree(&mut conn, &root.id).unwrap();
+
+        assert_eq!(deleted.node_ids, vec![root.id.clone(), child.id.clone()]);
+        assert_eq!(deleted.space_ids, vec![TEST_SPACE_ID.to_string()]);
+        assert_eq!(deleted.attachment_ids.len(), 2);
+        assert!(deleted.attachment_ids.contains(&own.id));
+        // The shared file is still used by the sibling's attachment.
+        assert_eq!(deleted.file_paths, vec!["/images/own.png".to_string()]);
+        assert!(get_node(&conn, &child.id).is_err());
+        assert!(attachments::get_blob(&conn, "own").unwrap().is_none());
+        assert_eq!(
+            attachments::get_blob(&conn, "shared")
+                .unwrap()
+                .unwrap()
+                .ref_count,
+            1
+        );
+        assert!(delete_subtree(&mut conn, &root.id).is_err());
+    }
+
+    #[test]
+    fn update_node_rejects_trashed_nodes() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some("text"));
+        trash::trash_node(&mut conn, &note.id).unwrap();
+
+        let result = update_node(
+            &mut conn,
+            &UpdateNodeRequest {
+                id: note.id.clone(),
+                content: Some("edited".to_string()),
+                ..Default::default()
+            },
+            &RevisionSettings::default(),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            get_node(&conn, &note.id).unwrap().content.as_deref(),
+            Some("text")
+        );
+    }
+
+    #[test]
+    fn move_node_reorders_within_its_siblings() {
+        let mut conn = open_test_database();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "b", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "c", None);
+
+        move_node(&mut conn, &move_request(&a.id, None, Some(2))).unwrap();
+        assert_eq!(
+            children(&conn, None),
+            vec![
+                ("b".to_string(), 0),
+                ("c".to_string(), 1),
+                ("a".to_string(), 2)
+            ]
+        );
+
+        // Out-of-range positions are clamped.
+        move_node(&mut conn, &move_request(&a.id, None, Some(-5))).unwrap();
+        assert_eq!(names(&children(&conn, None)), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn move_node_renumbers_both_sibling_lists() {
+        let mut conn = open_test_database();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let b = create_test_node(&mut conn, TEST_SPACE_ID, None, "b", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "c", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&a.id), "a1", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&a.id), "a2", None);
+
+        let moved = move_node(&mut conn, &move_request(&b.id, Some(&a.id), Some(1))).unwrap();
+
+        assert_eq!(moved.parent_id.as_deref(), Some(a.id.as_str()));
+        assert_eq!(
+            children(&conn, None),
+            vec![("a".to_string(), 0), ("c".to_string(), 1)]
+        );
+        assert_eq!(
+            children(&conn, Some(&a.id)),
+            vec![
+                ("a1".to_string(), 0),
+                ("b".to_string(), 1),
+                ("a2".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn move_node_refuses_to_create_a_cycle() {
+        let mut conn = open_test_database();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&a.id), "child", None);
+        let grandchild = create_test_node(&mut conn, TEST_SPACE_ID, Some(&child.id), "gc", None);
+
+        assert!(move_node(&mut conn, &move_request(&a.id, Some(&a.id), None)).is_err());
+        assert!(move_node(&mut conn, &move_request(&a.id, Some(&grandchild.id), None)).is_err());
+        assert_eq!(get_node(&conn, &a.id).unwrap().parent_id, None);
+    }
+
+    #[test]
+    fn move_node_carries_the_subtree_to_another_space() {
+        let mut conn = open_test_database();
+        let other = spaces::create_space(
+            &conn,
+            &CreateSpaceRequest {
+                id: None,
+                name: "other".to_string(),
+            },
+        )
+        .unwrap();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&a.id), "child", None);
+        let stay = create_test_node(&mut conn, TEST_SPACE_ID, None, "stay", None);
+
+        let moved = move_node(
+            &mut conn,
+            &MoveNodeRequest {
+                new_space_id: Some(other.id.clone()),
+                ..move_request(&a.id, None, None)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(moved.space_id, other.id);
+        assert_eq!(get_node(&conn, &child.id).unwrap().space_id, other.id);
+        assert_eq!(get_node(&conn, &stay.id).unwrap().sort_order, 0);
+
+        // A parent must be in the target space.
+        assert!(
+            move_node(
+                &mut conn,
+                &MoveNodeRequest {
+                    new_space_id: Some(TEST_SPACE_ID.to_string()),
+                    ..move_request(&stay.id, Some(&a.id), None)
+                },
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn duplicate_node_copies_the_subtree_after_the_original() {
+        let mut conn = open_test_database();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "next", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "child", None);
+        create_test_node(
+            &mut conn,
+            TEST_SPACE_ID,
+            Some(&child.id),
+            "grandchild",
+            None,
+        );
+        let trashed = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "gone", None);
+        trash::trash_node(&mut conn, &trashed.id).unwrap();
+        tags::add_tag(&mut conn, &root.id, "manual").unwrap();
+
+        let duplicated = duplicate_node(&mut conn, &root.id, true).unwrap();
+
+        assert_eq!(duplicated.node.name, "root (copy)");
+        assert_eq!(duplicated.node_ids.len(), 3);
+        assert_eq!(
+            names(&children(&conn, None)),
+            vec!["root", "root (copy)", "next"]
+        );
+        let copied_children = children(&conn, Some(&duplicated.node.id));
+        assert_eq!(names(&copied_children), vec!["child"]);
+        let copied_child = &list_nodes(&conn, TEST_SPACE_ID)
+            .unwrap()
+            .into_iter()
+            .find(|node| node.parent_id.as_deref() == Some(duplicated.node.id.as_str()))
+            .unwrap();
+        assert_ne!(copied_child.id, child.id);
+        assert_eq!(
+            names(&children(&conn, Some(&copied_child.id))),
+            vec!["grandchild"]
+        );
+        let copied_tags = tags::get_node_tags(&conn, &duplicated.node.id).unwrap();
+        assert_eq!(copied_tags.len(), 1);
+
+        let shallow = duplicate_node(&mut conn, &root.id, false).unwrap();
+        assert_eq!(shallow.node_ids, vec![shallow.node.id.clone()]);
+    }
+
+    #[test]
+    fn duplicate_node_points_content_at_copied_attachments() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let shared = insert_test_attachment(&conn, &note.id, "/images/blob.png", Some("hash"));
+        let legacy = insert_test_attachment(&conn, &note.id, "/images/legacy.png", None);
+        let content = json!({ "type": "doc", "content": [
+            { "type": "image", "attrs": { "attachmentId": shared.id, "src": "asset://localhost/%2Fimages%2Fblob.png" } },
+            { "type": "image", "attrs": { "attachmentId": legacy.id, "src": "asset://localhost/%2Fimages%2Flegacy.png" } }
+        ] })
+        .to_string();
+        conn.execute(
+            "UPDATE nodes SET content = ?1 WHERE id = ?2",
+            params![content, note.id],
+        )
+        .unwrap();
+
+        let duplicated = duplicate_node(&mut conn, &note.id, false).unwrap();
+
+        let copies = attachments::list_attachments(&conn, &duplicated.node.id).unwrap();
+        assert_eq!(copies.len(), 2);
+        let shared_copy = copies.iter().find(|copy| copy.blob_hash.is_some()).unwrap();
+        let legacy_copy = copies.iter().find(|copy| copy.blob_hash.is_none()).unwrap();
+        // Content-addressed files are shared; legacy ones are copied.
+        assert_eq!(shared_copy.file_path, "/images/blob.png");
+        assert_eq!(
+            attachments::get_blob(&conn, "hash")
+                .unwrap()
+                .unwrap()
+                .ref_count,
+            2
+        );
+        assert_eq!(
+            duplicated.file_copies,
+            vec![(
+                "/images/legacy.png".to_string(),
+                legacy_copy.file_path.clone()
+            )]
+        );
+
+        let copied: serde_json::Value =
+            serde_json::from_str(duplicated.node.content.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            copied["content"][0]["attrs"]["attachmentId"],
+            shared_copy.id
+        );
+        assert_eq!(
+            copied["content"][1]["attrs"]["attachmentId"],
+            legacy_copy.id
+        );
+        assert_eq!(
+            copied["content"][1]["attrs"]["src"],
+            format!("asset://localhost/%2Fimages%2F{}", legacy_copy.filename)
+        );
+    }
+
+    #[test]
+    fn save_node_content_rejects_stale_revisions() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let settings = RevisionSettings::default();
+
+        let saved =
+            save_node_content(&mut conn, &note.id, note.revision, "first", &settings).unwrap();
+        assert_eq!(saved.revision, note.revision + 1);
+
+        match save_node_content(&mut conn, &note.id, note.revision, "stale", &settings) {
+            Err(SaveNodeError::Conflict { current }) => {
+                assert_eq!(current.content.as_deref(), Some("first"));
+                assert_eq!(current.revision, saved.revision);
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(
+            get_node(&conn, &note.id).unwrap().content.as_deref(),
+            Some("first")
+        );
+        assert!(matches!(
+            save_node_content(&mut conn, "missing", 0, "x", &settings),
+            Err(SaveNodeError::Failed { .. })
+        ));
+    }
+
+    #[test]
+    fn save_node_content_rejects_trashed_nodes() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some("text"));
+        trash::trash_node(&mut conn, &note.id).unwrap();
+        let revision = get_node(&conn, &note.id).unwrap().revision;
+
+        let result = save_node_content(
+            &mut conn,
+            &note.id,
+            revision,
+            "edited",
+            &RevisionSettings::default(),
+        );
+
+        assert!(matches!(result, Err(SaveNodeError::Failed { .. })));
+        assert_eq!(
+            get_node(&conn, &note.id).unwrap().content.as_deref(),
+            Some("text")
+        );
+    }
+}
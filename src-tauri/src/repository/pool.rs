@@ -0,0 +1,169 @@
+use super::{open_database, open_database_read_only};
+use crate::config::StorageLocation;
+use rusqlite::Connection;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, RwLock};
+
+/** Idle connections kept open for reuse; extra ones are closed when returned. */
+const MAX_IDLE_CONNECTIONS: usize = 4;
+
+/** Pool of configured connections to the database of one storage location.
+ *
+ * Connections are opened on demand when none are idle, and concurrent
+ * writers wait on SQLite's busy timeout instead of failing.
+ */
+pub struct DbPool {
+    location: StorageLocation,
+    read_only: bool,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl DbPool {
+    /** Opens the database of `location`, failing early if it cannot be configured. */
+    pub fn open(location: StorageLocation) -> Result<Arc<Self>, String> {
+        Self::open_with(location, false)
+    }
+
+    /** Opens the database of `location` for reading only, as recovery mode does. */
+    pub fn open_read_only(location: StorageLocation) -> Result<Arc<Self>, String> {
+        Self::open_with(location, true)
+    }
+
+    fn open_with(location: StorageLocation, read_only: bool) -> Result<Arc<Self>, String> {
+        let pool = Self {
+            location,
+            read_only,
+            idle: Mutex::new(Vec::new()),
+        };
+        let conn = pool.connect()?;
+        pool.release(conn);
+
+        Ok(Arc::new(pool))
+    }
+
+    pub fn location(&self) -> &StorageLocation {
+        &self.location
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn connect(&self) -> Result<Connection, String> {
+        let path = self.location.database_path();
+        if self.read_only {
+            open_database_read_only(&path)
+        } else {
+            open_database(&path, !self.location.is_shared())
+        }
+    }
+
+    /** Takes an idle connection, or opens a new one when all are in use. */
+    pub fn get(self: &Arc<Self>) -> Result<PooledConnection, String> {
+        let idle = self
+            .idle
+            .lock()
+            .map_err(|_| "Database pool is poisoned.".to_string())?
+            .pop();
+
+        let conn = match idle {
+            Some(conn) => conn,
+            None => self.connect()?,
+        };
+
+        Ok(PooledConnection {
+            pool: Arc::clone(self),
+            conn: Some(conn),
+        })
+    }
+
+    fn release(&self, conn: Connection) {
+        // A connection returned mid-transaction would leak its locks to the
+        // next caller; close it instead.
+        if !conn.is_autocommit() {
+            return;
+        }
+
+        if let Ok(mut idle) = self.idle.lock()
+            && idle.len() < MAX_IDLE_CONNECTIONS
+        {
+            idle.push(conn);
+        }
+    }
+}
+
+/** Connection borrowed from a `DbPool`, returned to it when dropped.
+ *
+ * Holds on to its pool, so a connection taken before a vault switch keeps
+ * working against the old database until it is dropped.
+ */
+pub struct PooledConnection {
+    pool: Arc<DbPool>,
+    conn: Option<Connection>,
+}
+
+impl PooledConnection {
+    /** The storage location of the database this connection belongs to. */
+    pub fn location(&self) -> &StorageLocation {
+        self.pool.location()
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+            .as_ref()
+            .expect("pooled connection already released")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn
+            .as_mut()
+            .expect("pooled connection already released")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn);
+        }
+    }
+}
+
+/** The open vault's connection pool, registered as managed state in `main.rs`.
+ *
+ * Every command borrows its connection through here; switching vaults
+ * swaps the pool without restarting the app.
+ */
+pub struct Database {
+    pool: RwLock<Arc<DbPool>>,
+}
+
+impl Database {
+    pub fn new(pool: Arc<DbPool>) -> Self {
+        Self {
+            pool: RwLock::new(pool),
+        }
+    }
+
+    /** Returns the pool of the currently open vault. */
+    pub fn pool(&self) -> Arc<DbPool> {
+        match self.pool.read() {
+            Ok(pool) => Arc::clone(&pool),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /** Makes `pool` the current one. Connections still borrowed from the old pool stay valid. */
+    pub fn replace(&self, pool: Arc<DbPool>) {
+        match self.pool.write() {
+            Ok(mut current) => *current = pool,
+            Err(poisoned) => *poisoned.into_inner() = pool,
+        }
+    }
+}
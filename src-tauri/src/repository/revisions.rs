@@ -0,0 +1,341 @@
+use super::nodes;
+use crate::models::nodes::Node;
+use crate::models::revisions::{NodeRevision, NodeRevisionSummary, RevisionSettings};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use uuid::Uuid;
+
+fn revision_from_row(row: &Row) -> rusqlite::Result<NodeRevision> {
+    Ok(NodeRevision {
+        id: row.get("id")?,
+        node_id: row.get("node_id")?,
+        name: row.get("name")?,
+        content: row.get("content")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn insert_revision(conn: &Connection, node: &Node, timestamp: Option<&str>) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO node_revisions (id, node_id, name, content, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, COALESCE(?5, datetime('now')), COALESCE(?5, datetime('now')))",
+        params![
+            Uuid::new_v4().to_string(),
+            node.id,
+            node.name,
+            node.content,
+            timestamp
+        ],
+    )
+    .map_err(|e| format!("Failed to insert revision: {}", e))?;
+
+    Ok(())
+}
+
+/** Snapshots a node's content if it has no revisions yet.
+ *
+ * Called with the pre-update node so notes written before revision
+ * history existed keep their original content as the first revision.
+ * Returns whether a snapshot was taken.
+ */
+pub fn snapshot_untracked(conn: &Connection, node: &Node) -> Result<bool, String> {
+    let has_revisions: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM node_revisions WHERE node_id = ?1)",
+            [&node.id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to check revisions: {}", e))?;
+
+    let has_content = node
+        .content
+        .as_deref()
+        .is_some_and(|c| !c.trim().is_empty());
+    if has_revisions || !has_content {
+        return Ok(false);
+    }
+
+    insert_revision(conn, node, node.updated_at.as_deref())?;
+    Ok(true)
+}
+
+/** Records the node's current content as a revision and applies retention.
+ *
+ * When `coalesce` is set and the latest revision was updated within the
+ * configured window, that revision is overwritten instead, so rapid
+ * successive saves collapse into a single entry.
+ */
+pub fn record_revision(
+    conn: &Connection,
+    node: &Node,
+    settings: &RevisionSettings,
+    coalesce: bool,
+) -> Result<(), String> {
+    let recent_id: Option<String> = if coalesce && settings.coalesce_seconds > 0 {
+        conn.query_row(
+            "SELECT id FROM node_revisions
+             WHERE node_id = ?1 AND updated_at >= datetime('now', ?2)
+             ORDER BY updated_at DESC, rowid DESC
+             LIMIT 1",
+            params![node.id, format!("-{} seconds", settings.coalesce_seconds)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to find recent revision: {}", e))?
+    } else {
+        None
+    };
+
+    match recent_id {
+        Some(id) => {
+            conn.execute(
+                "UPDATE node_revisions
+                 SET name = ?1, content = ?2, updated_at = datetime('now')
+                 WHERE id = ?3",
+                params![node.name, node.content, id],
+            )
+            .map_err(|e| format!("Failed to update revision: {}", e))?;
+        }
+        None => insert_revision(conn, node, None)?,
+    }
+
+    prune_revisions(conn, &node.id, settings)
+}
+
+/** Deletes revisions that fall outside the retention settings. */
+pub fn prune_revisions(
+    conn: &Connection,
+    node_id: &str,
+    settings: &RevisionSettings,
+) -> Result<(), String> {
+    if let Some(days) = settings.daily_after_days {
+        conn.execute(
+            "DELETE FROM node_revisions
+             WHERE node_id = ?1
+               AND updated_at < datetime('now', ?2)
+               AND id NOT IN (
+                   SELECT id FROM (
+                       SELECT id, ROW_NUMBER() OVER (
+                           PARTITION BY date(updated_at)
+                           ORDER BY updated_at DESC, rowid DESC
+                       ) AS day_rank
+                       FROM node_revisions
+                       WHERE node_id = ?1 AND updated_at < datetime('now', ?2)
+                   )
+                   WHERE day_rank = 1
+               )",
+            params![node_id, format!("-{} days", days)],
+        )
+        .map_err(|e| format!("Failed to thin old revisions: {}", e))?;
+    }
+
+    if let Some(keep_last) = settings.keep_last {
+        conn.execute(
+            "DELETE FROM node_revisions
+             WHERE node_id = ?1
+               AND id NOT IN (
+                   SELECT id FROM node_revisions
+                   WHERE node_id = ?1
+                   ORDER BY updated_at DESC, rowid DESC
+                   LIMIT ?2
+               )",
+            params![node_id, keep_last],
+        )
+        .map_err(|e| format!("Failed to prune revisions: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/** Lists a node's revisions, newest first. */
+pub fn list_revisions(
+    conn: &Connection,
+    node_id: &str,
+) -> Result<Vec<NodeRevisionSummary>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, node_id, name, COALESCE(length(content), 0), created_at, updated_at
+             FROM node_revisions
+             WHERE node_id = ?1
+             ORDER BY updated_at DESC, rowid DESC",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let revisions = stmt
+        .query_map([node_id], |row| {
+            Ok(NodeRevisionSummary {
+                id: row.get(0)?,
+                node_id: row.get(1)?,
+                name: row.get(2)?,
+                size: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list revisions: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read revision: {}", e))?;
+
+    Ok(revisions)
+}
+
+/** Fetches a single revision with its content. */
+pub fn get_revision(conn: &Connection, revision_id: &str) -> Result<NodeRevision, String> {
+    conn.query_row(
+        "SELECT id, node_id, name, content, created_at, updated_at
+         FROM node_revisions WHERE id = ?1",
+        [revision_id],
+        revision_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get revision: {}", e))?
+    .ok_or_else(|| format!("Revision not found: {}", revision_id))
+}
+
+/** Restores a node's name and content from a revision.
+ *
+ * The restored state is recorded as a new revision so the restore can
+ * itself be undone.
+ */
+pub fn restore_revision(
+    conn: &mut Connection,
+    revision_id: &str,
+    settings: &RevisionSettings,
+) -> Result<Node, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let revision = get_revision(&tx, revision_id)?;
+    let previous = nodes::get_node(&tx, &revision.node_id)?;
+    snapshot_untracked(&tx, &previous)?;
+
+    tx.execute(
+        "UPDATE nodes SET name = ?1, content = ?2 WHERE id = ?3",
+        params![revision.name, revision.content, revision.node_id],
+    )
+    .map_err(|e| format!("Failed to restore revision: {}", e))?;
+
+    let node = nodes::get_node(&tx, &revision.node_id)?;
+    record_revision(&tx, &node, settings, false)?;
+    nodes::index_content(&tx, &node.id, node.content.as_deref())?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::nodes::UpdateNodeRequest;
+    use crate::repository::{TEST_SPACE_ID, create_test_node, open_test_database};
+
+    fn set_content(conn: &mut Connection, id: &str, content: &str, settings: &RevisionSettings) {
+        nodes::update_node(
+            conn,
+            &UpdateNodeRequest {
+                id: id.to_string(),
+                content: Some(content.to_string()),
+                ..Default::default()
+            },
+            settings,
+        )
+        .unwrap();
+    }
+
+    fn contents(conn: &Connection, node_id: &str) -> Vec<Option<String>> {
+        list_revisions(conn, node_id)
+            .unwrap()
+            .into_iter()
+            .map(|summary| get_revision(conn, &summary.id).unwrap().content)
+            .collect()
+    }
+
+    fn some(values: &[&str]) -> Vec<Option<String>> {
+        values.iter().map(|value| Some(value.to_string())).collect()
+    }
+
+    #[test]
+    fn rapid_saves_coalesce_into_one_revision() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some("v0"));
+        let settings = RevisionSettings::default();
+
+        set_content(&mut conn, &note.id, "v1", &settings);
+        set_content(&mut conn, &note.id, "v2", &settings);
+
+        // The untracked original is kept apart from the coalesced edits.
+        assert_eq!(contents(&conn, &note.id), some(&["v2", "v0"]));
+    }
+
+    #[test]
+    fn every_save_is_kept_without_coalescing() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let settings = RevisionSettings {
+            coalesce_seconds: 0,
+            keep_last: Some(2),
+            daily_after_days: None,
+        };
+
+        for content in ["v1", "v2", "v3"] {
+            set_content(&mut conn, &note.id, content, &settings);
+        }
+
+        assert_eq!(contents(&conn, &note.id), some(&["v3", "v2"]));
+    }
+
+    #[test]
+    fn prune_revisions_keeps_one_old_revision_per_day() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        for (content, timestamp) in [
+            ("day1-early", "2020-01-01 08:00:00"),
+            ("day1-late", "2020-01-01 18:00:00"),
+            ("day2", "2020-01-02 12:00:00"),
+        ] {
+            let node = Node {
+                content: Some(content.to_string()),
+                ..note.clone()
+            };
+            insert_revision(&conn, &node, Some(timestamp)).unwrap();
+        }
+        let node = Node {
+            content: Some("today".to_string()),
+            ..note.clone()
+        };
+        insert_revision(&conn, &node, None).unwrap();
+
+        let settings = RevisionSettings {
+            coalesce_seconds: 0,
+            keep_last: None,
+            daily_after_days: Some(7),
+        };
+        prune_revisions(&conn, &note.id, &settings).unwrap();
+
+        assert_eq!(
+            contents(&conn, &note.id),
+            some(&["today", "day2", "day1-late"])
+        );
+    }
+
+    #[test]
+    fn restore_revision_records_the_restored_state() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some("v0"));
+        let settings = RevisionSettings {
+            coalesce_seconds: 0,
+            ..RevisionSettings::default()
+        };
+        set_content(&mut conn, &note.id, "v1", &settings);
+        let original = list_revisions(&conn, &note.id).unwrap().pop().unwrap();
+
+        let restored = restore_revision(&mut conn, &original.id, &settings).unwrap();
+
+        assert_eq!(restored.content.as_deref(), Some("v0"));
+        assert_eq!(contents(&conn, &note.id), some(&["v0", "v1", "v0"]));
+        assert!(restore_revision(&mut conn, "missing", &settings).is_err());
+    }
+}
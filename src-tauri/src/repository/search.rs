@@ -0,0 +1,254 @@
+use super::nodes;
+use crate::config::{SEARCH_DEFAULT_LIMIT, SEARCH_MAX_LIMIT, SEARCH_SNIPPET_TOKENS};
+use crate::models::search::{SearchHit, SearchNotesRequest, SnippetSegment};
+use rusqlite::{Connection, params};
+
+// Private-use characters that cannot appear in extracted note text, used
+// to delimit highlighted terms in FTS5 snippets.
+const HIGHLIGHT_START: char = '\u{E000}';
+const HIGHLIGHT_END: char = '\u{E001}';
+
+/** Converts user input into an FTS5 MATCH expression.
+ *
+ * Every term is quoted so FTS5 operators in the input are treated as
+ * text. A trailing `*` on a word or phrase turns it into a prefix query.
+ * Returns `None` when the input contains no searchable terms.
+ */
+fn build_match_query(input: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut term = String::new();
+        if c == '"' {
+            chars.next();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                term.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                term.push(c);
+                chars.next();
+            }
+        }
+
+        let mut is_prefix = false;
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            is_prefix = true;
+        }
+        if term.ends_with('*') {
+            term = term.trim_end_matches('*').to_string();
+            is_prefix = true;
+        }
+
+        let term = term.trim();
+        if term.is_empty() {
+            continue;
+        }
+
+        let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+        terms.push(if is_prefix {
+            format!("{}*", quoted)
+        } else {
+            quoted
+        });
+    }
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/** Splits an FTS5 snippet into plain and highlighted segments. */
+fn split_snippet(snippet: &str) -> Vec<SnippetSegment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut highlighted = false;
+
+    for c in snippet.chars() {
+        if c != HIGHLIGHT_START && c != HIGHLIGHT_END {
+            current.push(c);
+            continue;
+        }
+
+        if !current.is_empty() {
+            segments.push(SnippetSegment {
+                text: std::mem::take(&mut current),
+                highlighted,
+            });
+        }
+        highlighted = c == HIGHLIGHT_START;
+    }
+
+    if !current.is_empty() {
+        segments.push(SnippetSegment {
+            text: current,
+            highlighted,
+        });
+    }
+
+    segments
+}
+
+/** Runs a ranked full-text search over node names and content.
+ *
+ * Name matches are weighted above body matches.
+ */
+pub fn search_notes(
+    conn: &Connection,
+    request: &SearchNotesRequest,
+) -> Result<Vec<SearchHit>, String> {
+    let Some(match_query) = build_match_query(&request.query) else {
+        return Ok(Vec::new());
+    };
+
+    let limit = request
+        .limit
+        .unwrap_or(SEARCH_DEFAULT_LIMIT)
+        .clamp(1, SEARCH_MAX_LIMIT);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT n.id, n.space_id, s.name, n.name,
+                    snippet(nodes_fts, -1, ?3, ?4, '…', ?5),
+                    bm25(nodes_fts, 0.0, 5.0, 1.0) AS score
+             FROM nodes_fts
+             JOIN nodes n ON n.id = nodes_fts.node_id
+             JOIN spaces s ON s.id = n.space_id
+             WHERE nodes_fts MATCH ?1
+               AND n.deleted_at IS NULL
+               AND (?2 IS NULL OR n.space_id = ?2)
+             ORDER BY score
+             LIMIT ?6",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let rows = stmt
+        .query_map(
+            params![
+                match_query,
+                request.space_id,
+                HIGHLIGHT_START.to_string(),
+                HIGHLIGHT_END.to_string(),
+                SEARCH_SNIPPET_TOKENS,
+                limit
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, f64>(5)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Failed to search notes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read search result: {}", e))?;
+
+    rows.into_iter()
+        .map(|(node_id, space_id, space_name, name, snippet, score)| {
+            Ok(SearchHit {
+                path: nodes::node_path(conn, &node_id)?,
+                node_id,
+                space_id,
+                space_name,
+                name,
+                snippet: split_snippet(&snippet),
+                // bm25 returns lower-is-better negative scores.
+                score: -score,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{TEST_SPACE_ID, create_test_node, open_test_database, trash};
+    use serde_json::json;
+
+    fn text_doc(text: &str) -> String {
+        json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [{ "type": "text", "text": text }] }
+        ] })
+        .to_string()
+    }
+
+    fn search(conn: &Connection, query: &str) -> Vec<SearchHit> {
+        search_notes(
+            conn,
+            &SearchNotesRequest {
+                query: query.to_string(),
+                space_id: None,
+                limit: None,
+            },
+        )
+        .unwrap()
+    }
+
+    fn names(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.name.as_str()).collect()
+    }
+
+    #[test]
+    fn build_match_query_quotes_every_term() {
+        assert_eq!(
+            build_match_query(r#"rust OR "exact phrase" pre* "phr"*"#).as_deref(),
+            Some(r#""rust" "OR" "exact phrase" "pre"* "phr"*"#)
+        );
+        assert_eq!(
+            build_match_query(r#"say"hi" a"b"#).as_deref(),
+            Some(r#""say" "hi" "a" "b""#)
+        );
+        assert_eq!(build_match_query("  * \"\" "), None);
+    }
+
+    #[test]
+    fn split_snippet_marks_highlighted_terms() {
+        let snippet = format!("a {}match{} b", HIGHLIGHT_START, HIGHLIGHT_END);
+
+        let segments = split_snippet(&snippet);
+
+        let parts: Vec<(&str, bool)> = segments
+            .iter()
+            .map(|segment| (segment.text.as_str(), segment.highlighted))
+            .collect();
+        assert_eq!(parts, vec![("a ", false), ("match", true), (" b", false)]);
+    }
+
+    #[test]
+    fn search_notes_ranks_name_matches_first() {
+        let mut conn = open_test_database();
+        let parent = create_test_node(&mut conn, TEST_SPACE_ID, None, "parent", None);
+        let body = text_doc("notes about a garden");
+        create_test_node(&mut conn, TEST_SPACE_ID, None, "body match", Some(&body));
+        create_test_node(&mut conn, TEST_SPACE_ID, Some(&parent.id), "Garden", None);
+        let trashed = create_test_node(&mut conn, TEST_SPACE_ID, None, "garden trash", None);
+        trash::trash_node(&mut conn, &trashed.id).unwrap();
+
+        let hits = search(&conn, "gard*");
+
+        assert_eq!(names(&hits), vec!["Garden", "body match"]);
+        assert_eq!(hits[0].path, vec!["parent".to_string()]);
+        assert!(hits[0].score > hits[1].score);
+        assert!(hits[1].snippet.iter().any(|segment| segment.highlighted));
+        assert!(search(&conn, "   ").is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use super::nodes::{self, DeletedSubtree};
+use super::resolve_id;
+use crate::models::spaces::{CreateSpaceRequest, Space};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+
+fn space_from_row(row: &Row) -> rusqlite::Result<Space> {
+    Ok(Space {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        sort_order: row.get("sort_order")?,
+    })
+}
+
+fn normalize_name(name: &str) -> Result<String, String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err("Space name is required.".to_string());
+    }
+
+    Ok(trimmed.to_string())
+}
+
+/** Lists all spaces in display order. */
+pub fn list_spaces(conn: &Connection) -> Result<Vec<Space>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, sort_order FROM spaces ORDER BY sort_order, name")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let spaces = stmt
+        .query_map([], space_from_row)
+        .map_err(|e| format!("Failed to list spaces: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read space: {}", e))?;
+
+    Ok(spaces)
+}
+
+/** Fetches a single space by ID. */
+pub fn get_space(conn: &Connection, id: &str) -> Result<Space, String> {
+    conn.query_row(
+        "SELECT id, name, sort_order FROM spaces WHERE id = ?1",
+        [id],
+        space_from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to get space: {}", e))?
+    .ok_or_else(|| format!("Space not found: {}", id))
+}
+
+/** Creates a space after the last existing one. */
+pub fn create_space(conn: &Connection, request: &CreateSpaceRequest) -> Result<Space, String> {
+    let id = resolve_id(request.id.as_deref())?;
+    let name = normalize_name(&request.name)?;
+
+    conn.execute(
+        "INSERT INTO spaces (id, name, sort_order)
+         VALUES (?1, ?2, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM spaces))",
+        params![id, name],
+    )
+    .map_err(|e| format!("Failed to create space: {}", e))?;
+
+    get_space(conn, &id)
+}
+
+/** Renames a space. */
+pub fn rename_space(conn: &Connection, id: &str, name: &str) -> Result<Space, String> {
+    let name = normalize_name(name)?;
+
+    let updated = conn
+        .execute("UPDATE spaces SET name = ?1 WHERE id = ?2", params![name, id])
+        .map_err(|e| format!("Failed to rename space: {}", e))?;
+
+    if updated == 0 {
+        return Err(format!("Space not found: {}", id));
+    }
+
+    get_space(conn, id)
+}
+
+/** Deletes a space together with every node it contains.
+ *
+ * Each subtree goes through [`nodes::remove_subtree`], so attachment
+ * rows and blobs go with their notes. Attachment files are left for the
+ * caller to remove once the transaction has committed.
+ */
+pub fn delete_space(conn: &mut Connection, id: &str) -> Result<DeletedSubtree, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    get_space(&tx, id)?;
+
+    let mut deleted = DeletedSubtree {
+        space_ids: vec![id.to_string()],
+        ..Default::default()
+    };
+    // Roots first; nodes stuck in a parent cycle have none and go last.
+    while let Some(node_id) = tx
+        .query_row(
+            "SELECT id FROM nodes WHERE space_id = ?1
+             ORDER BY parent_id IS NOT NULL, id LIMIT 1",
+            [id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to list space nodes: {}", e))?
+    {
+        deleted.extend(nodes::remove_subtree(&tx, &node_id)?);
+    }
+
+    tx.execute("DELETE FROM spaces WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete space: {}", e))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, attachments, create_test_node, insert_test_attachment, open_test_database,
+    };
+
+    #[test]
+    fn delete_space_removes_its_nodes_and_releases_attachments() {
+        let mut conn = open_test_database();
+        let space = create_space(
+            &conn,
+            &CreateSpaceRequest {
+                id: None,
+                name: "doomed".to_string(),
+            },
+        )
+        .unwrap();
+        let root = create_test_node(&mut conn, &space.id, None, "root", None);
+        let child = create_test_node(&mut conn, &space.id, Some(&root.id), "child", None);
+        let kept = create_test_node(&mut conn, TEST_SPACE_ID, None, "kept", None);
+        insert_test_attachment(&conn, &child.id, "/images/own.png", Some("own"));
+        insert_test_attachment(&conn, &child.id, "/images/shared.png", Some("shared"));
+        insert_test_attachment(&conn, &kept.id, "/images/shared.png", Some("shared"));
+
+        let deleted = delete_space(&mut conn, &space.id).unwrap();
+
+        assert_eq!(deleted.space_ids, vec![space.id.clone()]);
+        assert_eq!(deleted.node_ids, vec![root.id.clone(), child.id.clone()]);
+        assert_eq!(deleted.attachment_ids.len(), 2);
+        assert_eq!(deleted.file_paths, vec!["/images/own.png".to_string()]);
+        assert!(get_space(&conn, &space.id).is_err());
+        assert!(attachments::get_blob(&conn, "own").unwrap().is_none());
+        let remaining = attachments::list_attachments(&conn, &kept.id).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(delete_space(&mut conn, &space.id).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+use super::nodes::{self, NODE_COLUMNS, node_from_row};
+use crate::models::nodes::Node;
+use crate::models::tags::{ListNodesByTagsRequest, NodeTag, TagCount, TagMatch};
+use crate::utils::tiptap;
+use rusqlite::{Connection, params, params_from_iter};
+
+const SOURCE_INLINE: &str = "inline";
+const SOURCE_MANUAL: &str = "manual";
+
+// Matches tag `t` against the tag name in parameter `?N`, including its
+// descendants. `substr` is used instead of LIKE because tag names may
+// contain `_`.
+fn tag_matches(param: usize) -> String {
+    format!(
+        "(t.name = ?{0} OR substr(t.name, 1, length(?{0}) + 1) = ?{0} || '/')",
+        param
+    )
+}
+
+fn validate_tag(name: &str) -> Result<String, String> {
+    tiptap::normalize_tag(name).ok_or_else(|| {
+        format!(
+            "Invalid tag: {}. Tags may contain letters, digits, '_', '-' and '/'.",
+            name
+        )
+    })
+}
+
+/** Creates a tag and its ancestors if missing and returns the tag's ID. */
+fn ensure_tag(conn: &Connection, name: &str) -> Result<i64, String> {
+    let mut path = String::new();
+    for segment in name.split('/') {
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path.push_str(segment);
+
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name, created_at) VALUES (?1, datetime('now'))",
+            [&path],
+        )
+        .map_err(|e| format!("Failed to create tag: {}", e))?;
+    }
+
+    conn.query_row("SELECT id FROM tags WHERE name = ?1", [name], |row| {
+        row.get(0)
+    })
+    .map_err(|e| format!("Failed to get tag: {}", e))
+}
+
+/** Deletes tags that neither they nor any descendant are applied to a node. */
+pub fn prune_unused_tags(conn: &Connection) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM tags
+         WHERE NOT EXISTS (
+             SELECT 1 FROM node_tags nt
+             JOIN tags u ON u.id = nt.tag_id
+             WHERE u.name = tags.name
+                OR substr(u.name, 1, length(tags.name) + 1) = tags.name || '/'
+         )",
+        [],
+    )
+    .map_err(|e| format!("Failed to prune tags: {}", e))?;
+
+    Ok(())
+}
+
+/** Replaces the inline tags of a node with the `#tags` found in `content`. */
+pub fn index_inline_tags(
+    conn: &Connection,
+    node_id: &str,
+    content: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM node_tags WHERE node_id = ?1 AND source = ?2",
+        params![node_id, SOURCE_INLINE],
+    )
+    .map_err(|e| format!("Failed to clear inline tags: {}", e))?;
+
+    let tags = content.map(tiptap::extract_tags).unwrap_or_default();
+    for tag in tags {
+        let tag_id = ensure_tag(conn, &tag)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO node_tags (node_id, tag_id, source) VALUES (?1, ?2, ?3)",
+            params![node_id, tag_id, SOURCE_INLINE],
+        )
+        .map_err(|e| format!("Failed to tag node: {}", e))?;
+    }
+
+    prune_unused_tags(conn)
+}
+
+/** Rebuilds the inline tags of every node.
+ *
+ * Only reads `id` and `content` so it can run as a migration backfill
+ * before later columns exist.
+ */
+pub fn rebuild_tags(conn: &Connection) -> Result<(), String> {
+    let rows = {
+        let mut stmt = conn
+            .prepare("SELECT id, content FROM nodes")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(|e| format!("Failed to list nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?
+    };
+
+    for (id, content) in rows {
+        index_inline_tags(conn, &id, content.as_deref())?;
+    }
+
+    Ok(())
+}
+
+/** Lists a node's tags by name. */
+pub fn get_node_tags(conn: &Connection, node_id: &str) -> Result<Vec<NodeTag>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name, MAX(nt.source = ?2), MAX(nt.source = ?3)
+             FROM node_tags nt
+             JOIN tags t ON t.id = nt.tag_id
+             WHERE nt.node_id = ?1
+             GROUP BY t.id
+             ORDER BY t.name",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let tags = stmt
+        .query_map(params![node_id, SOURCE_INLINE, SOURCE_MANUAL], |row| {
+            Ok(NodeTag {
+                name: row.get(0)?,
+                inline: row.get(1)?,
+                manual: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list node tags: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node tag: {}", e))?;
+
+    Ok(tags)
+}
+
+/** Adds a tag to a node and returns the node's tags. */
+pub fn add_tag(conn: &mut Connection, node_id: &str, tag: &str) -> Result<Vec<NodeTag>, String> {
+    let tag = validate_tag(tag)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    nodes::get_node(&tx, node_id)?;
+    let tag_id = ensure_tag(&tx, &tag)?;
+    tx.execute(
+        "INSERT OR IGNORE INTO node_tags (node_id, tag_id, source) VALUES (?1, ?2, ?3)",
+        params![node_id, tag_id, SOURCE_MANUAL],
+    )
+    .map_err(|e| format!("Failed to tag node: {}", e))?;
+
+    let tags = get_node_tags(&tx, node_id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(tags)
+}
+
+/** Removes a manually added tag from a node and returns the node's tags.
+ *
+ * Inline tags stay until the `#tag` is removed from the note text.
+ */
+pub fn remove_tag(conn: &mut Connection, node_id: &str, tag: &str) -> Result<Vec<NodeTag>, String> {
+    let tag = validate_tag(tag)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute(
+        "DELETE FROM node_tags
+         WHERE node_id = ?1 AND source = ?2
+           AND tag_id = (SELECT id FROM tags WHERE name = ?3)",
+        params![node_id, SOURCE_MANUAL, tag],
+    )
+    .map_err(|e| format!("Failed to untag node: {}", e))?;
+
+    prune_unused_tags(&tx)?;
+    let tags = get_node_tags(&tx, node_id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(tags)
+}
+
+/** Lists tags with the number of non-trashed nodes using them.
+ *
+ * Counts are limited to `space_id` when set, and tags with no matching
+ * nodes are left out.
+ */
+pub fn list_tags(conn: &Connection, space_id: Option<&str>) -> Result<Vec<TagCount>, String> {
+    let mut stmt = conn
+        .prepare(
+            "WITH tagged AS (
+                SELECT DISTINCT nt.node_id, t.name
+                FROM node_tags nt
+                JOIN tags t ON t.id = nt.tag_id
+                JOIN nodes n ON n.id = nt.node_id
+                WHERE n.deleted_at IS NULL AND (?1 IS NULL OR n.space_id = ?1)
+             )
+             SELECT * FROM (
+                SELECT t.name,
+                       (SELECT COUNT(*) FROM tagged WHERE tagged.name = t.name) AS node_count,
+                       (SELECT COUNT(DISTINCT node_id) FROM tagged
+                        WHERE tagged.name = t.name
+                           OR substr(tagged.name, 1, length(t.name) + 1) = t.name || '/'
+                       ) AS total_count
+                FROM tags t
+             )
+             WHERE total_count > 0
+             ORDER BY name",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let tags = stmt
+        .query_map([space_id], |row| {
+            let name: String = row.get(0)?;
+            Ok(TagCount {
+                parent: name.rsplit_once('/').map(|(parent, _)| parent.to_string()),
+                name,
+                node_count: row.get(1)?,
+                total_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list tags: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read tag: {}", e))?;
+
+    Ok(tags)
+}
+
+/** Lists non-trashed nodes carrying all or any of the requested tags. */
+pub fn list_nodes_by_tags(
+    conn: &Connection,
+    request: &ListNodesByTagsRequest,
+) -> Result<Vec<Node>, String> {
+    let mut tags = request
+        .tags
+        .iter()
+        .map(|tag| validate_tag(tag))
+        .collect::<Result<Vec<_>, _>>()?;
+    tags.sort();
+    tags.dedup();
+    if tags.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // ?1 is the space filter; tags start at ?2.
+    let conditions = (0..tags.len())
+        .map(|index| {
+            format!(
+                "EXISTS (
+                    SELECT 1 FROM node_tags nt
+                    JOIN tags t ON t.id = nt.tag_id
+                    WHERE nt.node_id = nodes.id AND {}
+                )",
+                tag_matches(index + 2)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(match request.mode {
+            TagMatch::All => " AND ",
+            TagMatch::Any => " OR ",
+        });
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM nodes
+             WHERE deleted_at IS NULL AND (?1 IS NULL OR space_id = ?1) AND ({})
+             ORDER BY name COLLATE NOCASE, id",
+            NODE_COLUMNS, conditions
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let params = std::iter::once(request.space_id.clone()).chain(tags.into_iter().map(Some));
+    let nodes = stmt
+        .query_map(params_from_iter(params), node_from_row)
+        .map_err(|e| format!("Failed to list tagged nodes: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read node: {}", e))?;
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{TEST_SPACE_ID, create_test_node, open_test_database, trash};
+    use serde_json::json;
+
+    fn text_doc(text: &str) -> String {
+        json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [{ "type": "text", "text": text }] }
+        ] })
+        .to_string()
+    }
+
+    fn tag_names(tags: &[NodeTag]) -> Vec<(&str, bool, bool)> {
+        tags.iter()
+            .map(|tag| (tag.name.as_str(), tag.inline, tag.manual))
+            .collect()
+    }
+
+    fn request(tags: &[&str], mode: TagMatch) -> ListNodesByTagsRequest {
+        ListNodesByTagsRequest {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            mode,
+            space_id: None,
+        }
+    }
+
+    fn node_names(nodes: Vec<Node>) -> Vec<String> {
+        nodes.into_iter().map(|node| node.name).collect()
+    }
+
+    #[test]
+    fn inline_and_manual_tags_are_tracked_separately() {
+        let mut conn = open_test_database();
+        let content = text_doc("planning #Work/Project");
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", Some(&content));
+
+        let tags = add_tag(&mut conn, &note.id, "#work/project").unwrap();
+        assert_eq!(tag_names(&tags), vec![("work/project", true, true)]);
+
+        // Removing the manual tag leaves the inline one.
+        let tags = remove_tag(&mut conn, &note.id, "work/project").unwrap();
+        assert_eq!(tag_names(&tags), vec![("work/project", true, false)]);
+
+        assert!(add_tag(&mut conn, &note.id, "not a tag").is_err());
+    }
+
+    #[test]
+    fn list_tags_counts_nodes_under_parent_tags() {
+        let mut conn = open_test_database();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let b = create_test_node(&mut conn, TEST_SPACE_ID, None, "b", None);
+        let c = create_test_node(&mut conn, TEST_SPACE_ID, None, "c", None);
+        add_tag(&mut conn, &a.id, "work").unwrap();
+        add_tag(&mut conn, &a.id, "work/project").unwrap();
+        add_tag(&mut conn, &b.id, "work/project").unwrap();
+        add_tag(&mut conn, &c.id, "work/other").unwrap();
+        trash::trash_node(&mut conn, &c.id).unwrap();
+
+        let tags = list_tags(&conn, None).unwrap();
+
+        let counts: Vec<(&str, Option<&str>, i64, i64)> = tags
+            .iter()
+            .map(|tag| {
+                let parent = tag.parent.as_deref();
+                (tag.name.as_str(), parent, tag.node_count, tag.total_count)
+            })
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("work", None, 1, 2), ("work/project", Some("work"), 2, 2)]
+        );
+    }
+
+    #[test]
+    fn remove_tag_prunes_unused_tags() {
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        add_tag(&mut conn, &note.id, "a/b/c").unwrap();
+
+        remove_tag(&mut conn, &note.id, "a/b/c").unwrap();
+
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn list_nodes_by_tags_matches_descendant_tags() {
+        let mut conn = open_test_database();
+        let a = create_test_node(&mut conn, TEST_SPACE_ID, None, "a", None);
+        let b = create_test_node(&mut conn, TEST_SPACE_ID, None, "b", None);
+        let c = create_test_node(&mut conn, TEST_SPACE_ID, None, "c", None);
+        add_tag(&mut conn, &a.id, "work/project").unwrap();
+        add_tag(&mut conn, &a.id, "urgent").unwrap();
+        add_tag(&mut conn, &b.id, "work").unwrap();
+        add_tag(&mut conn, &c.id, "workshop").unwrap();
+
+        let any = list_nodes_by_tags(&conn, &request(&["work"], TagMatch::Any)).unwrap();
+        assert_eq!(node_names(any), vec!["a", "b"]);
+
+        let all = list_nodes_by_tags(&conn, &request(&["work", "urgent"], TagMatch::All));
+        assert_eq!(node_names(all.unwrap()), vec!["a"]);
+
+        let either = list_nodes_by_tags(&conn, &request(&["urgent", "workshop"], TagMatch::Any));
+        assert_eq!(node_names(either.unwrap()), vec!["a", "c"]);
+
+        assert!(
+            list_nodes_by_tags(&conn, &request(&[], TagMatch::All))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use super::nodes::{self, DeletedSubtree, SUBTREE_CTE};
+use crate::models::nodes::Node;
+use crate::models::trash::TrashEntry;
+use rusqlite::{Connection, Row, params};
+
+// Every node trashed by one operation records that operation's root in
+// `deleted_root_id`; a trash entry is a node that is its own root.
+const TRASH_ENTRY_QUERY: &str =
+    "SELECT n.id, n.space_id, s.name, n.parent_id, n.name, n.deleted_at,
+            (SELECT COUNT(*) - 1 FROM nodes d WHERE d.deleted_root_id = n.id)
+     FROM nodes n
+     JOIN spaces s ON s.id = n.space_id
+     WHERE n.deleted_at IS NOT NULL AND n.deleted_root_id = n.id";
+
+fn trash_entry_from_row(row: &Row) -> rusqlite::Result<TrashEntry> {
+    Ok(TrashEntry {
+        id: row.get(0)?,
+        space_id: row.get(1)?,
+        space_name: row.get(2)?,
+        parent_id: row.get(3)?,
+        name: row.get(4)?,
+        deleted_at: row.get(5)?,
+        descendant_count: row.get(6)?,
+    })
+}
+
+/** Moves a node and its descendants to the trash.
+ *
+ * Descendants that were already trashed keep their own trash entry.
+ */
+pub fn trash_node(conn: &mut Connection, id: &str) -> Result<TrashEntry, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let node = nodes::get_node(&tx, id)?;
+    if node.deleted_at.is_some() {
+        return Err("Node is already in the trash.".to_string());
+    }
+
+    tx.execute(
+        &format!(
+            "{} UPDATE nodes SET deleted_at = datetime('now'), deleted_root_id = ?1
+             WHERE id IN (SELECT id FROM subtree) AND deleted_at IS NULL",
+            SUBTREE_CTE
+        ),
+        [id],
+    )
+    .map_err(|e| format!("Failed to trash node: {}", e))?;
+
+    let entry = tx
+        .query_row(
+            &format!("{} AND n.id = ?1", TRASH_ENTRY_QUERY),
+            [id],
+            trash_entry_from_row,
+        )
+        .map_err(|e| format!("Failed to read trash entry: {}", e))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(entry)
+}
+
+/** Restores a trashed node and the descendants trashed with it.
+ *
+ * The node returns to its original parent when that parent still exists
+ * outside the trash, otherwise to the space root, and is placed after
+ * its new last sibling. Restoring a node that was trashed as part of an
+ * ancestor's subtree detaches it from that trash entry.
+ */
+pub fn restore_node(conn: &mut Connection, id: &str) -> Result<Node, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let node = nodes::get_node(&tx, id)?;
+    if node.deleted_at.is_none() {
+        return Err("Node is not in the trash.".to_string());
+    }
+
+    let deleted_root_id: Option<String> = tx
+        .query_row(
+            "SELECT deleted_root_id FROM nodes WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read trash entry: {}", e))?;
+
+    let parent_id = match node.parent_id.as_deref() {
+        Some(parent_id) => {
+            let parent_available: bool = tx
+                .query_row(
+                    "SELECT EXISTS(
+                        SELECT 1 FROM nodes WHERE id = ?1 AND deleted_at IS NULL
+                    )",
+                    [parent_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to check parent node: {}", e))?;
+            parent_available.then_some(parent_id)
+        }
+        None => None,
+    };
+
+    tx.execute(
+        &format!(
+            "{} UPDATE nodes SET deleted_at = NULL, deleted_root_id = NULL
+             WHERE id IN (SELECT id FROM subtree) AND deleted_root_id IS ?2",
+            SUBTREE_CTE
+        ),
+        params![id, deleted_root_id],
+    )
+    .map_err(|e| format!("Failed to restore node: {}", e))?;
+
+    let sort_order = nodes::next_sort_order(&tx, &node.space_id, parent_id)?;
+
+    tx.execute(
+        "UPDATE nodes SET parent_id = ?1, sort_order = ?2 WHERE id = ?3",
+        params![parent_id, sort_order, id],
+    )
+    .map_err(|e| format!("Failed to place restored node: {}", e))?;
+
+    let restored = nodes::get_node(&tx, id)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(restored)
+}
+
+/** Lists trash entries, most recently trashed first. */
+pub fn list_trash(conn: &Connection, space_id: Option<&str>) -> Result<Vec<TrashEntry>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} AND (?1 IS NULL OR n.space_id = ?1) ORDER BY n.deleted_at DESC",
+            TRASH_ENTRY_QUERY
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let entries = stmt
+        .query_map([space_id], trash_entry_from_row)
+        .map_err(|e| format!("Failed to list trash: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read trash entry: {}", e))?;
+
+    Ok(entries)
+}
+
+/** Permanently deletes trash entries with their attachments and revisions.
+ *
+ * Only entries trashed more than `older_than_days` ago are purged when
+ * set; otherwise the whole trash is emptied. Attachment files are left
+ * for the caller to remove after the transaction commits.
+ */
+pub fn purge_trash(
+    conn: &mut Connection,
+    older_than_days: Option<u32>,
+) -> Result<DeletedSubtree, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let cutoff = older_than_days.map(|days| format!("-{} days", days));
+    let entry_ids = {
+        let mut stmt = tx
+            .prepare(&format!(
+                "{} AND (?1 IS NULL OR n.deleted_at < datetime('now', ?1))",
+                TRASH_ENTRY_QUERY
+            ))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        stmt.query_map([cutoff], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to list trash: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read trash entry: {}", e))?
+    };
+
+    let mut purged = DeletedSubtree::default();
+    for entry_id in entry_ids {
+        // An older entry may sit inside a newer one that was already purged.
+        if nodes::subtree_ids(&tx, &entry_id)?.is_empty() {
+            continue;
+        }
+        purged.extend(nodes::remove_subtree(&tx, &entry_id)?);
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database,
+    };
+
+    fn trashed_ids(conn: &Connection) -> Vec<String> {
+        list_trash(conn, None)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    #[test]
+    fn trash_node_takes_descendants_along() {
+        let mut conn = open_test_database();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "child", None);
+        create_test_node(
+            &mut conn,
+            TEST_SPACE_ID,
+            Some(&child.id),
+            "grandchild",
+            None,
+        );
+
+        let entry = trash_node(&mut conn, &root.id).unwrap();
+
+        assert_eq!(entry.descendant_count, 2);
+        assert_eq!(trashed_ids(&conn), vec![root.id.clone()]);
+        assert!(
+            nodes::get_node(&conn, &child.id)
+                .unwrap()
+                .deleted_at
+                .is_some()
+        );
+        assert!(trash_node(&mut conn, &root.id).is_err());
+    }
+
+    #[test]
+    fn restore_node_keeps_separately_trashed_descendants() {
+        let mut conn = open_test_database();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        let kept = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "kept", None);
+        let earlier = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "earlier", None);
+        trash_node(&mut conn, &earlier.id).unwrap();
+        trash_node(&mut conn, &root.id).unwrap();
+
+        let restored = restore_node(&mut conn, &root.id).unwrap();
+
+        assert_eq!(restored.deleted_at, None);
+        assert_eq!(nodes::get_node(&conn, &kept.id).unwrap().deleted_at, None);
+        assert_eq!(trashed_ids(&conn), vec![earlier.id.clone()]);
+        assert!(restore_node(&mut conn, &root.id).is_err());
+    }
+
+    #[test]
+    fn restore_node_falls_back_to_the_space_root() {
+        let mut conn = open_test_database();
+        let root = create_test_node(&mut conn, TEST_SPACE_ID, None, "root", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&root.id), "child", None);
+        let other = create_test_node(&mut conn, TEST_SPACE_ID, None, "other", None);
+        trash_node(&mut conn, &root.id).unwrap();
+
+        // Restoring a descendant detaches it from the trashed parent.
+        let restored = restore_node(&mut conn, &child.id).unwrap();
+
+        assert_eq!(restored.parent_id, None);
+        assert!(restored.sort_order > other.sort_order);
+        assert_eq!(trashed_ids(&conn), vec![root.id.clone()]);
+        assert_eq!(list_trash(&conn, None).unwrap()[0].descendant_count, 0);
+    }
+
+    #[test]
+    fn purge_trash_deletes_old_entries_and_releases_files() {
+        let mut conn = open_test_database();
+        let old = create_test_node(&mut conn, TEST_SPACE_ID, None, "old", None);
+        let child = create_test_node(&mut conn, TEST_SPACE_ID, Some(&old.id), "child", None);
+        let recent = create_test_node(&mut conn, TEST_SPACE_ID, None, "recent", None);
+        let live = create_test_node(&mut conn, TEST_SPACE_ID, None, "live", None);
+        insert_test_attachment(&conn, &child.id, "/images/old.png", Some("old"));
+        insert_test_attachment(&conn, &child.id, "/images/shared.png", Some("shared"));
+        insert_test_attachment(&conn, &live.id, "/images/shared.png", Some("shared"));
+        trash_node(&mut conn, &old.id).unwrap();
+        trash_node(&mut conn, &recent.id).unwrap();
+        conn.execute(
+            "UPDATE nodes SET deleted_at = datetime('now', '-40 days') WHERE deleted_root_id = ?1",
+            [&old.id],
+        )
+        .unwrap();
+
+        let purged = purge_trash(&mut conn, Some(30)).unwrap();
+
+        assert_eq!(purged.node_ids, vec![old.id.clone(), child.id.clone()]);
+        assert_eq!(purged.file_paths, vec!["/images/old.png".to_string()]);
+        assert!(nodes::get_node(&conn, &old.id).is_err());
+        assert_eq!(trashed_ids(&conn), vec![recent.id.clone()]);
+
+        let purged = purge_trash(&mut conn, None).unwrap();
+        assert_eq!(purged.node_ids, vec![recent.id.clone()]);
+        assert!(trashed_ids(&conn).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+/** Store file shared with the frontend's settings service. */
+pub const SETTINGS_STORE_PATH: &str = "settings.json";
+
+// Settings keys
+pub const ATTACHMENTS_KEY: &str = "attachments";
+pub const BACKUPS_KEY: &str = "backups";
+pub const IMAGE_METADATA_KEY: &str = "image_metadata";
+pub const IMAGE_UPLOADS_KEY: &str = "image_uploads";
+pub const NOTE_WINDOWS_KEY: &str = "note_windows";
+pub const REVISIONS_KEY: &str = "revisions";
+pub const TRASH_KEY: &str = "trash";
+pub const VAULTS_KEY: &str = "vaults";
+pub const WINDOW_STATE_KEY: &str = "window_state";
+
+/** Reads a settings section from the store.
+ *
+ * Falls back to the section's defaults when the key is missing or its
+ * value cannot be deserialized, so a bad edit never blocks a command.
+ */
+pub fn load<T: DeserializeOwned + Default>(app: &AppHandle, key: &str) -> T {
+    let Ok(store) = app.store(SETTINGS_STORE_PATH) else {
+        return T::default();
+    };
+
+    store
+        .get(key)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/** Writes a settings section to the store and flushes it to disk. */
+pub fn save<T: Serialize>(app: &AppHandle, key: &str, value: &T) -> Result<(), String> {
+    let store = app
+        .store(SETTINGS_STORE_PATH)
+        .map_err(|e| format!("Failed to open settings store: {}", e))?;
+    let value =
+        serde_json::to_value(value).map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    store.set(key, value);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save settings: {}", e))
+}
@@ -0,0 +1,477 @@
+use crate::config::{self, StorageLocation};
+use crate::migrations;
+use crate::models::attachments::Attachment;
+use crate::repository::attachments::{self, attachment_from_row};
+use rusqlite::{Connection, OpenFlags, params};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/** Counts of what was merged in from a stray storage location. */
+#[derive(Debug, Default)]
+pub struct StrayMerge {
+    pub attachment_rows: usize,
+    pub moved_files: usize,
+    pub rewritten_notes: usize,
+}
+
+/** Merges a database and `images` folder left in `stray_root` into `location`.
+ *
+ * - attachment rows from the stray database are copied into `conn`'s database
+ * - files in the stray `images` folder are moved into the canonical one
+ * - attachment rows and note content pointing at moved files are updated
+ *
+ * The stray database is renamed with a `.merged` suffix afterwards, so the
+ * merge only happens once. Returns `None` when there was nothing to merge.
+ */
+pub fn merge_stray_location(
+    conn: &mut Connection,
+    stray_root: &Path,
+    location: &StorageLocation,
+) -> Result<Option<StrayMerge>, String> {
+    let stray_db = config::database_path(stray_root);
+    let stray_images_dir = stray_root.join(config::IMAGES_DIR_NAME);
+    if !stray_db.is_file() && !stray_images_dir.is_dir() {
+        return Ok(None);
+    }
+
+    let attachments = if stray_db.is_file() {
+        read_stray_attachments(&stray_db)?
+    } else {
+        Vec::new()
+    };
+
+    let moves = move_image_files(&stray_images_dir, &location.images_dir())?;
+
+    let merged = match record_merge(conn, &attachments, &moves) {
+        Ok(merged) => merged,
+        Err(e) => {
+            // Put the files back so the rows still pointing at them stay valid.
+            for (old_path, new_path) in &moves {
+                move_file(new_path, old_path).ok();
+            }
+            return Err(e);
+        }
+    };
+
+    if stray_db.is_file() {
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let path = PathBuf::from(format!("{}{}", stray_db.display(), suffix));
+            if path.exists() {
+                fs::rename(&path, format!("{}.merged", path.display()))
+                    .map_err(|e| format!("Failed to retire stray database: {}", e))?;
+            }
+        }
+    }
+    fs::remove_dir(&stray_images_dir).ok();
+
+    Ok(Some(StrayMerge {
+        moved_files: moves.len(),
+        ..merged
+    }))
+}
+
+/** Counts of stored paths pointed back into the vault by [`relocate_attachments`]. */
+#[derive(Debug, Default)]
+pub struct Relocation {
+    pub rewritten_rows: usize,
+    pub rewritten_notes: usize,
+}
+
+/** Points stored attachment paths at `location` after the vault moved.
+ *
+ * Attachment, blob and variant rows store absolute paths, so a vault
+ * synced to another machine, or whose folder was moved, still points at
+ * the old `images` folder. Each path is rebuilt from what follows its last
+ * `images` component; rows are only rewritten when the file exists at the
+ * new path, so files still syncing are picked up on a later open. Note
+ * content is rewritten to match. Returns `None` when nothing moved.
+ */
+pub fn relocate_attachments(
+    conn: &mut Connection,
+    location: &StorageLocation,
+) -> Result<Option<Relocation>, String> {
+    let images_dir = location.images_dir();
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut relocation = Relocation::default();
+    let mut moved_attachments = HashMap::new();
+    for table in ["attachments", "attachment_blobs", "attachment_variants"] {
+        for (rowid, old_path, new_path) in relocate_table(&tx, table, &images_dir)? {
+            relocation.rewritten_rows += 1;
+            if table == "attachments" {
+                let id: String = tx
+                    .query_row(
+                        "SELECT id FROM attachments WHERE rowid = ?1",
+                        [rowid],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| format!("Failed to read relocated attachment: {}", e))?;
+                moved_attachments.insert(id, (old_path, new_path));
+            }
+        }
+    }
+    if relocation.rewritten_rows == 0 {
+        return Ok(None);
+    }
+
+    relocation.rewritten_notes = attachments::rewrite_moved_files(&tx, &moved_attachments)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(Some(relocation))
+}
+
+/** Rewrites the `file_path` of rows in `table` that point outside `images_dir`.
+ *
+ * Returns the rowid, old path and new path of every rewritten row.
+ */
+fn relocate_table(
+    conn: &Connection,
+    table: &str,
+    images_dir: &Path,
+) -> Result<Vec<(i64, String, String)>, String> {
+    let rows = {
+        let mut stmt = conn
+            .prepare(&format!("SELECT rowid, file_path FROM {}", table))
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| format!("Failed to list {}: {}", table, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read {} row: {}", table, e))?
+    };
+
+    let mut moved = Vec::new();
+    for (rowid, old_path) in rows {
+        let Some(new_path) = relocated_path(&old_path, images_dir) else {
+            continue;
+        };
+        if !new_path.is_file() {
+            continue;
+        }
+
+        let new_path = new_path.to_string_lossy().to_string();
+        conn.execute(
+            &format!("UPDATE {} SET file_path = ?1 WHERE rowid = ?2", table),
+            params![new_path, rowid],
+        )
+        .map_err(|e| format!("Failed to update {} path: {}", table, e))?;
+        moved.push((rowid, old_path, new_path));
+    }
+
+    Ok(moved)
+}
+
+/** Rebuilds `stored` under `images_dir`, or `None` if it is already there.
+ *
+ * Both separators are accepted, since the path may have been stored on
+ * another platform.
+ */
+fn relocated_path(stored: &str, images_dir: &Path) -> Option<PathBuf> {
+    let components: Vec<&str> = stored.split(['/', '\\']).collect();
+    let images_index = components
+        .iter()
+        .rposition(|component| *component == config::IMAGES_DIR_NAME)?;
+    let relative = &components[images_index + 1..];
+    if relative.is_empty()
+        || relative
+            .iter()
+            .any(|component| component.is_empty() || *component == "..")
+    {
+        return None;
+    }
+
+    let relocated = relative
+        .iter()
+        .fold(images_dir.to_path_buf(), |path, component| {
+            path.join(component)
+        });
+    (relocated != Path::new(stored)).then_some(relocated)
+}
+
+/** Reads the attachment rows of a stray database.
+ *
+ * Stray databases were usually left by the legacy image commands and still
+ * have the `images` table, whose rows are read as image attachments. The
+ * rows come without a blob or dimensions; their files are hashed after the
+ * merge and measured once moved.
+ */
+fn read_stray_attachments(path: &Path) -> Result<Vec<Attachment>, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open stray database: {}", e))?;
+
+    if migrations::has_table(&conn, "attachments")? {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, note_id, kind, original_name, extension, filename, file_path, size,
+                     mime_type, created_at, NULL AS blob_hash, NULL AS width, NULL AS height,
+                     NULL AS original_id
+                 FROM attachments",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        return stmt
+            .query_map([], attachment_from_row)
+            .map_err(|e| format!("Failed to list stray attachments: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read stray attachment: {}", e));
+    }
+    if !migrations::has_table(&conn, "images")? {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, note_id, filename, file_path, CAST(size AS INTEGER), mime_type, created_at
+             FROM images",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let images = stmt
+        .query_map([], |row| {
+            let filename: String = row.get(2)?;
+            let mime_type: String = row.get(5)?;
+            let (_, extension) = attachments::split_file_name(&filename, &mime_type);
+
+            Ok(Attachment {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                kind: attachments::classify(&mime_type, &extension),
+                original_name: filename.clone(),
+                extension,
+                filename,
+                file_path: row.get(3)?,
+                size: row.get::<_, i64>(4)?.max(0) as u64,
+                mime_type,
+                created_at: row.get(6)?,
+                blob_hash: None,
+                width: None,
+                height: None,
+                original_id: None,
+            })
+        })
+        .map_err(|e| format!("Failed to list stray images: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read stray image: {}", e))?;
+
+    Ok(images)
+}
+
+/** Moves every file in `from` into `to` and returns `(old_path, new_path)` pairs.
+ *
+ * A file whose name is already taken gets a fresh UUID name. If a move
+ * fails, the files moved so far are put back.
+ */
+fn move_image_files(from: &Path, to: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let Ok(entries) = fs::read_dir(from) else {
+        return Ok(Vec::new());
+    };
+
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create images directory: {}", e))?;
+
+    let mut moves: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for entry in entries.flatten() {
+        let old_path = entry.path();
+        if !old_path.is_file() {
+            continue;
+        }
+
+        let mut new_path = to.join(entry.file_name());
+        if new_path.exists() {
+            let extension = old_path
+                .extension()
+                .map(|extension| format!(".{}", extension.to_string_lossy()))
+                .unwrap_or_default();
+            new_path = to.join(format!("{}{}", Uuid::new_v4(), extension));
+        }
+
+        if let Err(e) = move_file(&old_path, &new_path) {
+            for (old_path, new_path) in &moves {
+                move_file(new_path, old_path).ok();
+            }
+            return Err(format!("Failed to move {}: {}", old_path.display(), e));
+        }
+        moves.push((old_path, new_path));
+    }
+
+    Ok(moves)
+}
+
+/** Renames a file, falling back to copy and delete across file systems. */
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+fn record_merge(
+    conn: &mut Connection,
+    attachments: &[Attachment],
+    moves: &[(PathBuf, PathBuf)],
+) -> Result<StrayMerge, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut attachment_rows = 0;
+    for attachment in attachments {
+        attachment_rows += tx
+            .execute(
+                "INSERT OR IGNORE INTO attachments
+                    (id, note_id, kind, original_name, extension, filename, file_path, size,
+                     mime_type, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    attachment.id,
+                    attachment.note_id,
+                    attachment.kind.as_str(),
+                    attachment.original_name,
+                    attachment.extension,
+                    attachment.filename,
+                    attachment.file_path,
+                    attachment.size as i64,
+                    attachment.mime_type,
+                    attachment.created_at
+                ],
+            )
+            .map_err(|e| format!("Failed to merge attachment metadata: {}", e))?;
+    }
+
+    // Attachment ID -> (old path, new path), for rewriting note content.
+    let mut moved_attachments = HashMap::new();
+    for (old_path, new_path) in moves {
+        let old_path = old_path.to_string_lossy().to_string();
+        let new_path_str = new_path.to_string_lossy().to_string();
+        let filename = new_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let ids = {
+            let mut stmt = tx
+                .prepare("SELECT id FROM attachments WHERE file_path = ?1")
+                .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+            stmt.query_map([&old_path], |row| row.get::<_, String>(0))
+                .map_err(|e| format!("Failed to list moved attachments: {}", e))?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read moved attachment: {}", e))?
+        };
+
+        tx.execute(
+            "UPDATE attachments SET file_path = ?1, filename = ?2 WHERE file_path = ?3",
+            params![new_path_str, filename, old_path],
+        )
+        .map_err(|e| format!("Failed to update attachment path: {}", e))?;
+
+        for id in ids {
+            moved_attachments.insert(id, (old_path.clone(), new_path_str.clone()));
+        }
+    }
+
+    let rewritten_notes = attachments::rewrite_moved_files(&tx, &moved_attachments)?;
+    attachments::backfill_image_dimensions(&tx)?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(StrayMerge {
+        attachment_rows,
+        moved_files: 0,
+        rewritten_notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::{
+        TEST_SPACE_ID, create_test_node, insert_test_attachment, open_test_database,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn relocated_path_rebuilds_paths_under_the_images_folder() {
+        let images_dir = Path::new("/vault/images");
+
+        assert_eq!(
+            relocated_path("/old/home/images/ab/c.png", images_dir),
+            Some(images_dir.join("ab").join("c.png"))
+        );
+        assert_eq!(
+            relocated_path(r"C:\Users\me\images\c.png", images_dir),
+            Some(images_dir.join("c.png"))
+        );
+        assert_eq!(relocated_path("/vault/images/c.png", images_dir), None);
+        assert_eq!(relocated_path("/old/images/../c.png", images_dir), None);
+        assert_eq!(relocated_path("/old/images/", images_dir), None);
+        assert_eq!(relocated_path("/old/files/c.png", images_dir), None);
+    }
+
+    #[test]
+    fn relocate_attachments_only_rewrites_files_that_arrived() {
+        let root = std::env::temp_dir().join(format!("storage-test-{}", Uuid::new_v4()));
+        let location = StorageLocation::new(&root);
+        let images_dir = location.images_dir();
+        fs::create_dir_all(&images_dir).unwrap();
+        fs::write(images_dir.join("here.png"), b"here").unwrap();
+
+        let mut conn = open_test_database();
+        let note = create_test_node(&mut conn, TEST_SPACE_ID, None, "note", None);
+        let moved =
+            insert_test_attachment(&conn, &note.id, "/elsewhere/images/here.png", Some("here"));
+        let syncing = insert_test_attachment(&conn, &note.id, "/elsewhere/images/later.png", None);
+        let content = json!({ "type": "doc", "content": [
+            { "type": "image", "attrs": {
+                "attachmentId": moved.id,
+                "src": "asset://localhost/%2Felsewhere%2Fimages%2Fhere.png"
+            } }
+        ] })
+        .to_string();
+        conn.execute(
+            "UPDATE nodes SET content = ?1 WHERE id = ?2",
+            params![content, note.id],
+        )
+        .unwrap();
+
+        let relocation = relocate_attachments(&mut conn, &location).unwrap().unwrap();
+
+        let new_path = images_dir.join("here.png").to_string_lossy().to_string();
+        // The attachment row and its blob.
+        assert_eq!(relocation.rewritten_rows, 2);
+        assert_eq!(relocation.rewritten_notes, 1);
+        assert_eq!(
+            attachments::get_attachment(&conn, &moved.id)
+                .unwrap()
+                .file_path,
+            new_path
+        );
+        assert_eq!(
+            attachments::get_blob(&conn, "here")
+                .unwrap()
+                .unwrap()
+                .file_path,
+            new_path
+        );
+        assert_eq!(
+            attachments::get_attachment(&conn, &syncing.id)
+                .unwrap()
+                .file_path,
+            "/elsewhere/images/later.png"
+        );
+        assert!(
+            relocate_attachments(&mut conn, &location)
+                .unwrap()
+                .is_none()
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/** Hex-encoded SHA-256 of `data`, the key attachment files are stored under. */
+pub fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/** Hex-encoded SHA-256 of the file at `path`, read in chunks. */
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/** Removes attachment files from disk.
+ *
+ * Returns `(removed, failed)` path lists. A file that is already gone
+ * counts as removed.
+ */
+pub fn remove_attachment_files(paths: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut removed = Vec::new();
+    let mut failed = Vec::new();
+
+    for path in paths {
+        match fs::remove_file(path) {
+            Ok(()) => removed.push(path.clone()),
+            Err(e) if e.kind() == ErrorKind::NotFound => removed.push(path.clone()),
+            Err(e) => {
+                eprintln!("Failed to delete attachment file {}: {}", path, e);
+                failed.push(path.clone());
+            }
+        }
+    }
+
+    (removed, failed)
+}
+
+/** Copies attachment files given as `(source, destination)` pairs.
+ *
+ * Stops at the first failure and removes the copies already made, so
+ * either every file is copied or none is.
+ */
+pub fn copy_attachment_files(copies: &[(String, String)]) -> Result<(), String> {
+    for (index, (source, destination)) in copies.iter().enumerate() {
+        if let Err(e) = fs::copy(source, destination) {
+            let copied: Vec<String> = copies[..index]
+                .iter()
+                .map(|(_, destination)| destination.clone())
+                .collect();
+            remove_attachment_files(&copied);
+            return Err(format!("Failed to copy attachment file {}: {}", source, e));
+        }
+    }
+
+    Ok(())
+}
+
+/** Moves files into `quarantine_dir`, keeping their file names.
+ *
+ * Returns `(moved, failed)` lists of the original paths.
+ */
+pub fn quarantine_files(paths: &[String], quarantine_dir: &Path) -> (Vec<String>, Vec<String>) {
+    let mut moved = Vec::new();
+    let mut failed = Vec::new();
+
+    if let Err(e) = fs::create_dir_all(quarantine_dir) {
+        eprintln!("Failed to create quarantine directory: {}", e);
+        return (moved, paths.to_vec());
+    }
+
+    for path in paths {
+        let Some(file_name) = Path::new(path).file_name() else {
+            failed.push(path.clone());
+            continue;
+        };
+
+        match fs::rename(path, quarantine_dir.join(file_name)) {
+            Ok(()) => moved.push(path.clone()),
+            Err(e) => {
+                eprintln!("Failed to quarantine file {}: {}", path, e);
+                failed.push(path.clone());
+            }
+        }
+    }
+
+    (moved, failed)
+}
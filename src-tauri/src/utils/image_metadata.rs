@@ -0,0 +1,634 @@
+use crate::models::images::ImageMetadataPolicy;
+use exif::experimental::Writer;
+use exif::{Context, Exif, Field, In, Reader, Tag};
+use img_parts::jpeg::{Jpeg, JpegSegment, markers};
+use img_parts::png::{Png, PngChunk};
+use img_parts::riff::{RiffChunk, RiffContent};
+use img_parts::webp::WebP;
+use img_parts::{Bytes, DynImage, ImageEXIF};
+use std::io::Cursor;
+
+const EXIF_PREFIX: &[u8] = b"Exif\0\0";
+const XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXTENDED_XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+
+const PNG_EXIF: [u8; 4] = *b"eXIf";
+const PNG_TEXT_CHUNKS: [[u8; 4]; 3] = [*b"tEXt", *b"zTXt", *b"iTXt"];
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+/** Keywords of PNG text chunks holding a hex dump of EXIF. */
+const PNG_RAW_EXIF_KEYWORDS: [&[u8]; 2] = [b"Raw profile type exif", b"Raw profile type APP1"];
+
+const WEBP_VP8X: [u8; 4] = *b"VP8X";
+const WEBP_EXIF: [u8; 4] = *b"EXIF";
+const WEBP_XMP: [u8; 4] = *b"XMP ";
+const WEBP_EXIF_FLAG: u8 = 0x08;
+const WEBP_XMP_FLAG: u8 = 0x04;
+
+/** XMP properties that place an image somewhere. */
+const XMP_LOCATION_PROPERTIES: [&[u8]; 4] = [
+    b"GPSLatitude",
+    b"GPSLongitude",
+    b"LocationCreated",
+    b"LocationShown",
+];
+
+/** What to do with an EXIF block. */
+enum ExifChange {
+    Keep,
+    Remove,
+    Replace(Vec<u8>),
+}
+
+/** Removes metadata from a JPEG, PNG or WebP file according to `policy`.
+ *
+ * The image data itself is left untouched. Returns the cleaned file, or
+ * `None` when nothing had to go or the file isn't in one of those
+ * formats.
+ */
+pub fn scrub_metadata(data: &[u8], policy: ImageMetadataPolicy) -> Result<Option<Vec<u8>>, String> {
+    if policy == ImageMetadataPolicy::Keep {
+        return Ok(None);
+    }
+
+    let mut image = match DynImage::from_bytes(Bytes::copy_from_slice(data)) {
+        Ok(Some(image)) => image,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read image metadata: {}", e)),
+    };
+
+    let changed = match &mut image {
+        DynImage::Jpeg(jpeg) => scrub_jpeg(jpeg, policy),
+        DynImage::Png(png) => scrub_png(png, policy),
+        DynImage::WebP(webp) => scrub_webp(webp, policy),
+    };
+    if !changed {
+        return Ok(None);
+    }
+
+    Ok(Some(image.encoder().bytes().to_vec()))
+}
+
+/** Whether an image file carries GPS coordinates or an XMP location.
+ *
+ * JPEG, PNG and WebP files are read for both; other containers, such as
+ * TIFF and HEIF, only for EXIF.
+ */
+pub fn has_location(data: &[u8]) -> bool {
+    let image = match DynImage::from_bytes(Bytes::copy_from_slice(data)) {
+        Ok(Some(image)) => image,
+        _ => {
+            return Reader::new()
+                .read_from_container(&mut Cursor::new(data))
+                .is_ok_and(|exif| has_gps_fields(&exif));
+        }
+    };
+
+    let exif_has_gps = exif_block(&image).is_some_and(|exif| {
+        Reader::new()
+            .read_raw(exif.to_vec())
+            .is_ok_and(|exif| has_gps_fields(&exif))
+    });
+
+    exif_has_gps || xmp_blocks(&image).iter().any(|xmp| mentions_location(xmp))
+}
+
+fn scrub_jpeg(jpeg: &mut Jpeg, policy: ImageMetadataPolicy) -> bool {
+    let mut changed = false;
+
+    if let Some(index) = jpeg.segments().iter().position(is_exif_segment) {
+        let exif = jpeg.segments()[index].contents().slice(EXIF_PREFIX.len()..);
+        match scrub_exif(&exif, policy) {
+            ExifChange::Keep => {}
+            ExifChange::Remove => {
+                jpeg.segments_mut().remove(index);
+                changed = true;
+            }
+            ExifChange::Replace(exif) => {
+                jpeg.segments_mut()[index] = JpegSegment::new_with_contents(
+                    markers::APP1,
+                    [EXIF_PREFIX, &exif].concat().into(),
+                );
+                changed = true;
+            }
+        }
+    }
+
+    let xmp: Vec<Bytes> = jpeg
+        .segments()
+        .iter()
+        .filter(|segment| is_xmp_segment(segment))
+        .map(|segment| segment.contents().clone())
+        .collect();
+    if drops_xmp(&xmp, policy) {
+        jpeg.segments_mut()
+            .retain(|segment| !is_xmp_segment(segment));
+        changed = true;
+    }
+
+    changed
+}
+
+fn scrub_png(png: &mut Png, policy: ImageMetadataPolicy) -> bool {
+    let mut changed = false;
+
+    if let Some(index) = png
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.kind() == PNG_EXIF)
+    {
+        let exif = png.chunks()[index].contents().clone();
+        match scrub_exif(&exif, policy) {
+            ExifChange::Keep => {}
+            ExifChange::Remove => {
+                png.chunks_mut().remove(index);
+                changed = true;
+            }
+            ExifChange::Replace(exif) => {
+                png.chunks_mut()[index] = PngChunk::new(PNG_EXIF, exif.into());
+                changed = true;
+            }
+        }
+    }
+
+    // Hex-dumped EXIF can't be checked for a location, so it always goes.
+    let xmp: Vec<Bytes> = png
+        .chunks()
+        .iter()
+        .filter(|chunk| png_text_keyword(chunk) == Some(PNG_XMP_KEYWORD))
+        .map(|chunk| chunk.contents().clone())
+        .collect();
+    let drop_xmp = drops_xmp(&xmp, policy);
+    let count = png.chunks().len();
+    png.chunks_mut()
+        .retain(|chunk| match png_text_keyword(chunk) {
+            Some(keyword) if keyword == PNG_XMP_KEYWORD => !drop_xmp,
+            Some(keyword) => !PNG_RAW_EXIF_KEYWORDS.contains(&keyword),
+            None => true,
+        });
+    changed |= png.chunks().len() != count;
+
+    changed
+}
+
+fn scrub_webp(webp: &mut WebP, policy: ImageMetadataPolicy) -> bool {
+    let mut changed = false;
+    let mut cleared_flags = 0;
+
+    if let Some(index) = webp
+        .chunks()
+        .iter()
+        .position(|chunk| chunk.id() == WEBP_EXIF)
+    {
+        let data = webp.chunks()[index]
+            .content()
+            .data()
+            .cloned()
+            .unwrap_or_default();
+        // The spec stores bare TIFF data, but some writers keep the JPEG prefix.
+        let exif = data.strip_prefix(EXIF_PREFIX).unwrap_or(&data);
+        match scrub_exif(exif, policy) {
+            ExifChange::Keep => {}
+            ExifChange::Remove => {
+                webp.chunks_mut().remove(index);
+                cleared_flags |= WEBP_EXIF_FLAG;
+                changed = true;
+            }
+            ExifChange::Replace(exif) => {
+                webp.chunks_mut()[index] =
+                    RiffChunk::new(WEBP_EXIF, RiffContent::Data(exif.into()));
+                changed = true;
+            }
+        }
+    }
+
+    let xmp: Vec<Bytes> = webp
+        .chunks_by_id(WEBP_XMP)
+        .filter_map(|chunk| chunk.content().data().cloned())
+        .collect();
+    if drops_xmp(&xmp, policy) {
+        webp.remove_chunks_by_id(WEBP_XMP);
+        cleared_flags |= WEBP_XMP_FLAG;
+        changed = true;
+    }
+
+    // Extended files announce their metadata chunks in the VP8X header.
+    if cleared_flags != 0
+        && let Some(vp8x) = webp
+            .chunks_mut()
+            .iter_mut()
+            .find(|chunk| chunk.id() == WEBP_VP8X)
+        && let RiffContent::Data(header) = vp8x.content_mut()
+        && !header.is_empty()
+    {
+        let mut updated = header.to_vec();
+        updated[0] &= !cleared_flags;
+        *header = updated.into();
+    }
+
+    changed
+}
+
+/** Decides what `policy` leaves of an EXIF block.
+ *
+ * Stripping everything still keeps the orientation, without which the
+ * image would show sideways. Blocks that fail to parse are removed,
+ * since they can't be checked.
+ */
+fn scrub_exif(data: &[u8], policy: ImageMetadataPolicy) -> ExifChange {
+    let Ok(exif) = Reader::new().read_raw(data.to_vec()) else {
+        return ExifChange::Remove;
+    };
+
+    match policy {
+        ImageMetadataPolicy::Keep => ExifChange::Keep,
+        ImageMetadataPolicy::StripLocation if !has_gps_fields(&exif) => ExifChange::Keep,
+        ImageMetadataPolicy::StripLocation => {
+            let fields: Vec<&Field> = exif
+                .fields()
+                .filter(|field| field.tag.context() != Context::Gps)
+                .collect();
+            match write_exif(&exif, &fields, thumbnail(&exif)) {
+                Some(data) => ExifChange::Replace(data),
+                None => orientation_only(&exif),
+            }
+        }
+        ImageMetadataPolicy::StripAll => orientation_only(&exif),
+    }
+}
+
+/** An EXIF block holding nothing but the orientation, if it isn't the default. */
+fn orientation_only(exif: &Exif) -> ExifChange {
+    let Some(orientation) = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .filter(|field| field.value.get_uint(0).is_some_and(|value| value != 1))
+    else {
+        return ExifChange::Remove;
+    };
+
+    match write_exif(exif, &[orientation], None) {
+        Some(data) => ExifChange::Replace(data),
+        None => ExifChange::Remove,
+    }
+}
+
+fn write_exif(exif: &Exif, fields: &[&Field], thumbnail: Option<&[u8]>) -> Option<Vec<u8>> {
+    let mut writer = Writer::new();
+    for field in fields {
+        writer.push_field(field);
+    }
+    if let Some(thumbnail) = thumbnail {
+        writer.set_jpeg(thumbnail, In::THUMBNAIL);
+    }
+
+    let mut data = Cursor::new(Vec::new());
+    match writer.write(&mut data, exif.little_endian()) {
+        Ok(()) => Some(data.into_inner()),
+        Err(e) => {
+            eprintln!("Failed to rewrite EXIF: {}", e);
+            None
+        }
+    }
+}
+
+/** The JPEG thumbnail embedded in an EXIF block, if any. */
+fn thumbnail(exif: &Exif) -> Option<&[u8]> {
+    let offset = exif
+        .get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+    let length = exif
+        .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?
+        .value
+        .get_uint(0)? as usize;
+
+    exif.buf().get(offset..offset.checked_add(length)?)
+}
+
+fn has_gps_fields(exif: &Exif) -> bool {
+    exif.fields()
+        .any(|field| field.tag.context() == Context::Gps && field.tag != Tag::GPSVersionID)
+}
+
+/** Whether `policy` removes the XMP blocks `xmp`, which are dropped whole. */
+fn drops_xmp(xmp: &[Bytes], policy: ImageMetadataPolicy) -> bool {
+    match policy {
+        _ if xmp.is_empty() => false,
+        ImageMetadataPolicy::StripAll => true,
+        ImageMetadataPolicy::StripLocation => xmp.iter().any(|xmp| mentions_location(xmp)),
+        ImageMetadataPolicy::Keep => false,
+    }
+}
+
+fn mentions_location(xmp: &[u8]) -> bool {
+    XMP_LOCATION_PROPERTIES.iter().any(|property| {
+        xmp.windows(property.len())
+            .any(|window| window == *property)
+    })
+}
+
+fn xmp_blocks(image: &DynImage) -> Vec<Bytes> {
+    match image {
+        DynImage::Jpeg(jpeg) => jpeg
+            .segments()
+            .iter()
+            .filter(|segment| is_xmp_segment(segment))
+            .map(|segment| segment.contents().clone())
+            .collect(),
+        DynImage::Png(png) => png
+            .chunks()
+            .iter()
+            .filter(|chunk| png_text_keyword(chunk) == Some(PNG_XMP_KEYWORD))
+            .map(|chunk| chunk.contents().clone())
+            .collect(),
+        DynImage::WebP(webp) => webp
+            .chunks_by_id(WEBP_XMP)
+            .filter_map(|chunk| chunk.content().data().cloned())
+            .collect(),
+    }
+}
+
+/** The EXIF block of an image, without the JPEG `Exif` prefix.
+ *
+ * WebP chunks are read directly, since writers disagree on whether they
+ * carry the prefix and `img_parts` only reads them when they do.
+ */
+fn exif_block(image: &DynImage) -> Option<Bytes> {
+    match image {
+        DynImage::WebP(webp) => {
+            let data = webp.chunk_by_id(WEBP_EXIF)?.content().data()?.clone();
+            Some(match data.strip_prefix(EXIF_PREFIX) {
+                Some(exif) => Bytes::copy_from_slice(exif),
+                None => data,
+            })
+        }
+        _ => image.exif(),
+    }
+}
+
+fn is_exif_segment(segment: &JpegSegment) -> bool {
+    segment.marker() == markers::APP1 && segment.contents().starts_with(EXIF_PREFIX)
+}
+
+fn is_xmp_segment(segment: &JpegSegment) -> bool {
+    segment.marker() == markers::APP1
+        && (segment.contents().starts_with(XMP_PREFIX)
+            || segment.contents().starts_with(EXTENDED_XMP_PREFIX))
+}
+
+/** The keyword of a PNG text chunk, which runs up to the first NUL byte. */
+fn png_text_keyword(chunk: &PngChunk) -> Option<&[u8]> {
+    if !PNG_TEXT_CHUNKS.contains(&chunk.kind()) {
+        return None;
+    }
+
+    let contents = chunk.contents();
+    let end = contents.iter().position(|byte| *byte == 0)?;
+    Some(&contents[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{Rational, Value};
+    use image::{DynamicImage, ImageFormat, RgbImage};
+
+    const XMP_WITH_LOCATION: &[u8] =
+        b"<x:xmpmeta><rdf:Description exif:GPSLatitude=\"52,31.0N\"/></x:xmpmeta>";
+
+    fn encoded(format: ImageFormat) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(4, 4))
+            .write_to(&mut data, format)
+            .unwrap();
+        data.into_inner()
+    }
+
+    fn field(tag: Tag, ifd: In, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: ifd,
+            value,
+        }
+    }
+
+    /** Bare TIFF-format EXIF with a camera make, a rotation and a GPS position. */
+    fn exif_with_location() -> Vec<u8> {
+        let fields = [
+            field(
+                Tag::Make,
+                In::PRIMARY,
+                Value::Ascii(vec![b"Camera".to_vec()]),
+            ),
+            field(Tag::Orientation, In::PRIMARY, Value::Short(vec![6])),
+            field(
+                Tag::GPSLatitudeRef,
+                In::PRIMARY,
+                Value::Ascii(vec![b"N".to_vec()]),
+            ),
+            field(
+                Tag::GPSLatitude,
+                In::PRIMARY,
+                Value::Rational(vec![
+                    Rational { num: 52, denom: 1 },
+                    Rational { num: 31, denom: 1 },
+                    Rational { num: 0, denom: 1 },
+                ]),
+            ),
+        ];
+        let exif = Reader::new().read_raw(write_fields(&fields)).unwrap();
+        write_exif(&exif, &exif.fields().collect::<Vec<_>>(), None).unwrap()
+    }
+
+    fn write_fields(fields: &[Field]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut data = Cursor::new(Vec::new());
+        writer.write(&mut data, false).unwrap();
+        data.into_inner()
+    }
+
+    fn jpeg_with_metadata() -> Vec<u8> {
+        let mut jpeg = Jpeg::from_bytes(encoded(ImageFormat::Jpeg).into()).unwrap();
+        jpeg.set_exif(Some(exif_with_location().into()));
+        jpeg.segments_mut().insert(
+            3,
+            JpegSegment::new_with_contents(
+                markers::APP1,
+                [XMP_PREFIX, XMP_WITH_LOCATION].concat().into(),
+            ),
+        );
+        jpeg.encoder().bytes().to_vec()
+    }
+
+    /** Tags left in the EXIF block of a JPEG, PNG or WebP file. */
+    fn exif_tags(data: &[u8]) -> Vec<Tag> {
+        let image = DynImage::from_bytes(Bytes::copy_from_slice(data))
+            .unwrap()
+            .unwrap();
+        let Some(exif) = exif_block(&image) else {
+            return Vec::new();
+        };
+        let exif = Reader::new().read_raw(exif.to_vec()).unwrap();
+        exif.fields().map(|field| field.tag).collect()
+    }
+
+    #[test]
+    fn keep_leaves_the_file_alone() {
+        let jpeg = jpeg_with_metadata();
+
+        assert!(has_location(&jpeg));
+        assert_eq!(
+            scrub_metadata(&jpeg, ImageMetadataPolicy::Keep).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_location_removes_gps_and_located_xmp_from_jpeg() {
+        let jpeg = jpeg_with_metadata();
+
+        let scrubbed = scrub_metadata(&jpeg, ImageMetadataPolicy::StripLocation)
+            .unwrap()
+            .unwrap();
+
+        assert!(!has_location(&scrubbed));
+        assert_eq!(exif_tags(&scrubbed), vec![Tag::Make, Tag::Orientation]);
+        assert!(image::load_from_memory(&scrubbed).is_ok());
+        assert_eq!(
+            scrub_metadata(&scrubbed, ImageMetadataPolicy::StripLocation).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn strip_all_keeps_only_the_orientation() {
+        let jpeg = jpeg_with_metadata();
+
+        let scrubbed = scrub_metadata(&jpeg, ImageMetadataPolicy::StripAll)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(exif_tags(&scrubbed), vec![Tag::Orientation]);
+        let jpeg = Jpeg::from_bytes(scrubbed.into()).unwrap();
+        assert!(!jpeg.segments().iter().any(is_xmp_segment));
+    }
+
+    #[test]
+    fn strip_all_drops_exif_without_a_rotation() {
+        let exif = write_fields(&[field(
+            Tag::Make,
+            In::PRIMARY,
+            Value::Ascii(vec![b"Camera".to_vec()]),
+        )]);
+        let mut jpeg = Jpeg::from_bytes(encoded(ImageFormat::Jpeg).into()).unwrap();
+        jpeg.set_exif(Some(exif.into()));
+
+        let scrubbed = scrub_metadata(&jpeg.encoder().bytes(), ImageMetadataPolicy::StripAll)
+            .unwrap()
+            .unwrap();
+
+        assert!(exif_tags(&scrubbed).is_empty());
+    }
+
+    #[test]
+    fn files_without_metadata_are_not_rewritten() {
+        for format in [ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::WebP] {
+            let data = encoded(format);
+            assert_eq!(
+                scrub_metadata(&data, ImageMetadataPolicy::StripAll).unwrap(),
+                None
+            );
+            assert!(!has_location(&data));
+        }
+        assert_eq!(
+            scrub_metadata(b"GIF89a", ImageMetadataPolicy::StripAll).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn png_loses_gps_and_hex_dumped_exif_but_keeps_other_text() {
+        let mut png = Png::from_bytes(encoded(ImageFormat::Png).into()).unwrap();
+        png.set_exif(Some(exif_with_location().into()));
+        let end = png.chunks().len() - 1;
+        png.chunks_mut().insert(
+            end,
+            PngChunk::new(
+                *b"tEXt",
+                b"Raw profile type exif\0\nexif\n   4\n00000000"
+                    .to_vec()
+                    .into(),
+            ),
+        );
+        png.chunks_mut().insert(
+            end,
+            PngChunk::new(*b"tEXt", b"Comment\0hello".to_vec().into()),
+        );
+        let data = png.encoder().bytes();
+        assert!(has_location(&data));
+
+        let scrubbed = scrub_metadata(&data, ImageMetadataPolicy::StripLocation)
+            .unwrap()
+            .unwrap();
+
+        assert!(!has_location(&scrubbed));
+        let png = Png::from_bytes(scrubbed.into()).unwrap();
+        let keywords: Vec<&[u8]> = png.chunks().iter().filter_map(png_text_keyword).collect();
+        assert_eq!(keywords, vec![b"Comment".as_slice()]);
+    }
+
+    #[test]
+    fn webp_clears_the_exif_flag_with_the_block() {
+        let mut webp = WebP::from_bytes(encoded(ImageFormat::WebP).into()).unwrap();
+        webp.set_exif(Some(exif_with_location().into()));
+        let flags = |webp: &WebP| {
+            webp.chunk_by_id(WEBP_VP8X)
+                .unwrap()
+                .content()
+                .data()
+                .unwrap()[0]
+        };
+        assert_ne!(flags(&webp) & WEBP_EXIF_FLAG, 0);
+        let data = webp.encoder().bytes();
+        assert!(has_location(&data));
+
+        let scrubbed = scrub_metadata(&data, ImageMetadataPolicy::StripAll)
+            .unwrap()
+            .unwrap();
+
+        assert!(!has_location(&scrubbed));
+        let webp = WebP::from_bytes(scrubbed.into()).unwrap();
+        assert!(webp.chunk_by_id(WEBP_EXIF).is_some());
+        assert_eq!(exif_tags(&webp.encoder().bytes()), vec![Tag::Orientation]);
+    }
+
+    #[test]
+    fn webp_exif_without_the_jpeg_prefix_is_read() {
+        let mut webp = WebP::from_bytes(encoded(ImageFormat::WebP).into()).unwrap();
+        webp.set_exif(Some(exif_with_location().into()));
+        let index = webp
+            .chunks()
+            .iter()
+            .position(|chunk| chunk.id() == WEBP_EXIF)
+            .unwrap();
+        webp.chunks_mut()[index] =
+            RiffChunk::new(WEBP_EXIF, RiffContent::Data(exif_with_location().into()));
+        let data = webp.encoder().bytes();
+
+        assert!(has_location(&data));
+        let scrubbed = scrub_metadata(&data, ImageMetadataPolicy::StripLocation)
+            .unwrap()
+            .unwrap();
+        assert!(!has_location(&scrubbed));
+        assert_eq!(exif_tags(&scrubbed), vec![Tag::Make, Tag::Orientation]);
+    }
+
+    #[test]
+    fn xmp_locations_are_found_by_property_name() {
+        assert!(mentions_location(XMP_WITH_LOCATION));
+        assert!(mentions_location(b"<Iptc4xmpExt:LocationCreated/>"));
+        assert!(!mentions_location(b"<dc:title>Trip</dc:title>"));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::models::attachments::ImageVariantSize;
+use crate::models::images::{ImageUploadFormat, ImageUploadSettings};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+use std::path::Path;
+
+/** Longest edge of a thumbnail, in pixels. */
+const THUMBNAIL_EDGE: u32 = 320;
+/** Width of the display variant, about twice the editor's column on a high-DPI screen. */
+const DISPLAY_WIDTH: u32 = 1600;
+/** JPEG quality used for variants of opaque images. */
+const VARIANT_JPEG_QUALITY: u8 = 85;
+
+/** Formats that get decoded for their size and resized variants. */
+const DECODED_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::WebP,
+    ImageFormat::Gif,
+];
+
+/** An uploaded image decoded into upright pixels.
+ *
+ * GIFs hold their first frame. `rotated` is set when the file's EXIF
+ * orientation had to be applied to get there.
+ */
+pub struct DecodedImage {
+    pub image: DynamicImage,
+    pub format: ImageFormat,
+    pub rotated: bool,
+}
+
+impl DecodedImage {
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+}
+
+/** A resized or re-encoded image, ready to write. */
+pub struct EncodedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub extension: &'static str,
+    pub mime_type: &'static str,
+}
+
+/** Decodes a png, jpeg, webp or gif image, turned upright.
+ *
+ * The format is sniffed from the data rather than trusted from the MIME
+ * type. Returns `None` for other formats and for data that fails to
+ * decode; such files are still stored, just without dimensions or
+ * variants.
+ */
+pub fn decode_image(data: &[u8]) -> Option<DecodedImage> {
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?;
+    let format = reader.format()?;
+    if !DECODED_FORMATS.contains(&format) {
+        return None;
+    }
+
+    let decoded = reader.into_decoder().and_then(|mut decoder| {
+        let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        Ok((image, orientation))
+    });
+
+    match decoded {
+        Ok((image, orientation)) => Some(DecodedImage {
+            image,
+            format,
+            rotated: orientation != Orientation::NoTransforms,
+        }),
+        Err(e) => {
+            eprintln!("Failed to decode image: {}", e);
+            None
+        }
+    }
+}
+
+/** Reads the upright pixel size of a png, jpeg, webp or gif file from its header. */
+pub fn image_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    if !DECODED_FORMATS.contains(&reader.format()?) {
+        return None;
+    }
+
+    let mut decoder = reader.into_decoder().ok()?;
+    let (width, height) = decoder.dimensions();
+    match decoder.orientation() {
+        Ok(
+            Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH,
+        ) => Some((height, width)),
+        _ => Some((width, height)),
+    }
+}
+
+/** Whether an image of this format and size gets a separate `size` variant.
+ *
+ * Images that already fit are served as they are, which is always the
+ * case for `Original`. GIFs only get thumbnails, since a display variant
+ * would drop their animation.
+ */
+pub fn needs_variant(format: ImageFormat, width: u32, height: u32, size: ImageVariantSize) -> bool {
+    match size {
+        ImageVariantSize::Original => false,
+        ImageVariantSize::Thumbnail => width > THUMBNAIL_EDGE || height > THUMBNAIL_EDGE,
+        ImageVariantSize::Display => format != ImageFormat::Gif && width > DISPLAY_WIDTH,
+    }
+}
+
+/** Renders the `size` variant of `decoded`, or `None` if it doesn't need one.
+ *
+ * Images with transparency are encoded as PNG and everything else as
+ * JPEG.
+ */
+pub fn render_variant(
+    decoded: &DecodedImage,
+    size: ImageVariantSize,
+) -> Result<Option<EncodedImage>, String> {
+    if !needs_variant(decoded.format, decoded.width(), decoded.height(), size) {
+        return Ok(None);
+    }
+
+    let resized = match size {
+        ImageVariantSize::Thumbnail => decoded.image.thumbnail(THUMBNAIL_EDGE, THUMBNAIL_EDGE),
+        _ => decoded
+            .image
+            .resize(DISPLAY_WIDTH, u32::MAX, FilterType::CatmullRom),
+    };
+
+    let format = if resized.color().has_alpha() {
+        ImageFormat::Png
+    } else {
+        ImageFormat::Jpeg
+    };
+
+    encode(&resized, format, VARIANT_JPEG_QUALITY).map(Some)
+}
+
+/** Applies the upload policy in `settings` to an uploaded image.
+ *
+ * The image is downscaled to `max_dimension`, converted to the target
+ * format and, with `auto_orient`, turned upright. Re-encoding drops the
+ * EXIF orientation, so images that get resized or converted are always
+ * turned upright. Images with transparency keep their format instead of
+ * becoming JPEGs, and GIFs are never touched so they keep their
+ * animation. Returns `None` when the upload should be stored as it is:
+ * when the policy is disabled, nothing needs to change, or re-encoding
+ * would not make the file smaller and it didn't need turning upright.
+ */
+pub fn recompress(
+    data: &[u8],
+    settings: &ImageUploadSettings,
+) -> Result<Option<EncodedImage>, String> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let Some(decoded) = decode_image(data) else {
+        return Ok(None);
+    };
+    if decoded.format == ImageFormat::Gif {
+        return Ok(None);
+    }
+
+    let has_alpha = decoded.image.color().has_alpha();
+    let format = match settings.format {
+        ImageUploadFormat::Jpeg if !has_alpha => ImageFormat::Jpeg,
+        _ => decoded.format,
+    };
+    let resize = settings
+        .max_dimension
+        .map(|max_dimension| max_dimension.max(1))
+        .filter(|max_dimension| decoded.width().max(decoded.height()) > *max_dimension);
+    let rotate = settings.auto_orient && decoded.rotated;
+    if resize.is_none() && !rotate && format == decoded.format {
+        return Ok(None);
+    }
+
+    let image = match resize {
+        Some(max_dimension) => {
+            decoded
+                .image
+                .resize(max_dimension, max_dimension, FilterType::CatmullRom)
+        }
+        None => decoded.image,
+    };
+    let encoded = encode(&image, format, settings.jpeg_quality.clamp(1, 100))?;
+    if !rotate && encoded.data.len() >= data.len() {
+        return Ok(None);
+    }
+
+    Ok(Some(encoded))
+}
+
+/** Encodes `image` as `format`; JPEGs use `jpeg_quality` and drop any alpha. */
+fn encode(
+    image: &DynamicImage,
+    format: ImageFormat,
+    jpeg_quality: u8,
+) -> Result<EncodedImage, String> {
+    let mut data = Vec::new();
+    let result = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut data, jpeg_quality)),
+        _ => image.write_to(&mut Cursor::new(&mut data), format),
+    };
+    result.map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    let (extension, mime_type) = match format {
+        ImageFormat::Jpeg => ("jpg", "image/jpeg"),
+        ImageFormat::WebP => ("webp", "image/webp"),
+        _ => ("png", "image/png"),
+    };
+
+    Ok(EncodedImage {
+        data,
+        width: image.width(),
+        height: image.height(),
+        extension,
+        mime_type,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::images::ImageUploadFormat;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Tag, Value};
+    use image::{RgbImage, RgbaImage};
+    use img_parts::ImageEXIF;
+    use img_parts::jpeg::Jpeg;
+
+    /** An opaque image filled with noise, so it doesn't compress to nothing. */
+    fn noise(width: u32, height: u32) -> DynamicImage {
+        let mut state: u32 = 0x2545_f491;
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |_, _| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let [r, g, b, _] = state.to_le_bytes();
+            image::Rgb([r, g, b])
+        }))
+    }
+
+    fn encoded(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, format).unwrap();
+        data.into_inner()
+    }
+
+    /** A JPEG whose EXIF says it has to be turned 90 degrees to be upright. */
+    fn rotated_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let mut writer = Writer::new();
+        let orientation = Field {
+            tag: Tag::Orientation,
+            ifd_num: In::PRIMARY,
+            value: Value::Short(vec![6]),
+        };
+        writer.push_field(&orientation);
+        let mut exif = Cursor::new(Vec::new());
+        writer.write(&mut exif, false).unwrap();
+
+        let mut jpeg =
+            Jpeg::from_bytes(encoded(&noise(width, height), ImageFormat::Jpeg).into()).unwrap();
+        jpeg.set_exif(Some(exif.into_inner().into()));
+        jpeg.encoder().bytes().to_vec()
+    }
+
+    #[test]
+    fn decode_image_sniffs_the_format_and_turns_images_upright() {
+        let png = decode_image(&encoded(&noise(8, 4), ImageFormat::Png)).unwrap();
+        assert_eq!(png.format, ImageFormat::Png);
+        assert_eq!((png.width(), png.height(), png.rotated), (8, 4, false));
+
+        let jpeg = decode_image(&rotated_jpeg(8, 4)).unwrap();
+        assert_eq!(jpeg.format, ImageFormat::Jpeg);
+        assert_eq!((jpeg.width(), jpeg.height(), jpeg.rotated), (4, 8, true));
+
+        assert!(decode_image(b"not an image").is_none());
+        assert!(decode_image(&encoded(&noise(8, 4), ImageFormat::Png)[..40]).is_none());
+    }
+
+    #[test]
+    fn image_dimensions_reads_the_upright_size() {
+        let path =
+            std::env::temp_dir().join(format!("unfold-rotated-{}.jpg", uuid::Uuid::new_v4()));
+        std::fs::write(&path, rotated_jpeg(8, 4)).unwrap();
+
+        assert_eq!(image_dimensions(&path), Some((4, 8)));
+
+        std::fs::remove_file(&path).ok();
+        assert_eq!(image_dimensions(&path), None);
+    }
+
+    #[test]
+    fn needs_variant_skips_images_that_already_fit() {
+        use ImageVariantSize::*;
+
+        assert!(needs_variant(ImageFormat::Png, 321, 10, Thumbnail));
+        assert!(needs_variant(ImageFormat::Png, 10, 321, Thumbnail));
+        assert!(!needs_variant(ImageFormat::Png, 320, 320, Thumbnail));
+        assert!(needs_variant(ImageFormat::Jpeg, 1601, 10, Display));
+        assert!(!needs_variant(ImageFormat::Jpeg, 1600, 4000, Display));
+        assert!(!needs_variant(ImageFormat::Gif, 4000, 4000, Display));
+        assert!(needs_variant(ImageFormat::Gif, 4000, 4000, Thumbnail));
+        assert!(!needs_variant(ImageFormat::Png, 4000, 4000, Original));
+    }
+
+    #[test]
+    fn render_variant_resizes_and_picks_a_format_by_transparency() {
+        let wide = decode_image(&encoded(&noise(3200, 20), ImageFormat::Png)).unwrap();
+
+        let display = render_variant(&wide, ImageVariantSize::Display)
+            .unwrap()
+            .unwrap();
+        assert_eq!((display.width, display.height), (1600, 10));
+        assert_eq!(
+            (display.extension, display.mime_type),
+            ("jpg", "image/jpeg")
+        );
+        assert_eq!(
+            decode_image(&display.data).unwrap().format,
+            ImageFormat::Jpeg
+        );
+
+        let opaque = decode_image(&encoded(&noise(640, 320), ImageFormat::Png)).unwrap();
+        let thumbnail = render_variant(&opaque, ImageVariantSize::Thumbnail)
+            .unwrap()
+            .unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (320, 160));
+
+        let transparent = DynamicImage::ImageRgba8(RgbaImage::new(400, 400));
+        let transparent = decode_image(&encoded(&transparent, ImageFormat::Png)).unwrap();
+        let thumbnail = render_variant(&transparent, ImageVariantSize::Thumbnail)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (thumbnail.extension, thumbnail.mime_type),
+            ("png", "image/png")
+        );
+
+        let small = decode_image(&encoded(&noise(100, 100), ImageFormat::Png)).unwrap();
+        assert!(
+            render_variant(&small, ImageVariantSize::Thumbnail)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    fn policy(format: ImageUploadFormat, max_dimension: Option<u32>) -> ImageUploadSettings {
+        ImageUploadSettings {
+            enabled: true,
+            max_dimension,
+            format,
+            ..ImageUploadSettings::default()
+        }
+    }
+
+    #[test]
+    fn recompress_does_nothing_when_disabled_or_already_compliant() {
+        let png = encoded(&noise(64, 32), ImageFormat::Png);
+
+        let disabled = ImageUploadSettings {
+            enabled: false,
+            ..policy(ImageUploadFormat::Jpeg, Some(16))
+        };
+        assert!(recompress(&png, &disabled).unwrap().is_none());
+        assert!(
+            recompress(&png, &policy(ImageUploadFormat::Original, Some(64)))
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            recompress(b"not an image", &policy(ImageUploadFormat::Jpeg, Some(16)))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn recompress_downscales_and_converts_opaque_images() {
+        let png = encoded(&noise(400, 200), ImageFormat::Png);
+
+        let encoded = recompress(&png, &policy(ImageUploadFormat::Jpeg, Some(100)))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!((encoded.width, encoded.height), (100, 50));
+        assert_eq!(encoded.extension, "jpg");
+        assert!(encoded.data.len() < png.len());
+    }
+
+    #[test]
+    fn recompress_keeps_transparent_images_out_of_jpeg() {
+        let transparent = DynamicImage::ImageRgba8(RgbaImage::from_fn(400, 200, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, (x ^ y) as u8])
+        }));
+        let png = encoded(&transparent, ImageFormat::Png);
+
+        let encoded = recompress(&png, &policy(ImageUploadFormat::Jpeg, Some(100)))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(encoded.extension, "png");
+        assert_eq!((encoded.width, encoded.height), (100, 50));
+    }
+
+    #[test]
+    fn recompress_leaves_gifs_alone() {
+        let gif = encoded(&noise(400, 200), ImageFormat::Gif);
+
+        assert!(
+            recompress(&gif, &policy(ImageUploadFormat::Jpeg, Some(100)))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn recompress_turns_rotated_images_upright_even_if_larger() {
+        let jpeg = rotated_jpeg(8, 4);
+        let settings = ImageUploadSettings {
+            jpeg_quality: 100,
+            ..policy(ImageUploadFormat::Original, None)
+        };
+
+        let encoded = recompress(&jpeg, &settings).unwrap().unwrap();
+        assert_eq!((encoded.width, encoded.height), (4, 8));
+
+        let without_orienting = ImageUploadSettings {
+            auto_orient: false,
+            ..settings
+        };
+        assert!(recompress(&jpeg, &without_orienting).unwrap().is_none());
+    }
+
+    #[test]
+    fn recompress_keeps_the_upload_when_re_encoding_would_grow_it() {
+        let flat =
+            DynamicImage::ImageRgb8(RgbImage::from_pixel(300, 300, image::Rgb([40, 90, 160])));
+        let png = encoded(&flat, ImageFormat::Png);
+        let settings = ImageUploadSettings {
+            jpeg_quality: 100,
+            ..policy(ImageUploadFormat::Jpeg, None)
+        };
+
+        assert!(recompress(&png, &settings).unwrap().is_none());
+    }
+}
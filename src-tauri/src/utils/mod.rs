@@ -1,3 +1,10 @@
+pub mod attachments;
+pub mod image_metadata;
+pub mod images;
+pub mod tiptap;
 pub mod web_import;
 
+pub use attachments::{
+    content_hash, copy_attachment_files, hash_file, quarantine_files, remove_attachment_files,
+};
 pub use web_import::{normalize_and_validate_external_url, normalize_and_validate_url};
@@ -0,0 +1,804 @@
+use crate::models::links::LinkKind;
+use crate::models::revisions::BlockDiff;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/** Extracts the plain text of a TipTap JSON document.
+ *
+ * Text nodes are concatenated in document order and each block node is
+ * terminated by a newline. Content that is not valid JSON is returned
+ * unchanged so legacy plain-text notes remain searchable.
+ */
+pub fn extract_text(content: &str) -> String {
+    let Ok(document) = serde_json::from_str::<Value>(content) else {
+        return content.to_string();
+    };
+
+    let mut text = String::new();
+    collect_text(&document, &mut text);
+    text.trim().to_string()
+}
+
+fn collect_text(node: &Value, out: &mut String) {
+    if let Some(text) = node.get("text").and_then(Value::as_str) {
+        out.push_str(text);
+    }
+
+    if node.get("type").and_then(Value::as_str) == Some("hardBreak") {
+        out.push('\n');
+    }
+
+    if let Some(children) = node.get("content").and_then(Value::as_array) {
+        for child in children {
+            collect_text(child, out);
+        }
+
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+}
+
+/** Returns the top-level blocks of a TipTap JSON document.
+ *
+ * Missing, empty or unparsable content yields no blocks.
+ */
+pub fn top_level_blocks(content: Option<&str>) -> Vec<Value> {
+    content
+        .and_then(|content| serde_json::from_str::<Value>(content).ok())
+        .and_then(|mut document| match document.get_mut("content") {
+            Some(Value::Array(blocks)) => Some(std::mem::take(blocks)),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/** Computes a block-level diff between two TipTap JSON documents.
+ *
+ * Blocks are matched with a longest common subsequence. Runs of removed
+ * blocks directly followed by added ones are paired up as modifications.
+ */
+pub fn diff_blocks(before: Option<&str>, after: Option<&str>) -> Vec<BlockDiff> {
+    let before = top_level_blocks(before);
+    let after = top_level_blocks(after);
+
+    // lcs[i][j] is the LCS length of before[i..] and after[j..].
+    let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if before[i] == after[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            flush_changes(&mut diff, &mut removed, &mut added);
+            diff.push(BlockDiff::Unchanged {
+                block: before[i].clone(),
+            });
+            i += 1;
+            j += 1;
+        } else if j < after.len() && (i == before.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(after[j].clone());
+            j += 1;
+        } else {
+            removed.push(before[i].clone());
+            i += 1;
+        }
+    }
+    flush_changes(&mut diff, &mut removed, &mut added);
+
+    diff
+}
+
+fn flush_changes(diff: &mut Vec<BlockDiff>, removed: &mut Vec<Value>, added: &mut Vec<Value>) {
+    let mut removed = removed.drain(..);
+    let mut added = added.drain(..);
+
+    loop {
+        match (removed.next(), added.next()) {
+            (Some(before), Some(after)) => diff.push(BlockDiff::Modified { before, after }),
+            (Some(block), None) => diff.push(BlockDiff::Removed { block }),
+            (None, Some(block)) => diff.push(BlockDiff::Added { block }),
+            (None, None) => break,
+        }
+    }
+}
+
+/** A reference to another note found in a TipTap document. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedLink {
+    pub kind: LinkKind,
+    pub label: String,
+    pub href: Option<String>,
+    pub target_id: Option<String>,
+    pub target_name: Option<String>,
+}
+
+/** Returns the node ID an in-app note route points at.
+ *
+ * Note routes have the form `/spaces/<space_id>/files/<node_id>`; any
+ * other href yields `None`.
+ */
+pub fn node_id_from_href(href: &str) -> Option<String> {
+    let path = href.split(['?', '#']).next().unwrap_or_default();
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        ["", "spaces", space_id, "files", node_id]
+            if !space_id.is_empty() && !node_id.is_empty() =>
+        {
+            Some(node_id.to_string())
+        }
+        _ => None,
+    }
+}
+
+/** Extracts links to other notes from a TipTap JSON document.
+ *
+ * Link marks pointing at a note route are returned with the target's
+ * ID, merging adjacent text nodes that share the same href. `[[Name]]`
+ * and `[[Name|label]]` references are found in the combined text of each
+ * block, so they may span differently formatted text nodes. One link is
+ * returned per occurrence, block by block.
+ */
+pub fn extract_links(content: &str) -> Vec<ExtractedLink> {
+    let Ok(document) = serde_json::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+
+    let mut links = Vec::new();
+    collect_links(&document, &mut links);
+    links
+}
+
+fn collect_links(node: &Value, out: &mut Vec<ExtractedLink>) {
+    let Some(children) = node.get("content").and_then(Value::as_array) else {
+        return;
+    };
+
+    let mut text = String::new();
+    let mut current_link: Option<(String, String)> = None;
+    for child in children {
+        let Some(child_text) = child.get("text").and_then(Value::as_str) else {
+            flush_link(&mut current_link, out);
+            collect_links(child, out);
+            continue;
+        };
+        text.push_str(child_text);
+
+        let href = link_href(child).filter(|href| node_id_from_href(href).is_some());
+        match (&mut current_link, href) {
+            (Some((current_href, label)), Some(href)) if current_href == href => {
+                label.push_str(child_text);
+            }
+            (_, href) => {
+                flush_link(&mut current_link, out);
+                current_link = href.map(|href| (href.to_string(), child_text.to_string()));
+            }
+        }
+    }
+    flush_link(&mut current_link, out);
+
+    for reference in find_wiki_references(&text) {
+        let name = &text[reference.name];
+        out.push(ExtractedLink {
+            kind: LinkKind::Wiki,
+            label: reference
+                .label
+                .map(|label| text[label].to_string())
+                .unwrap_or_else(|| name.to_string()),
+            href: None,
+            target_id: None,
+            target_name: Some(name.to_string()),
+        });
+    }
+}
+
+fn link_href(node: &Value) -> Option<&str> {
+    node.get("marks")?
+        .as_array()?
+        .iter()
+        .find(|mark| mark.get("type").and_then(Value::as_str) == Some("link"))?
+        .get("attrs")?
+        .get("href")?
+        .as_str()
+}
+
+fn flush_link(current: &mut Option<(String, String)>, out: &mut Vec<ExtractedLink>) {
+    if let Some((href, label)) = current.take() {
+        out.push(ExtractedLink {
+            kind: LinkKind::Link,
+            target_id: node_id_from_href(&href),
+            label,
+            href: Some(href),
+            target_name: None,
+        });
+    }
+}
+
+/** Byte ranges of a `[[name|label]]` reference, with the name trimmed. */
+struct WikiReference {
+    name: Range<usize>,
+    label: Option<Range<usize>>,
+}
+
+fn find_wiki_references(text: &str) -> Vec<WikiReference> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = text[offset..].find("[[") {
+        let inner_start = offset + found + 2;
+        let Some(length) = text[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &text[inner_start..inner_start + length];
+
+        // "[[a [[b]]" should match "[[b]]", so restart just after the
+        // opening bracket rather than after the closing ones.
+        if inner.contains(['[', ']', '\n']) {
+            offset = offset + found + 1;
+            continue;
+        }
+        offset = inner_start + length + 2;
+
+        let (name, label) = match inner.split_once('|') {
+            Some((name, label)) => (name, Some(label)),
+            None => (inner, None),
+        };
+        let name_start = inner_start + (name.len() - name.trim_start().len());
+        let name = name_start..name_start + name.trim().len();
+        if name.is_empty() {
+            continue;
+        }
+
+        let label = label.filter(|label| !label.trim().is_empty()).map(|label| {
+            let label_start =
+                inner_start + inner.len() - label.len() + (label.len() - label.trim_start().len());
+            label_start..label_start + label.trim().len()
+        });
+
+        references.push(WikiReference { name, label });
+    }
+
+    references
+}
+
+/** Rewrites `[[old_name]]` references in a TipTap JSON document to `new_name`.
+ *
+ * Names are compared ignoring ASCII case, like the link index. Labels
+ * are kept. Only references contained in a single text node are
+ * rewritten. Returns `None` when nothing changed or `new_name` cannot
+ * be written as a wiki reference.
+ */
+pub fn rewrite_wiki_links(content: &str, old_name: &str, new_name: &str) -> Option<String> {
+    if new_name.trim().is_empty() || new_name.contains(['[', ']', '|', '\n']) {
+        return None;
+    }
+
+    let mut document = serde_json::from_str::<Value>(content).ok()?;
+    if !rewrite_text_nodes(&mut document, old_name, new_name.trim()) {
+        return None;
+    }
+
+    serde_json::to_string(&document).ok()
+}
+
+fn rewrite_text_nodes(node: &mut Value, old_name: &str, new_name: &str) -> bool {
+    let mut changed = false;
+
+    if let Some(Value::String(text)) = node.get_mut("text") {
+        for reference in find_wiki_references(text).into_iter().rev() {
+            if text[reference.name.clone()].eq_ignore_ascii_case(old_name.trim()) {
+                text.replace_range(reference.name, new_name);
+                changed = true;
+            }
+        }
+    }
+
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            changed |= rewrite_text_nodes(child, old_name, new_name);
+        }
+    }
+
+    changed
+}
+
+/** Normalizes a tag name: strips a leading `#`, lowercases it and
+ * validates each `/`-separated segment.
+ *
+ * Segments may contain letters, digits, `_` and `-`. Returns `None` for
+ * names that are empty or contain other characters.
+ */
+pub fn normalize_tag(name: &str) -> Option<String> {
+    let name = name.trim().trim_start_matches('#').to_lowercase();
+    let valid = name
+        .split('/')
+        .all(|segment| !segment.is_empty() && segment.chars().all(is_tag_char));
+
+    valid.then_some(name)
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/** Extracts inline `#tag` names from a TipTap JSON document.
+ *
+ * A tag starts with `#` at the beginning of a word and may be
+ * hierarchical (`#project/alpha`). Purely numeric tags such as `#1` are
+ * ignored, as is text in code blocks and code marks. Returns normalized,
+ * deduplicated names in sorted order.
+ */
+pub fn extract_tags(content: &str) -> Vec<String> {
+    let Ok(document) = serde_json::from_str::<Value>(content) else {
+        return Vec::new();
+    };
+
+    let mut tags = Vec::new();
+    collect_tags(&document, &mut tags);
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+fn collect_tags(node: &Value, out: &mut Vec<String>) {
+    if node.get("type").and_then(Value::as_str) == Some("codeBlock") {
+        return;
+    }
+    let Some(children) = node.get("content").and_then(Value::as_array) else {
+        return;
+    };
+
+    let mut text = String::new();
+    for child in children {
+        let Some(child_text) = child.get("text").and_then(Value::as_str) else {
+            text.push(' ');
+            collect_tags(child, out);
+            continue;
+        };
+
+        if has_mark(child, "code") {
+            text.push(' ');
+        } else {
+            text.push_str(child_text);
+        }
+    }
+
+    let mut previous = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let starts_tag = c == '#'
+            && !previous.is_some_and(|p: char| is_tag_char(p) || matches!(p, '#' | '/' | '&'));
+        previous = Some(c);
+        if !starts_tag {
+            continue;
+        }
+
+        let mut end = index + 1;
+        while let Some(&(next_index, next)) = chars.peek() {
+            if !is_tag_char(next) && next != '/' {
+                break;
+            }
+            end = next_index + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        let raw = text[index + 1..end].trim_end_matches('/');
+        if raw.chars().all(|c| c.is_ascii_digit() || c == '/') {
+            continue;
+        }
+        if let Some(tag) = normalize_tag(raw) {
+            out.push(tag);
+        }
+    }
+}
+
+fn has_mark(node: &Value, mark_type: &str) -> bool {
+    node.get("marks")
+        .and_then(Value::as_array)
+        .is_some_and(|marks| {
+            marks
+                .iter()
+                .any(|mark| mark.get("type").and_then(Value::as_str) == Some(mark_type))
+        })
+}
+
+/** Replacement for an attachment in a copied document. */
+#[derive(Debug, Clone)]
+pub struct AttachmentRewrite {
+    pub id: String,
+    pub old_filename: String,
+    pub new_filename: String,
+}
+
+/** Points attachment nodes at copied attachments.
+ *
+ * `rewrites` is keyed by the original attachment ID. Nodes whose
+ * `attachmentId` matches get the new `attachmentId`, and the original file name in their
+ * `src` is replaced with the copy's. Returns `None` when nothing changed.
+ */
+pub fn rewrite_attachments(
+    content: &str,
+    rewrites: &HashMap<String, AttachmentRewrite>,
+) -> Option<String> {
+    let mut document = serde_json::from_str::<Value>(content).ok()?;
+    if !rewrite_attachment_nodes(&mut document, rewrites) {
+        return None;
+    }
+
+    serde_json::to_string(&document).ok()
+}
+
+fn rewrite_attachment_nodes(
+    node: &mut Value,
+    rewrites: &HashMap<String, AttachmentRewrite>,
+) -> bool {
+    let mut changed = false;
+
+    if let Some(attrs) = node.get_mut("attrs").and_then(Value::as_object_mut)
+        && let Some(rewrite) = attrs
+            .get("attachmentId")
+            .and_then(Value::as_str)
+            .and_then(|id| rewrites.get(id))
+    {
+        if let Some(Value::String(src)) = attrs.get_mut("src") {
+            *src = src.replace(&rewrite.old_filename, &rewrite.new_filename);
+        }
+        attrs.insert(
+            "attachmentId".to_string(),
+            Value::String(rewrite.id.clone()),
+        );
+        changed = true;
+    }
+
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            changed |= rewrite_attachment_nodes(child, rewrites);
+        }
+    }
+
+    changed
+}
+
+/** Points attachment nodes at files that moved on disk.
+ *
+ * `moves` maps an attachment ID to its `(old_path, new_path)`. The `src`
+ * of an attachment node is an asset URL wrapping the percent-encoded file path, so
+ * the encoded old path is swapped for the encoded new one. Returns `None`
+ * when nothing changed.
+ */
+pub fn rewrite_attachment_paths(
+    content: &str,
+    moves: &HashMap<String, (String, String)>,
+) -> Option<String> {
+    let mut document = serde_json::from_str::<Value>(content).ok()?;
+    if !rewrite_attachment_sources(&mut document, moves) {
+        return None;
+    }
+
+    serde_json::to_string(&document).ok()
+}
+
+fn rewrite_attachment_sources(node: &mut Value, moves: &HashMap<String, (String, String)>) -> bool {
+    let mut changed = false;
+
+    if let Some(attrs) = node.get_mut("attrs").and_then(Value::as_object_mut)
+        && let Some((old_path, new_path)) = attrs
+            .get("attachmentId")
+            .and_then(Value::as_str)
+            .and_then(|id| moves.get(id))
+        && let Some(Value::String(src)) = attrs.get_mut("src")
+    {
+        let encoded = encode_uri_component(old_path);
+        if src.contains(&encoded) {
+            *src = src.replace(&encoded, &encode_uri_component(new_path));
+            changed = true;
+        } else if src.contains(old_path.as_str()) {
+            *src = src.replace(old_path.as_str(), new_path);
+            changed = true;
+        }
+    }
+
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            changed |= rewrite_attachment_sources(child, moves);
+        }
+    }
+
+    changed
+}
+
+/** Percent-encodes `value` the way JavaScript's `encodeURIComponent` does. */
+fn encode_uri_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paragraph(text: &str) -> Value {
+        json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+    }
+
+    fn doc(blocks: Vec<Value>) -> String {
+        json!({ "type": "doc", "content": blocks }).to_string()
+    }
+
+    fn wiki_names(text: &str) -> Vec<(&str, Option<&str>)> {
+        find_wiki_references(text)
+            .into_iter()
+            .map(|reference| {
+                (
+                    &text[reference.name],
+                    reference.label.map(|label| &text[label]),
+                )
+            })
+            .collect()
+    }
+
+    /** Summarizes a diff entry as its kind and the text of its blocks. */
+    fn summarize(entry: &BlockDiff) -> String {
+        let text = |block: &Value| extract_text(&block.to_string());
+        match entry {
+            BlockDiff::Unchanged { block } => format!("= {}", text(block)),
+            BlockDiff::Added { block } => format!("+ {}", text(block)),
+            BlockDiff::Removed { block } => format!("- {}", text(block)),
+            BlockDiff::Modified { before, after } => {
+                format!("~ {} -> {}", text(before), text(after))
+            }
+        }
+    }
+
+    #[test]
+    fn extract_text_separates_blocks_and_hard_breaks() {
+        let content = doc(vec![
+            json!({ "type": "heading", "content": [{ "type": "text", "text": "Title" }] }),
+            json!({ "type": "paragraph", "content": [
+                { "type": "text", "text": "one" },
+                { "type": "hardBreak" },
+                { "type": "text", "text": "two", "marks": [{ "type": "bold" }] }
+            ] }),
+        ]);
+
+        assert_eq!(extract_text(&content), "Title\none\ntwo");
+        assert_eq!(extract_text("plain legacy note"), "plain legacy note");
+        assert_eq!(extract_text(&doc(vec![])), "");
+    }
+
+    #[test]
+    fn find_wiki_references_reads_names_and_labels() {
+        assert_eq!(
+            wiki_names("see [[ Alpha ]] and [[Beta|the beta]]"),
+            vec![("Alpha", None), ("Beta", Some("the beta"))]
+        );
+        assert_eq!(wiki_names("[[a [[b]]"), vec![("b", None)]);
+        assert_eq!(wiki_names("[[]] [[ |label]] [[open"), vec![]);
+        assert_eq!(wiki_names("[[line\nbreak]]"), vec![]);
+        assert_eq!(wiki_names("[[Name| ]]"), vec![("Name", None)]);
+    }
+
+    #[test]
+    fn extract_links_merges_adjacent_link_text_and_finds_wiki_references() {
+        let link = |text: &str| {
+            json!({ "type": "text", "text": text, "marks": [
+                { "type": "link", "attrs": { "href": "/spaces/s1/files/n1" } }
+            ] })
+        };
+        let content = doc(vec![json!({ "type": "paragraph", "content": [
+            link("Target "),
+            link("note"),
+            { "type": "text", "text": " and [[Other" },
+            { "type": "text", "text": " Note]]", "marks": [{ "type": "italic" }] },
+            { "type": "text", "text": " [web](x)", "marks": [
+                { "type": "link", "attrs": { "href": "https://example.com" } }
+            ] }
+        ] })]);
+
+        let links = extract_links(&content);
+
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].kind, LinkKind::Link);
+        assert_eq!(links[0].label, "Target note");
+        assert_eq!(links[0].target_id.as_deref(), Some("n1"));
+        assert_eq!(links[1].kind, LinkKind::Wiki);
+        assert_eq!(links[1].target_name.as_deref(), Some("Other Note"));
+    }
+
+    #[test]
+    fn node_id_from_href_only_accepts_note_routes() {
+        assert_eq!(
+            node_id_from_href("/spaces/s/files/n?x=1#top").as_deref(),
+            Some("n")
+        );
+        assert_eq!(node_id_from_href("/spaces//files/n"), None);
+        assert_eq!(
+            node_id_from_href("https://example.com/spaces/s/files/n"),
+            None
+        );
+    }
+
+    #[test]
+    fn rewrite_wiki_links_renames_matching_references_only() {
+        let content = doc(vec![paragraph(
+            "[[old name|label]] [[OLD NAME]] [[older name]]",
+        )]);
+
+        let rewritten = rewrite_wiki_links(&content, "Old Name", "New").unwrap();
+
+        assert_eq!(
+            extract_text(&rewritten),
+            "[[New|label]] [[New]] [[older name]]"
+        );
+        assert_eq!(rewrite_wiki_links(&content, "missing", "New"), None);
+        assert_eq!(rewrite_wiki_links(&content, "old name", "bad]]name"), None);
+        assert_eq!(rewrite_wiki_links(&content, "old name", "  "), None);
+    }
+
+    #[test]
+    fn extract_tags_skips_numbers_code_and_mid_word_hashes() {
+        let content = doc(vec![
+            json!({ "type": "paragraph", "content": [
+                { "type": "text", "text": "#Project/Alpha issue #1 #2/3 a#b &#39; #x/ #todo" },
+                { "type": "text", "text": " #inline", "marks": [{ "type": "code" }] },
+                { "type": "text", "text": " #todo #v2" }
+            ] }),
+            json!({ "type": "codeBlock", "content": [{ "type": "text", "text": "#include" }] }),
+        ]);
+
+        assert_eq!(
+            extract_tags(&content),
+            vec!["project/alpha", "todo", "v2", "x"]
+        );
+        assert_eq!(extract_tags("not json #tag"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn normalize_tag_validates_segments() {
+        assert_eq!(
+            normalize_tag("#Area/Sub-Topic_2").as_deref(),
+            Some("area/sub-topic_2")
+        );
+        assert_eq!(normalize_tag("a//b"), None);
+        assert_eq!(normalize_tag("#"), None);
+        assert_eq!(normalize_tag("with space"), None);
+    }
+
+    #[test]
+    fn diff_blocks_pairs_replacements_as_modifications() {
+        let before = doc(vec![paragraph("a"), paragraph("b"), paragraph("c")]);
+        let after = doc(vec![
+            paragraph("a"),
+            paragraph("B"),
+            paragraph("c"),
+            paragraph("d"),
+        ]);
+
+        let diff: Vec<String> = diff_blocks(Some(&before), Some(&after))
+            .iter()
+            .map(summarize)
+            .collect();
+
+        assert_eq!(diff, vec!["= a", "~ b -> B", "= c", "+ d"]);
+    }
+
+    #[test]
+    fn diff_blocks_treats_missing_content_as_empty() {
+        let content = doc(vec![paragraph("a")]);
+
+        let added: Vec<String> = diff_blocks(None, Some(&content))
+            .iter()
+            .map(summarize)
+            .collect();
+        let removed: Vec<String> = diff_blocks(Some(&content), Some("not json"))
+            .iter()
+            .map(summarize)
+            .collect();
+
+        assert_eq!(added, vec!["+ a"]);
+        assert_eq!(removed, vec!["- a"]);
+        assert!(diff_blocks(None, None).is_empty());
+    }
+
+    #[test]
+    fn encode_uri_component_matches_javascript() {
+        // Expected values from `encodeURIComponent` in a browser.
+        assert_eq!(
+            encode_uri_component("/Users/me/My Notes/images/a b.png"),
+            "%2FUsers%2Fme%2FMy%20Notes%2Fimages%2Fa%20b.png"
+        );
+        assert_eq!(
+            encode_uri_component("C:\\Notes\\ü?&=#+:"),
+            "C%3A%5CNotes%5C%C3%BC%3F%26%3D%23%2B%3A"
+        );
+        assert_eq!(encode_uri_component("-_.!~*'()"), "-_.!~*'()");
+    }
+
+    #[test]
+    fn rewrite_attachment_paths_swaps_encoded_and_plain_paths() {
+        let src = format!(
+            "asset://localhost/{}",
+            encode_uri_component("/old/images/a.png")
+        );
+        let content = doc(vec![
+            json!({ "type": "image", "attrs": { "attachmentId": "a", "src": src } }),
+            json!({ "type": "fileAttachment", "attrs": { "attachmentId": "b", "src": "/old/images/b.pdf" } }),
+            json!({ "type": "image", "attrs": { "attachmentId": "c", "src": "untouched" } }),
+        ]);
+        let moves = HashMap::from([
+            (
+                "a".to_string(),
+                (
+                    "/old/images/a.png".to_string(),
+                    "/new/images/a.png".to_string(),
+                ),
+            ),
+            (
+                "b".to_string(),
+                (
+                    "/old/images/b.pdf".to_string(),
+                    "/new/images/b.pdf".to_string(),
+                ),
+            ),
+        ]);
+
+        let rewritten: Value =
+            serde_json::from_str(&rewrite_attachment_paths(&content, &moves).unwrap()).unwrap();
+
+        assert_eq!(
+            rewritten["content"][0]["attrs"]["src"],
+            format!(
+                "asset://localhost/{}",
+                encode_uri_component("/new/images/a.png")
+            )
+        );
+        assert_eq!(rewritten["content"][1]["attrs"]["src"], "/new/images/b.pdf");
+        assert_eq!(rewritten["content"][2]["attrs"]["src"], "untouched");
+        assert_eq!(rewrite_attachment_paths(&content, &HashMap::new()), None);
+    }
+
+    #[test]
+    fn rewrite_attachments_points_copies_at_new_attachments() {
+        let content = doc(vec![json!({ "type": "image", "attrs": {
+            "attachmentId": "a",
+            "src": "asset://localhost/%2Fimages%2Fold.png"
+        } })]);
+        let rewrites = HashMap::from([(
+            "a".to_string(),
+            AttachmentRewrite {
+                id: "a2".to_string(),
+                old_filename: "old.png".to_string(),
+                new_filename: "new.png".to_string(),
+            },
+        )]);
+
+        let rewritten: Value =
+            serde_json::from_str(&rewrite_attachments(&content, &rewrites).unwrap()).unwrap();
+
+        assert_eq!(rewritten["content"][0]["attrs"]["attachmentId"], "a2");
+        assert_eq!(
+            rewritten["content"][0]["attrs"]["src"],
+            "asset://localhost/%2Fimages%2Fnew.png"
+        );
+    }
+}
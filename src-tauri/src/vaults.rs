@@ -0,0 +1,297 @@
+use crate::backups;
+use crate::config::{self, StorageLocation};
+use crate::migrations;
+use crate::models::backups::BackupSettings;
+use crate::models::trash::TrashSettings;
+use crate::models::vaults::{Vault, VaultList, VaultRegistry};
+use crate::repository::{self, Database, DbPool};
+use crate::settings;
+use crate::storage;
+use crate::utils;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+/** ID of the vault in the app data directory, which always exists. */
+pub const DEFAULT_VAULT_ID: &str = "default";
+
+/** Emitted with the opened `Vault` after switching vaults. */
+pub const VAULT_CHANGED_EVENT: &str = "vault-changed";
+
+fn load_registry(app: &AppHandle) -> VaultRegistry {
+    settings::load(app, settings::VAULTS_KEY)
+}
+
+fn default_vault(app: &AppHandle) -> Result<Vault, String> {
+    let location = config::default_storage_location(app)?;
+
+    Ok(Vault {
+        id: DEFAULT_VAULT_ID.to_string(),
+        name: "Default".to_string(),
+        path: location.root().to_string_lossy().to_string(),
+        last_opened_at: None,
+    })
+}
+
+fn vault_location(app: &AppHandle, vault: &Vault) -> Result<StorageLocation, String> {
+    if vault.id == DEFAULT_VAULT_ID {
+        config::default_storage_location(app)
+    } else {
+        Ok(StorageLocation::shared(&vault.path))
+    }
+}
+
+fn same_folder(a: &Path, b: &Path) -> bool {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    canonical(a) == canonical(b)
+}
+
+/** Resolves the location of the vault to open at startup.
+ *
+ * Falls back to the default vault when the last active vault is no longer
+ * registered or its folder is unreachable, e.g. on an unmounted drive.
+ */
+pub fn startup_location(app: &AppHandle) -> Result<StorageLocation, String> {
+    let registry = load_registry(app);
+    let active = registry
+        .active_id
+        .as_deref()
+        .and_then(|id| registry.vaults.iter().find(|vault| vault.id == id));
+
+    if let Some(vault) = active {
+        if Path::new(&vault.path).is_dir() {
+            return Ok(StorageLocation::shared(&vault.path));
+        }
+        eprintln!(
+            "Vault folder {} is unavailable, opening the default vault",
+            vault.path
+        );
+    }
+
+    config::default_storage_location(app)
+}
+
+/** Opens the database of `location` and brings it up to date.
+ *
+ * Creates the folder if needed, lets the asset protocol serve its images,
+ * applies migrations, merges stray data into the default vault, points
+ * attachment paths stored elsewhere at this folder, hashes attachment
+ * files not yet stored by content and purges expired trash.
+ */
+pub fn open_pool(app: &AppHandle, location: StorageLocation) -> Result<Arc<DbPool>, String> {
+    fs::create_dir_all(location.root())
+        .map_err(|e| format!("Failed to create vault folder: {}", e))?;
+    app.asset_protocol_scope()
+        .allow_directory(location.images_dir(), true)
+        .map_err(|e| format!("Failed to allow vault images: {}", e))?;
+
+    let pool = DbPool::open(location.clone())?;
+    let mut conn = pool.get()?;
+    let backup_settings: BackupSettings = settings::load(app, settings::BACKUPS_KEY);
+    let backups_dir = backups::resolve_backups_dir(&location, &backup_settings);
+    migrations::run_migrations(&mut conn, Some(&backups_dir))?;
+
+    // Image commands used to write to the local data dir; fold anything
+    // left there into the default vault.
+    if !location.is_shared()
+        && let Some(stray_root) = config::stray_storage_root(app, &location)
+    {
+        match storage::merge_stray_location(&mut conn, &stray_root, &location) {
+            Ok(Some(merged)) => println!(
+                "Merged {} attachment rows, {} files and {} notes from {}",
+                merged.attachment_rows,
+                merged.moved_files,
+                merged.rewritten_notes,
+                stray_root.display()
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to merge {}: {}", stray_root.display(), e),
+        }
+    }
+
+    // Paths are stored absolute; a synced or moved vault still has the old ones.
+    match storage::relocate_attachments(&mut conn, &location) {
+        Ok(Some(relocation)) => println!(
+            "Relocated {} attachment paths and {} notes into {}",
+            relocation.rewritten_rows,
+            relocation.rewritten_notes,
+            location.root().display()
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to relocate attachments: {}", e),
+    }
+
+    match repository::attachments::dedupe_attachments(&mut conn) {
+        Ok(dedupe) => {
+            if dedupe.merged_rows > 0 {
+                println!(
+                    "Merged {} duplicate attachments of {} hashed, rewrote {} notes",
+                    dedupe.merged_rows, dedupe.hashed_rows, dedupe.rewritten_notes
+                );
+            }
+            utils::remove_attachment_files(&dedupe.file_paths);
+        }
+        Err(e) => eprintln!("Failed to deduplicate attachments: {}", e),
+    }
+
+    let trash_settings: TrashSettings = settings::load(app, settings::TRASH_KEY);
+    if let Some(days) = trash_settings.purge_after_days {
+        match repository::trash::purge_trash(&mut conn, Some(days)) {
+            Ok(purged) => {
+                utils::remove_attachment_files(&purged.file_paths);
+            }
+            Err(e) => eprintln!("Failed to purge trash: {}", e),
+        }
+    }
+
+    drop(conn);
+    Ok(pool)
+}
+
+/** Returns the ID of the vault whose database is open.
+ *
+ * This can differ from the registry's `active_id` when the active vault
+ * was unavailable at startup.
+ */
+fn open_vault_id(app: &AppHandle, vaults: &[Vault]) -> Result<String, String> {
+    let current = config::storage_location(app)?;
+
+    Ok(vaults
+        .iter()
+        .find(|vault| vault_location(app, vault).is_ok_and(|location| location == current))
+        .map(|vault| vault.id.clone())
+        .unwrap_or_else(|| DEFAULT_VAULT_ID.to_string()))
+}
+
+/** Lists the default vault followed by the others, most recently opened first. */
+pub fn list_vaults(app: &AppHandle) -> Result<VaultList, String> {
+    let mut vaults = load_registry(app).vaults;
+    vaults.sort_by(|a, b| b.last_opened_at.cmp(&a.last_opened_at));
+    vaults.insert(0, default_vault(app)?);
+
+    Ok(VaultList {
+        active_id: open_vault_id(app, &vaults)?,
+        vaults,
+    })
+}
+
+/** Opens `vault` in place of the current one and records it as active.
+ *
+ * Commands already running finish against the previous vault's database.
+ */
+fn switch_to(app: &AppHandle, mut vault: Vault) -> Result<Vault, String> {
+    let database = app
+        .try_state::<Database>()
+        .ok_or_else(|| "Database is not open.".to_string())?;
+
+    let location = vault_location(app, &vault)?;
+    if database.pool().location() != &location {
+        database.replace(open_pool(app, location)?);
+    }
+
+    let mut registry = load_registry(app);
+    if vault.id == DEFAULT_VAULT_ID {
+        registry.active_id = None;
+    } else {
+        vault.last_opened_at = Some(
+            repository::open_connection(app)?
+                .query_row("SELECT datetime('now')", [], |row| row.get(0))
+                .map_err(|e| format!("Failed to read timestamp: {}", e))?,
+        );
+        registry.vaults.retain(|existing| existing.id != vault.id);
+        registry.vaults.push(vault.clone());
+        registry.active_id = Some(vault.id.clone());
+    }
+    settings::save(app, settings::VAULTS_KEY, &registry)?;
+
+    app.emit(VAULT_CHANGED_EVENT, &vault)
+        .map_err(|e| format!("Failed to announce vault change: {}", e))?;
+
+    Ok(vault)
+}
+
+/** Switches to a vault from the registry. */
+pub fn switch_vault(app: &AppHandle, vault_id: &str) -> Result<Vault, String> {
+    let vault = if vault_id == DEFAULT_VAULT_ID {
+        default_vault(app)?
+    } else {
+        load_registry(app)
+            .vaults
+            .into_iter()
+            .find(|vault| vault.id == vault_id)
+            .ok_or_else(|| format!("Vault not found: {}", vault_id))?
+    };
+
+    if !Path::new(&vault.path).is_dir() {
+        return Err(format!("Vault folder is unavailable: {}", vault.path));
+    }
+
+    switch_to(app, vault)
+}
+
+/** Opens `path` as a vault, registering it on first use.
+ *
+ * An empty folder becomes a new vault; a folder holding a vault database,
+ * e.g. on a synced drive, is opened as is.
+ */
+pub fn open_vault(app: &AppHandle, path: &str, name: Option<&str>) -> Result<Vault, String> {
+    let path = Path::new(path.trim());
+    if !path.is_dir() {
+        return Err(format!("Vault folder does not exist: {}", path.display()));
+    }
+
+    let default = default_vault(app)?;
+    if same_folder(path, Path::new(&default.path)) {
+        return switch_to(app, default);
+    }
+
+    let name = name.map(str::trim).filter(|name| !name.is_empty());
+    let existing = load_registry(app)
+        .vaults
+        .into_iter()
+        .find(|vault| same_folder(path, Path::new(&vault.path)));
+
+    let vault = match existing {
+        Some(vault) => Vault {
+            name: name.map(str::to_string).unwrap_or(vault.name),
+            ..vault
+        },
+        None => Vault {
+            id: Uuid::new_v4().to_string(),
+            name: name
+                .map(str::to_string)
+                .or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+                .unwrap_or_else(|| "Vault".to_string()),
+            path: path.to_string_lossy().to_string(),
+            last_opened_at: None,
+        },
+    };
+
+    switch_to(app, vault)
+}
+
+/** Removes a vault from the registry, leaving its folder untouched. */
+pub fn forget_vault(app: &AppHandle, vault_id: &str) -> Result<VaultList, String> {
+    if vault_id == DEFAULT_VAULT_ID {
+        return Err("The default vault cannot be removed.".to_string());
+    }
+
+    let mut registry = load_registry(app);
+    if open_vault_id(app, &registry.vaults)? == vault_id {
+        return Err("Switch to another vault before removing this one.".to_string());
+    }
+
+    let count = registry.vaults.len();
+    registry.vaults.retain(|vault| vault.id != vault_id);
+    if registry.vaults.len() == count {
+        return Err(format!("Vault not found: {}", vault_id));
+    }
+    settings::save(app, settings::VAULTS_KEY, &registry)?;
+
+    list_vaults(app)
+}
@@ -0,0 +1,200 @@
+use crate::models::windows::{WindowGeometry, WindowStates};
+use crate::settings;
+use crate::windows::{MAIN_WINDOW, NOTE_WINDOW_PREFIX, log_failure};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window};
+
+/** Geometry of the open windows as last seen, registered as managed state in `main.rs`.
+ *
+ * Moves and resizes only update memory; the store is written when a
+ * window closes and when the app quits.
+ */
+pub struct WindowStateTracker {
+    states: Mutex<WindowStates>,
+}
+
+impl WindowStateTracker {
+    pub fn load(app: &AppHandle) -> Self {
+        Self {
+            states: Mutex::new(settings::load(app, settings::WINDOW_STATE_KEY)),
+        }
+    }
+}
+
+/** Only the main window and note windows keep their geometry. */
+fn is_tracked(label: &str) -> bool {
+    label == MAIN_WINDOW || label.starts_with(NOTE_WINDOW_PREFIX)
+}
+
+/** Records a window's bounds after it moved or resized.
+ *
+ * Bounds while maximized, fullscreen or minimized are ignored, so the
+ * normal bounds survive for un-maximizing later.
+ */
+pub fn record_bounds(window: &Window) {
+    let label = window.label();
+    let Some(tracker) = window.try_state::<WindowStateTracker>() else {
+        return;
+    };
+    if !is_tracked(label)
+        || window.is_maximized().unwrap_or(true)
+        || window.is_fullscreen().unwrap_or(true)
+        || window.is_minimized().unwrap_or(true)
+    {
+        return;
+    }
+
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+        return;
+    };
+    let Ok(mut states) = tracker.states.lock() else {
+        return;
+    };
+
+    let geometry = states
+        .windows
+        .entry(label.to_string())
+        .or_insert_with(|| WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized: false,
+            fullscreen: false,
+            monitor: None,
+        });
+    geometry.x = position.x;
+    geometry.y = position.y;
+    geometry.width = size.width;
+    geometry.height = size.height;
+}
+
+/** Notes the maximized, fullscreen and monitor state of every open window and writes it to the store.
+ *
+ * Geometry of windows that are no longer open is dropped, so closed note
+ * windows don't accumulate.
+ */
+pub fn save(app: &AppHandle) {
+    let Some(tracker) = app.try_state::<WindowStateTracker>() else {
+        return;
+    };
+    let Ok(mut states) = tracker.states.lock() else {
+        return;
+    };
+
+    let windows = app.webview_windows();
+    states
+        .windows
+        .retain(|label, _| windows.contains_key(label));
+
+    for (label, window) in windows.iter().filter(|(label, _)| is_tracked(label)) {
+        if !states.windows.contains_key(label) {
+            // A window that never moved still has its initial bounds.
+            if window.is_minimized().unwrap_or(true) {
+                continue;
+            }
+            let (Ok(position), Ok(size)) = (window.outer_position(), window.inner_size()) else {
+                continue;
+            };
+            states.windows.insert(
+                label.clone(),
+                WindowGeometry {
+                    x: position.x,
+                    y: position.y,
+                    width: size.width,
+                    height: size.height,
+                    maximized: false,
+                    fullscreen: false,
+                    monitor: None,
+                },
+            );
+        }
+        let Some(geometry) = states.windows.get_mut(label) else {
+            continue;
+        };
+
+        geometry.maximized = window.is_maximized().unwrap_or(false);
+        geometry.fullscreen = window.is_fullscreen().unwrap_or(false);
+        if let Ok(Some(monitor)) = window.current_monitor() {
+            geometry.monitor = monitor.name().cloned();
+        }
+    }
+
+    if let Err(e) = settings::save(app, settings::WINDOW_STATE_KEY, &*states) {
+        eprintln!("Failed to save window state: {}", e);
+    }
+}
+
+fn contains(monitor: &Monitor, x: i32, y: i32) -> bool {
+    let position = monitor.position();
+    let size = monitor.size();
+
+    x >= position.x
+        && y >= position.y
+        && (x as i64) < position.x as i64 + size.width as i64
+        && (y as i64) < position.y as i64 + size.height as i64
+}
+
+/** Puts a window back where it was last saved.
+ *
+ * A window whose saved bounds are no longer on any monitor, e.g. after
+ * unplugging one, is centered on the monitor it was on if that still
+ * exists and on the primary monitor otherwise, shrunk to fit. Returns
+ * false when nothing was saved for the window.
+ */
+pub fn restore(window: &WebviewWindow) -> bool {
+    let label = window.label();
+    let geometry = window
+        .try_state::<WindowStateTracker>()
+        .and_then(|tracker| {
+            let states = tracker.states.lock().ok()?;
+            states.windows.get(label).cloned()
+        });
+    let Some(geometry) = geometry else {
+        return false;
+    };
+
+    let mut size = PhysicalSize::new(geometry.width, geometry.height);
+    let mut position = PhysicalPosition::new(geometry.x, geometry.y);
+
+    let monitors = window.available_monitors().unwrap_or_default();
+    let center_x = geometry.x.saturating_add((geometry.width / 2) as i32);
+    let center_y = geometry.y.saturating_add((geometry.height / 2) as i32);
+    if !monitors
+        .iter()
+        .any(|monitor| contains(monitor, center_x, center_y))
+    {
+        let fallback = monitors
+            .iter()
+            .find(|monitor| {
+                geometry.monitor.is_some() && monitor.name() == geometry.monitor.as_ref()
+            })
+            .cloned()
+            .or_else(|| window.primary_monitor().ok().flatten())
+            .or_else(|| monitors.first().cloned());
+
+        if let Some(monitor) = fallback {
+            let area = monitor.size();
+            size.width = size.width.min(area.width);
+            size.height = size.height.min(area.height);
+            position.x = monitor.position().x + ((area.width - size.width) / 2) as i32;
+            position.y = monitor.position().y + ((area.height - size.height) / 2) as i32;
+        }
+    }
+
+    // Bounds only apply to a normal window; the main window starts
+    // maximized from its config.
+    if window.is_maximized().unwrap_or(false) {
+        log_failure("unmaximize", label, window.unmaximize());
+    }
+    log_failure("resize", label, window.set_size(size));
+    log_failure("move", label, window.set_position(position));
+
+    if geometry.fullscreen {
+        log_failure("fullscreen", label, window.set_fullscreen(true));
+    } else if geometry.maximized {
+        log_failure("maximize", label, window.maximize());
+    }
+
+    true
+}
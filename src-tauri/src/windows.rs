@@ -0,0 +1,252 @@
+use crate::config;
+use crate::models::windows::NoteWindows;
+use crate::repository::{self, nodes};
+use crate::settings;
+use crate::window_state;
+use std::time::{Duration, Instant};
+use tauri::window::Color;
+use tauri::{
+    AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder, Window, WindowEvent,
+};
+
+pub const MAIN_WINDOW: &str = "main";
+pub const SPLASH_WINDOW: &str = "splashscreen";
+pub const RECOVERY_WINDOW: &str = "recovery";
+
+/** Label prefix of windows showing a single note; the node ID follows it. */
+pub const NOTE_WINDOW_PREFIX: &str = "note-";
+
+// The splash stays up at least this long, then fades out before closing.
+const MIN_SPLASH: Duration = Duration::from_millis(2700);
+const SPLASH_EXIT: Duration = Duration::from_millis(550);
+
+pub(crate) fn log_failure<E: std::fmt::Display>(action: &str, label: &str, result: Result<(), E>) {
+    if let Err(e) = result {
+        eprintln!("Failed to {} the {} window: {}", action, label, e);
+    }
+}
+
+/** Shows a window and gives it focus, maximizing it if asked.
+ *
+ * Failures are logged rather than raised; a window that can't be
+ * maximized is still better shown than not.
+ */
+pub fn reveal(window: &WebviewWindow, maximize: bool) {
+    let label = window.label();
+    if maximize {
+        log_failure("maximize", label, window.maximize());
+    }
+    log_failure("show", label, window.show());
+    log_failure("focus", label, window.set_focus());
+}
+
+/** Closes the splash window once it has been up long enough, then reveals `target`. */
+pub fn dismiss_splash(app: AppHandle, start: Instant, target: &'static str) {
+    std::thread::spawn(move || {
+        if let Some(remaining) = MIN_SPLASH.checked_sub(start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+
+        let splash = app.get_webview_window(SPLASH_WINDOW);
+        if let Some(splash) = &splash {
+            log_failure(
+                "animate",
+                SPLASH_WINDOW,
+                splash.eval("document.body.classList.add('is-exiting')"),
+            );
+            std::thread::sleep(SPLASH_EXIT);
+        }
+
+        if target == MAIN_WINDOW {
+            show_main_window(&app);
+        } else {
+            match app.get_webview_window(target) {
+                Some(window) => reveal(&window, false),
+                None => eprintln!("No {} window to show", target),
+            }
+        }
+
+        if let Some(splash) = splash {
+            log_failure("close", SPLASH_WINDOW, splash.close());
+        }
+    });
+}
+
+/** Shows the main window where it was last left, then reopens the note windows.
+ *
+ * Without saved geometry the main window starts maximized.
+ */
+pub fn show_main_window(app: &AppHandle) {
+    match app.get_webview_window(MAIN_WINDOW) {
+        Some(window) => {
+            let restored = window_state::restore(&window);
+            reveal(&window, !restored);
+        }
+        None => eprintln!("No {} window to show", MAIN_WINDOW),
+    }
+    restore_note_windows(app);
+}
+
+/** Creates the hidden recovery window shown when the database can't be opened. */
+pub fn create_recovery_window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    WebviewWindowBuilder::new(
+        app,
+        RECOVERY_WINDOW,
+        WebviewUrl::App("recovery.html".into()),
+    )
+    .title("Unfold Recovery")
+    .inner_size(640.0, 600.0)
+    .min_inner_size(480.0, 480.0)
+    .center()
+    .visible(false)
+    .build()
+    .map_err(|e| format!("Failed to create recovery window: {}", e))
+}
+
+fn note_window_label(node_id: &str) -> String {
+    format!("{}{}", NOTE_WINDOW_PREFIX, node_id)
+}
+
+/** Identifies the open vault in the note window settings by its storage root. */
+fn vault_key(app: &AppHandle) -> Result<String, String> {
+    Ok(config::storage_location(app)?
+        .root()
+        .to_string_lossy()
+        .to_string())
+}
+
+/** Records whether `node_id` of the vault `vault` has a window of its own, for reopening at launch. */
+fn remember_note_window(
+    app: &AppHandle,
+    vault: &str,
+    node_id: &str,
+    open: bool,
+) -> Result<(), String> {
+    let mut note_windows: NoteWindows = settings::load(app, settings::NOTE_WINDOWS_KEY);
+    let node_ids = note_windows.node_ids.entry(vault.to_string()).or_default();
+    let known = node_ids.iter().any(|id| id == node_id);
+    if open == known {
+        return Ok(());
+    }
+
+    if open {
+        node_ids.push(node_id.to_string());
+    } else {
+        node_ids.retain(|id| id != node_id);
+        if node_ids.is_empty() {
+            note_windows.node_ids.remove(vault);
+        }
+    }
+    settings::save(app, settings::NOTE_WINDOWS_KEY, &note_windows)
+}
+
+/** Shows a note in a window of its own, focusing the existing one if it is already open.
+ *
+ * The window loads the note's route in the regular app. Closing it
+ * forgets it; quitting the app keeps it for the next launch.
+ */
+pub fn open_note_window(app: &AppHandle, node_id: &str) -> Result<WebviewWindow, String> {
+    let label = note_window_label(node_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        log_failure("unminimize", &label, window.unminimize());
+        reveal(&window, false);
+        return Ok(window);
+    }
+
+    let node = nodes::get_node(&repository::open_connection(app)?, node_id)?;
+    if node.deleted_at.is_some() {
+        return Err("Cannot open a note that is in the trash.".to_string());
+    }
+
+    let route = format!("spaces/{}/files/{}", node.space_id, node.id);
+    let builder = WebviewWindowBuilder::new(app, &label, WebviewUrl::App(route.into()))
+        .title(if node.name.trim().is_empty() {
+            "Unfold"
+        } else {
+            node.name.trim()
+        })
+        .inner_size(960.0, 800.0)
+        .min_inner_size(400.0, 400.0)
+        .background_color(Color(13, 13, 12, 255))
+        .center()
+        .visible(false)
+        .disable_drag_drop_handler();
+    #[cfg(target_os = "macos")]
+    let builder = builder
+        .title_bar_style(tauri::TitleBarStyle::Overlay)
+        .hidden_title(true)
+        .traffic_light_position(tauri::LogicalPosition::new(20.0, 23.0));
+
+    let window = builder
+        .build()
+        .map_err(|e| format!("Failed to create note window: {}", e))?;
+    window_state::restore(&window);
+    reveal(&window, false);
+
+    // The vault is captured now: it may have been switched by the time
+    // the window is closed.
+    let vault = vault_key(app)?;
+    let handle = app.clone();
+    let id = node.id.clone();
+    let window_vault = vault.clone();
+    window.on_window_event(move |event| {
+        // Only a window the user closes is forgotten; quitting destroys
+        // windows without asking them to close.
+        if matches!(event, WindowEvent::CloseRequested { .. })
+            && let Err(e) = remember_note_window(&handle, &window_vault, &id, false)
+        {
+            eprintln!("Failed to forget note window: {}", e);
+        }
+    });
+
+    remember_note_window(app, &vault, &node.id, true)?;
+    Ok(window)
+}
+
+/** Reopens the open vault's note windows that were open when the app last quit.
+ *
+ * Notes that were deleted or trashed since are forgotten.
+ */
+pub fn restore_note_windows(app: &AppHandle) {
+    if let Err(e) = try_restore_note_windows(app) {
+        eprintln!("Failed to reopen note windows: {}", e);
+    }
+}
+
+fn try_restore_note_windows(app: &AppHandle) -> Result<(), String> {
+    let vault = vault_key(app)?;
+    let mut note_windows: NoteWindows = settings::load(app, settings::NOTE_WINDOWS_KEY);
+    let node_ids = note_windows.node_ids.remove(&vault).unwrap_or_default();
+
+    for node_id in node_ids {
+        let node = nodes::find_node(&repository::open_connection(app)?, &node_id)?;
+        if node.is_none_or(|node| node.deleted_at.is_some()) {
+            remember_note_window(app, &vault, &node_id, false)?;
+            continue;
+        }
+
+        if let Err(e) = open_note_window(app, &node_id) {
+            eprintln!("Failed to reopen note window for {}: {}", node_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/** Keeps track of window geometry, and quits when the main window is closed.
+ *
+ * Closing the main window quits even while note windows are open; those
+ * are reopened at the next launch.
+ */
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+    match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => window_state::record_bounds(window),
+        WindowEvent::CloseRequested { .. } => {
+            window_state::save(window.app_handle());
+            if window.label() == MAIN_WINDOW {
+                window.app_handle().exit(0);
+            }
+        }
+        _ => {}
+    }
+}
@@ -12,7 +12,7 @@ import {
 import { createPortal } from "react-dom";
 import { Download, Maximize2, RefreshCw, X } from "lucide-react";
 
-import { saveImageFile } from "@/utils/invoke";
+import { getImageVariantSrc, saveImageFile } from "@/utils/invoke";
 
 const MIN_IMAGE_WIDTH_PERCENT = 15;
 const MAX_IMAGE_WIDTH_PERCENT = 100;
@@ -76,6 +76,25 @@ export const ImageNodeView = ({ node, updateAttributes }: NodeViewProps) => {
   const [isResizing, setIsResizing] = useState(false);
   const [isPreviewOpen, setIsPreviewOpen] = useState(false);
   const [downloadState, setDownloadState] = useState<"idle" | "done" | "error">("idle");
+  // The note stores the original; the editor shows the display-width copy.
+  const [displaySrc, setDisplaySrc] = useState<string>(src);
+
+  useEffect(() => {
+    setDisplaySrc(src);
+    if (!src || typeof attachmentId !== "string" || attachmentId.startsWith("uploading")) {
+      return;
+    }
+
+    let cancelled = false;
+    void getImageVariantSrc(attachmentId, "display", src).then((variantSrc) => {
+      if (!cancelled) {
+        setDisplaySrc(variantSrc);
+      }
+    });
+    return () => {
+      cancelled = true;
+    };
+  }, [src, attachmentId]);
 
   const loading = useMemo(() => !src && attachmentId === "uploading", [src, attachmentId]);
   const error = useMemo(() => !src && attachmentId && attachmentId !== "uploading", [src, attachmentId]);
@@ -319,7 +338,7 @@ export const ImageNodeView = ({ node, updateAttributes }: NodeViewProps) => {
           onClick={openPreview}
         >
           <img
-            src={src}
+            src={displaySrc}
             alt={alt || ""}
             draggable={false}
             onMouseDown={stopNodeSelection}
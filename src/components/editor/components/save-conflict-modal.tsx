@@ -0,0 +1,54 @@
+import { Button } from '@/components/ui/button';
+import { Modal } from '@/components/ui/modal';
+
+interface SaveConflictModalProps {
+  open: boolean;
+  noteName: string;
+  onOverwrite: () => void;
+  onMerge: () => void;
+  onDiscard: () => void;
+}
+
+/** Asks how to resolve a save rejected because the note changed in another window. */
+export function SaveConflictModal({
+  open,
+  noteName,
+  onOverwrite,
+  onMerge,
+  onDiscard,
+}: SaveConflictModalProps) {
+  return (
+    <Modal
+      open={open}
+      showClose={false}
+      enableEscapeShortcut={false}
+      enableEnterShortcut={false}
+      className="border border-sidebar-container-border/80 bg-sidebar-container-bg ring-sidebar-container-border/80 shadow-lg backdrop-blur-2xl backdrop-saturate-150"
+      backdropClassName="bg-sidebar/50"
+    >
+      <div className="flex select-none flex-col gap-4 p-6 text-modal-surface-foreground lowercase sm:p-7">
+        <div className="space-y-2.5">
+          <h3 className="font-sans-serif text-lg leading-snug font-medium text-modal-surface-foreground">
+            {noteName ? `"${noteName}" changed elsewhere` : 'this note changed elsewhere'}
+          </h3>
+          <p className="font-sans text-xs leading-relaxed text-modal-surface-foreground/62">
+            another window saved this note after you started editing. merge keeps the blocks from
+            both versions; overwrite replaces theirs with yours.
+          </p>
+        </div>
+
+        <div className="flex flex-wrap items-center justify-end gap-2.5 pt-3">
+          <Button type="button" variant="outline" size="action" onClick={onDiscard}>
+            use theirs
+          </Button>
+          <Button type="button" variant="outline" size="action" onClick={onMerge}>
+            merge
+          </Button>
+          <Button type="button" variant="error" size="action" onClick={onOverwrite}>
+            overwrite
+          </Button>
+        </div>
+      </div>
+    </Modal>
+  );
+}
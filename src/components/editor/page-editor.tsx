@@ -1,5 +1,5 @@
 import { EditorContent, useEditor } from "@tiptap/react";
-import { useEffect, useRef, useCallback, useMemo } from "react";
+import { useEffect, useRef, useCallback, useMemo, useState } from "react";
 import { TrailingNode, Placeholder } from "@tiptap/extensions";
 import { ReactNodeViewRenderer } from "@tiptap/react";
 import { useEditorContext } from "@/contexts/EditorContext";
@@ -27,6 +27,10 @@ import { TaskItem, TaskList } from "@tiptap/extension-list";
 import { TextStyle } from "@tiptap/extension-text-style";
 import Color from "@tiptap/extension-color";
 import invoke from "@/utils/invoke";
+import { mergeDocuments } from "@/lib/merge-content";
+import type { NodeRow } from "@/services/database";
+import { trackNodeRevision, type AppApiError } from "@/store/api/app-api";
+import { SaveConflictModal } from "./components/save-conflict-modal";
 import "./styles/drag-handle.css";
 import "./styles/block-spacing.css";
 import "./styles/image-node.css";
@@ -51,6 +55,15 @@ function hasAncestorNode($pos: { depth: number; node: (depth: number) => { type:
   return false;
 }
 
+function parseContent(raw: string) {
+  if (!raw) return "";
+  try {
+    return JSON.parse(raw);
+  } catch {
+    return raw;
+  }
+}
+
 function isEditableSurface(target: HTMLElement) {
   return Boolean(
     target.closest('input, textarea, [contenteditable="true"], [contenteditable=""]') ||
@@ -68,6 +81,8 @@ function PageEditor({ fileId }: PageEditorProps) {
   const lastSavedRef = useRef<string>(file?.content || "");
   const editorContainerRef = useRef<HTMLDivElement>(null);
   const pendingArrowScrollRef = useRef(false);
+  const conflictRef = useRef<NodeRow | null>(null);
+  const [conflict, setConflict] = useState<NodeRow | null>(null);
 
   const clearDropHighlight = useCallback(() => {
     if (!editorContainerRef.current) return;
@@ -104,16 +119,34 @@ function PageEditor({ fileId }: PageEditorProps) {
     return true;
   }, []);
 
-  const initialContent = useMemo(() => {
-    const raw = file?.content || "";
-    if (!raw) return "";
-    try {
-      return JSON.parse(raw);
-    } catch {
-      return raw;
+  const initialContent = useMemo(() => parseContent(file?.content || ""), [fileId]);
+
+  useEffect(() => {
+    // Saves are checked against the revision the loaded content came from.
+    if (file?.revision !== undefined) {
+      trackNodeRevision(fileId, file.revision);
     }
+    conflictRef.current = null;
+    setConflict(null);
   }, [fileId]);
 
+  const saveContent = useCallback(
+    (content: string) => {
+      updateNodeContent(fileId, content).catch((error: AppApiError) => {
+        if (!error?.conflict) {
+          console.error("Failed to save note:", error);
+          return;
+        }
+        // Later saves of the same burst fail the same way; ask only once.
+        if (!conflictRef.current) {
+          conflictRef.current = error.conflict;
+          setConflict(error.conflict);
+        }
+      });
+    },
+    [fileId, updateNodeContent],
+  );
+
   const editorExtensions = useMemo(
     () => [
       starterKit,
@@ -183,7 +216,10 @@ function PageEditor({ fileId }: PageEditorProps) {
       const jsonContent = JSON.stringify(editor.getJSON());
       if (jsonContent !== lastSavedRef.current) {
         lastSavedRef.current = jsonContent;
-        updateNodeContent(fileId, jsonContent);
+        // Held back until the conflict is resolved, which saves the latest content.
+        if (!conflictRef.current) {
+          saveContent(jsonContent);
+        }
       }
     },
     onSelectionUpdate: ({ editor }) => {
@@ -286,6 +322,33 @@ function PageEditor({ fileId }: PageEditorProps) {
     },
   }, [fileId, settings.keybindings.selectAll]);
 
+  const resolveConflict = useCallback(
+    (resolution: "overwrite" | "merge" | "discard") => {
+      const current = conflictRef.current;
+      if (!editor || !current) return;
+
+      conflictRef.current = null;
+      setConflict(null);
+      trackNodeRevision(fileId, current.revision);
+
+      const theirs = current.content ?? "";
+      if (resolution === "discard") {
+        editor.commands.setContent(parseContent(theirs), { emitUpdate: false });
+        lastSavedRef.current = JSON.stringify(editor.getJSON());
+        return;
+      }
+
+      const mine = JSON.stringify(editor.getJSON());
+      const content = resolution === "merge" ? mergeDocuments(mine, theirs) : mine;
+      if (content !== mine) {
+        editor.commands.setContent(parseContent(content), { emitUpdate: false });
+      }
+      lastSavedRef.current = content;
+      saveContent(content);
+    },
+    [editor, fileId, saveContent],
+  );
+
   const clearEditorSelection = useCallback(() => {
     if (!editor) return;
     const { selection } = editor.state;
@@ -363,6 +426,13 @@ function PageEditor({ fileId }: PageEditorProps) {
 
       <EditorBubbleMenu editor={editor} />
       <EditorContent editor={editor} />
+      <SaveConflictModal
+        open={conflict !== null}
+        noteName={conflict?.name ?? ""}
+        onOverwrite={() => resolveConflict("overwrite")}
+        onMerge={() => resolveConflict("merge")}
+        onDiscard={() => resolveConflict("discard")}
+      />
     </div>
   );
 }
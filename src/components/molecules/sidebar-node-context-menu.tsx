@@ -14,6 +14,7 @@ interface SidebarNodeContextMenuProps {
   pinLabel: string;
   onCreateChild: () => void;
   onTogglePin: () => void;
+  onOpenInWindow: () => void;
   onOpenDeleteModal: () => void;
   children: React.ReactNode;
 }
@@ -22,6 +23,7 @@ export function SidebarNodeContextMenu({
   pinLabel,
   onCreateChild,
   onTogglePin,
+  onOpenInWindow,
   onOpenDeleteModal,
   children,
 }: SidebarNodeContextMenuProps) {
@@ -37,6 +39,7 @@ export function SidebarNodeContextMenu({
           {pinLabel}
           <ContextMenuShortcut>{getShortcutDisplay(KEYBOARD_SHORTCUTS.PIN_NOTE)}</ContextMenuShortcut>
         </ContextMenuItem>
+        <ContextMenuItem onSelect={onOpenInWindow}>Open in new window</ContextMenuItem>
         <ContextMenuSeparator />
         <ContextMenuItem onSelect={onOpenDeleteModal} variant="destructive">
           Delete
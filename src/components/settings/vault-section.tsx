@@ -0,0 +1,129 @@
+import { X } from 'lucide-react';
+import { useEffect, useState } from 'react';
+
+import { IconActionButton } from '@/components/atoms/icon-action-button';
+import { PanelCard } from '@/components/atoms/panel-card';
+import { ChoiceRow } from '@/components/molecules/choice-row';
+import { FooterActionBar } from '@/components/molecules/footer-action-bar';
+import {
+  DEFAULT_VAULT_ID,
+  forgetVault,
+  listVaults,
+  openVault,
+  switchVault,
+  type VaultList,
+} from '@/services/vaults';
+
+function toMessage(error: unknown): string {
+  return error instanceof Error ? error.message : String(error);
+}
+
+/**
+ * Lists known vaults and switches between them. A switch reloads the
+ * window through the `vault-changed` listener in `main.tsx`.
+ */
+const VaultSection = () => {
+  const [vaultList, setVaultList] = useState<VaultList | null>(null);
+  const [isBusy, setIsBusy] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    listVaults()
+      .then(setVaultList)
+      .catch((err) => setError(toMessage(err)));
+  }, []);
+
+  const run = async (action: () => Promise<void>) => {
+    setIsBusy(true);
+    setError(null);
+    try {
+      await action();
+    } catch (err) {
+      setError(toMessage(err));
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  const handleOpenFolder = () =>
+    run(async () => {
+      await openVault();
+    });
+
+  const handleSwitch = (vaultId: string) =>
+    run(async () => {
+      await switchVault(vaultId);
+    });
+
+  const handleForget = (vaultId: string) =>
+    run(async () => {
+      setVaultList(await forgetVault(vaultId));
+    });
+
+  const vaults = vaultList?.vaults ?? [];
+
+  return (
+    <div className="flex h-full flex-col bg-modal-surface-bg text-modal-surface-foreground">
+      <div className="flex-1 overflow-y-auto px-5 py-4 dropdown-darker-scroll">
+        <div className="mx-auto w-full max-w-135 space-y-2.5">
+          <div className="w-full px-3.5">
+            <p className="whitespace-nowrap text-right font-sans text-[0.82rem] font-medium tracking-[0.02em] text-modal-surface-foreground/92">
+              known vaults
+            </p>
+          </div>
+
+          <PanelCard>
+            {vaults.map((vault, index) => {
+              const selected = vault.id === vaultList?.active_id;
+              const canForget = !selected && vault.id !== DEFAULT_VAULT_ID;
+
+              return (
+                <div key={vault.id} className="flex items-center">
+                  <ChoiceRow
+                    className="min-w-0 flex-1"
+                    disabled={isBusy}
+                    onClick={() => {
+                      if (!selected) {
+                        void handleSwitch(vault.id);
+                      }
+                    }}
+                    selected={selected}
+                    title={vault.id === DEFAULT_VAULT_ID ? `${vault.name} (default)` : vault.name}
+                    subtitle={vault.path}
+                    showDivider={index < vaults.length - 1}
+                  />
+                  {canForget ? (
+                    <IconActionButton
+                      aria-label={`Forget ${vault.name}`}
+                      title="forget vault; its folder is kept"
+                      disabled={isBusy}
+                      onClick={() => {
+                        void handleForget(vault.id);
+                      }}
+                      className="mr-2.5 text-modal-surface-foreground/55 hover:text-modal-surface-foreground/85"
+                    >
+                      <X size={12} />
+                    </IconActionButton>
+                  ) : null}
+                </div>
+              );
+            })}
+          </PanelCard>
+
+          {error ? <p className="font-sans text-[0.75rem] text-red-400">{error}</p> : null}
+        </div>
+      </div>
+
+      <FooterActionBar
+        hint="a vault is a folder holding your notes and attachments"
+        primaryLabel={isBusy ? 'opening...' : 'open folder'}
+        onPrimaryClick={() => {
+          void handleOpenFolder();
+        }}
+        primaryDisabled={isBusy}
+      />
+    </div>
+  );
+};
+
+export default VaultSection;
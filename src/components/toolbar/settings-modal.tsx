@@ -1,4 +1,4 @@
-import { GlobeIcon, PaletteIcon, PrinterIcon, SlidersHorizontalIcon, X } from 'lucide-react';
+import { FolderOpenIcon, GlobeIcon, PaletteIcon, PrinterIcon, SlidersHorizontalIcon, X } from 'lucide-react';
 import { useEffect, useRef, useState } from 'react';
 import type { ComponentType } from 'react';
 
@@ -9,6 +9,7 @@ import { FooterActionBar } from '@/components/molecules/footer-action-bar';
 import { FormField } from '@/components/molecules/form-field';
 import { FilterToggleCard } from '@/components/molecules/filter-toggle-card';
 import CustomizabilitySection from '@/components/settings/customizability-section';
+import VaultSection from '@/components/settings/vault-section';
 import { Modal } from '@/components/ui/modal';
 import { ThemePreference, useTheme } from '@/contexts/ThemeContext';
 import { cn } from '@/lib/tiptap-utils';
@@ -54,7 +55,7 @@ export function SettingsModal({
   const { theme, setTheme } = useTheme();
   const websiteUrlInputRef = useRef<HTMLInputElement | null>(null);
   const [activeSection, setActiveSection] = useState<
-    'export' | 'import' | 'appearance' | 'customizability'
+    'export' | 'import' | 'appearance' | 'customizability' | 'vaults'
   >('export');
   const [importState, setImportState] = useState(defaultImportState);
 
@@ -97,7 +98,7 @@ export function SettingsModal({
 
 
   const sections: Array<{
-    id: 'export' | 'import' | 'appearance' | 'customizability';
+    id: 'export' | 'import' | 'appearance' | 'customizability' | 'vaults';
     label: string;
     icon: ComponentType<{ size?: number }>;
   }> = [
@@ -105,6 +106,7 @@ export function SettingsModal({
     { id: 'import', label: 'import', icon: GlobeIcon },
     { id: 'appearance', label: 'appearance', icon: PaletteIcon },
     { id: 'customizability', label: 'customization', icon: SlidersHorizontalIcon },
+    { id: 'vaults', label: 'vaults', icon: FolderOpenIcon },
   ];
 
   const activeSectionTitle = activeSection === 'customizability' ? 'customization' : activeSection;
@@ -198,7 +200,9 @@ export function SettingsModal({
           <div
             className={cn(
               'flex-1 min-h-0',
-              activeSection === 'customizability' ? 'overflow-hidden px-0 py-0' : 'overflow-y-auto px-5 py-4',
+              activeSection === 'customizability' || activeSection === 'vaults'
+                ? 'overflow-hidden px-0 py-0'
+                : 'overflow-y-auto px-5 py-4',
             )}
           >
             {activeSection === 'export' ? (
@@ -297,6 +301,10 @@ export function SettingsModal({
               <div className="h-full min-h-0">
                 <CustomizabilitySection onClose={() => onOpenChange(false)} />
               </div>
+            ) : activeSection === 'vaults' ? (
+              <div className="h-full min-h-0">
+                <VaultSection />
+              </div>
             ) : (
               <div className="mx-auto w-full max-w-135 space-y-2.5">
                 <div className="w-full px-3.5">
@@ -342,7 +350,7 @@ export function SettingsModal({
               onPrimaryClick={handleImportWebsite}
               primaryDisabled={!isWebsiteUrlValid || isImporting}
             />
-          ) : activeSection === 'customizability' ? null : (
+          ) : activeSection === 'customizability' || activeSection === 'vaults' ? null : (
             <FooterActionBar
               hint="we will remember your preference"
               primaryLabel="done"
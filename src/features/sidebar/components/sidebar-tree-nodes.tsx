@@ -12,6 +12,7 @@ import { useFileSystem } from '@/contexts/FileSystemContext';
 import { useAppEvent, APP_EVENTS } from '@/lib/app-events';
 import { cn } from '@/lib/tiptap-utils';
 import { useIsNodeSelected, useSelectedFileId } from '@/store/hooks/use-filesystem-store';
+import { openNoteWindow } from '@/services/windows';
 import { useAppDispatch } from '@/store/hooks';
 import { setPendingFileId } from '@/store/slices/ui-slice';
 import { Node as SidebarNode } from '@/types/sidebar';
@@ -64,6 +65,12 @@ function useNodeActions(node: SidebarNode) {
     await togglePinNode(node.id);
   }, [togglePinNode, node.id]);
 
+  const handleOpenInWindow = useCallback(() => {
+    openNoteWindow(node.id).catch((error) => {
+      console.error('Failed to open note window:', error);
+    });
+  }, [node.id]);
+
   const handleAddChild = useCallback(async (event: React.MouseEvent) => {
     event.stopPropagation();
     const createdNode = await addNode(node.id);
@@ -89,6 +96,7 @@ function useNodeActions(node: SidebarNode) {
     handleConfirmDelete,
     handleCreateChild,
     handleTogglePin,
+    handleOpenInWindow,
     handleAddChild,
     dispatch,
   };
@@ -113,6 +121,7 @@ const PinnedNodeItem = memo(({
     handleConfirmDelete,
     handleCreateChild,
     handleTogglePin,
+    handleOpenInWindow,
     handleAddChild,
   } = useNodeActions(node);
 
@@ -140,6 +149,7 @@ const PinnedNodeItem = memo(({
           pinLabel="Unpin"
           onCreateChild={handleCreateChild}
           onTogglePin={handleTogglePin}
+          onOpenInWindow={handleOpenInWindow}
           onOpenDeleteModal={handleOpenDeleteModal}
         >
           <SidebarNodeRow
@@ -205,6 +215,7 @@ export const SidebarNodes = memo(({
     handleConfirmDelete,
     handleCreateChild,
     handleTogglePin,
+    handleOpenInWindow,
     handleAddChild,
   } = useNodeActions(node);
 
@@ -247,6 +258,7 @@ export const SidebarNodes = memo(({
           pinLabel={isPinned ? 'Unpin' : 'Pin'}
           onCreateChild={handleCreateChild}
           onTogglePin={handleTogglePin}
+          onOpenInWindow={handleOpenInWindow}
           onOpenDeleteModal={handleOpenDeleteModal}
         >
           <SidebarNodeRow
@@ -0,0 +1,61 @@
+interface JSONContent {
+  type?: string;
+  content?: JSONContent[];
+  [key: string]: unknown;
+}
+
+function parseDocument(raw: string): JSONContent | null {
+  try {
+    const parsed = JSON.parse(raw) as JSONContent;
+    return parsed?.type === 'doc' ? parsed : null;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Merges two versions of a TipTap document block by block.
+ *
+ * Every top-level block of `mine` is kept in order. Blocks only `theirs`
+ * has are inserted after the last block both versions share before them,
+ * so nothing either side wrote is lost; a block edited on both sides shows
+ * up once per version. Content that isn't TipTap JSON can't be merged and
+ * `mine` is returned as is.
+ */
+export function mergeDocuments(mine: string, theirs: string): string {
+  const mineDoc = parseDocument(mine);
+  const theirDoc = parseDocument(theirs);
+  if (!mineDoc || !theirDoc) {
+    return mine;
+  }
+
+  const mineBlocks = mineDoc.content ?? [];
+  const mineKeys = mineBlocks.map((block) => JSON.stringify(block));
+
+  // Their new blocks, keyed by the index of the shared block they follow.
+  const insertions = new Map<number, JSONContent[]>();
+  let anchor = -1;
+  for (const block of theirDoc.content ?? []) {
+    const key = JSON.stringify(block);
+    const index = mineKeys.indexOf(key, anchor + 1);
+    if (index !== -1) {
+      anchor = index;
+      continue;
+    }
+    if (mineKeys.includes(key)) {
+      // Moved rather than new.
+      continue;
+    }
+
+    const following = insertions.get(anchor) ?? [];
+    following.push(block);
+    insertions.set(anchor, following);
+  }
+
+  const merged: JSONContent[] = [...(insertions.get(-1) ?? [])];
+  mineBlocks.forEach((block, index) => {
+    merged.push(block, ...(insertions.get(index) ?? []));
+  });
+
+  return JSON.stringify({ ...mineDoc, content: merged });
+}
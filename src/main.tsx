@@ -1,9 +1,16 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import { Provider } from "react-redux";
+import { listen } from "@tauri-apps/api/event";
 import "@/styles/tailwind.css";
 import App from "./App";
 import { store } from "@/store";
+import { syncChangeEvents } from "@/store/change-sync";
+
+// Everything loaded so far belongs to the previous vault.
+void listen("vault-changed", () => window.location.reload());
+// Keep cached data in step with changes made by other windows and background tasks.
+void syncChangeEvents(store);
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
@@ -0,0 +1,83 @@
+import { invoke } from "@tauri-apps/api/core";
+
+interface BackupInfo {
+  id: string;
+  created_at: string;
+}
+
+interface RecoveryStatus {
+  error: string;
+  database_path: string;
+  log_path: string | null;
+  latest_backup: BackupInfo | null;
+  read_only: boolean;
+}
+
+const element = <T extends HTMLElement>(id: string) =>
+  document.getElementById(id) as T;
+
+const restoreButton = element<HTMLButtonElement>("restore");
+const readOnlyButton = element<HTMLButtonElement>("read-only");
+const exportButton = element<HTMLButtonElement>("export");
+const buttons = [restoreButton, readOnlyButton, exportButton];
+const message = element<HTMLParagraphElement>("message");
+let hasBackup = false;
+
+function showMessage(text: string, isError = false) {
+  message.textContent = text;
+  message.classList.toggle("is-error", isError);
+}
+
+/** Runs one recovery action at a time, keeping the buttons disabled meanwhile. */
+async function run(action: () => Promise<void>) {
+  buttons.forEach((button) => (button.disabled = true));
+  showMessage("");
+  try {
+    await action();
+  } catch (error) {
+    showMessage(String(error), true);
+  } finally {
+    buttons.forEach((button) => (button.disabled = false));
+    restoreButton.disabled = !hasBackup;
+  }
+}
+
+async function load() {
+  const status = await invoke<RecoveryStatus | null>("get_recovery_status");
+  if (!status) {
+    return;
+  }
+
+  element("error").textContent = `${status.error}\n\n${status.database_path}`;
+  element("log").textContent = status.log_path
+    ? `Details were saved to ${status.log_path}. Please attach this file if you report the problem.`
+    : "";
+
+  hasBackup = status.latest_backup !== null;
+  restoreButton.disabled = !hasBackup;
+  element("restore-detail").textContent = status.latest_backup
+    ? `From ${new Date(status.latest_backup.created_at).toLocaleString()}. The current database is kept next to it.`
+    : "No usable backup was found.";
+}
+
+restoreButton.addEventListener("click", () =>
+  run(async () => {
+    showMessage("Restoring…");
+    await invoke("recover_from_backup");
+  }),
+);
+
+readOnlyButton.addEventListener("click", () =>
+  run(() => invoke("open_read_only")),
+);
+
+exportButton.addEventListener("click", () =>
+  run(async () => {
+    const path = await invoke<string | null>("export_raw_content");
+    if (path) {
+      showMessage(`Exported to ${path}`);
+    }
+  }),
+);
+
+load().catch((error) => showMessage(String(error), true));
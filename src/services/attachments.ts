@@ -0,0 +1,65 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export type AttachmentKind = 'image' | 'pdf' | 'audio' | 'video' | 'archive' | 'file';
+
+export interface Attachment {
+  id: string;
+  note_id: string;
+  kind: AttachmentKind;
+  /** Name the file was uploaded with. */
+  original_name: string;
+  /** Extension of the uploaded file, without the dot; empty if it had none. */
+  extension: string;
+  /** Name the file is stored under; shared by attachments with the same content. */
+  filename: string;
+  file_path: string;
+  size: number;
+  mime_type: string;
+  created_at: string;
+  /** SHA-256 of the content; null until a file from an older vault is hashed. */
+  blob_hash: string | null;
+  /** Pixel size of images that could be decoded. */
+  width: number | null;
+  height: number | null;
+  /** Untouched upload kept alongside an image the upload policy re-encoded. */
+  original_id: string | null;
+}
+
+function toBase64(bytes: Uint8Array): string {
+  let binary = '';
+  const chunkSize = 0x8000;
+  for (let i = 0; i < bytes.length; i += chunkSize) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + chunkSize));
+  }
+  return btoa(binary);
+}
+
+/** Attaches a file of any type to a note; fails when it is over the size limit for its kind. */
+export async function uploadAttachment(noteId: string, file: File): Promise<Attachment> {
+  const bytes = new Uint8Array(await file.arrayBuffer());
+  return invoke<Attachment>('upload_attachment', {
+    request: {
+      note_id: noteId,
+      file_name: file.name,
+      base64_data: toBase64(bytes),
+      mime_type: file.type || 'application/octet-stream',
+    },
+  });
+}
+
+export async function getAttachment(attachmentId: string): Promise<Attachment> {
+  return invoke<Attachment>('get_attachment', { attachmentId });
+}
+
+export async function listAttachments(noteId: string): Promise<Attachment[]> {
+  return invoke<Attachment[]>('list_attachments', { noteId });
+}
+
+export async function deleteAttachment(attachmentId: string): Promise<void> {
+  await invoke('delete_attachment', { attachmentId });
+}
+
+/** Image attachments whose files still carry GPS coordinates or an XMP location. */
+export async function listImagesWithLocation(): Promise<Attachment[]> {
+  return invoke<Attachment[]>('list_images_with_location');
+}
@@ -0,0 +1,47 @@
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+// Event names, matching `src-tauri/src/events.rs`
+export const CHANGE_EVENTS = {
+  NODE: 'node-changed',
+  SPACE: 'space-changed',
+  ATTACHMENT: 'attachment-changed',
+} as const;
+
+export type ChangeKind = 'created' | 'updated' | 'moved' | 'trashed' | 'restored' | 'deleted';
+
+export interface ChangedNode {
+  id: string;
+  /** Revision after the change; null when unknown or deleted. */
+  revision: number | null;
+}
+
+export interface NodeChanged {
+  kind: ChangeKind;
+  space_ids: string[];
+  nodes: ChangedNode[];
+}
+
+export interface SpaceChanged {
+  kind: ChangeKind;
+  space_id: string;
+}
+
+export interface AttachmentChanged {
+  kind: ChangeKind;
+  node_id: string | null;
+  attachment_ids: string[];
+}
+
+export function onNodeChanged(handler: (change: NodeChanged) => void): Promise<UnlistenFn> {
+  return listen<NodeChanged>(CHANGE_EVENTS.NODE, (event) => handler(event.payload));
+}
+
+export function onSpaceChanged(handler: (change: SpaceChanged) => void): Promise<UnlistenFn> {
+  return listen<SpaceChanged>(CHANGE_EVENTS.SPACE, (event) => handler(event.payload));
+}
+
+export function onAttachmentChanged(
+  handler: (change: AttachmentChanged) => void
+): Promise<UnlistenFn> {
+  return listen<AttachmentChanged>(CHANGE_EVENTS.ATTACHMENT, (event) => handler(event.payload));
+}
@@ -1,20 +1,5 @@
-import Database from "@tauri-apps/plugin-sql";
-import { appDataDir, join } from "@tauri-apps/api/path";
+import { invoke } from "@tauri-apps/api/core";
 import { Node } from "../types/sidebar";
-import { getDatabaseFileName } from "@/config/database";
-
-let db: Database | null = null;
-
-async function getDb(): Promise<Database> {
-  if (!db) {
-    const dbName = getDatabaseFileName();
-    const dataDir = await appDataDir();
-    const dbPath = await join(dataDir, dbName);
-    const dbUrl = `sqlite:${dbPath}`;
-    db = await Database.load(dbUrl);
-  }
-  return db;
-}
 
 export interface SpaceRow {
   id: string;
@@ -30,43 +15,33 @@ export interface NodeRow {
   content: string | null;
   created_at: string | null;
   updated_at: string | null;
-  is_open: number;
+  is_open: boolean;
   sort_order: number;
-  is_pinned: number;
+  is_pinned: boolean;
+  deleted_at: string | null;
+  revision: number;
 }
 
 export async function getSpaces(): Promise<SpaceRow[]> {
-  const database = await getDb();
-  return await database.select<SpaceRow[]>("SELECT * FROM spaces ORDER BY sort_order");
+  return await invoke<SpaceRow[]>("list_spaces");
 }
 
-export async function createSpace(space: SpaceRow): Promise<void> {
-  const database = await getDb();
-  await database.execute(
-    "INSERT INTO spaces (id, name, sort_order) VALUES ($1, $2, $3)",
-    [space.id, space.name, space.sort_order]
-  );
+export async function createSpace(space: { id: string; name: string }): Promise<SpaceRow> {
+  return await invoke<SpaceRow>("create_space", {
+    request: { id: space.id, name: space.name },
+  });
 }
 
 export async function updateSpace(id: string, name: string): Promise<void> {
-  const database = await getDb();
-  await database.execute("UPDATE spaces SET name = $1 WHERE id = $2", [name, id]);
+  await invoke("rename_space", { spaceId: id, name });
 }
 
 export async function deleteSpace(id: string): Promise<void> {
-  const database = await getDb();
-  // Delete all nodes in this space first
-  await database.execute("DELETE FROM nodes WHERE space_id = $1", [id]);
-  // Then delete the space
-  await database.execute("DELETE FROM spaces WHERE id = $1", [id]);
+  await invoke("delete_space", { spaceId: id });
 }
 
 export async function getNodesBySpace(spaceId: string): Promise<NodeRow[]> {
-  const database = await getDb();
-  return await database.select<NodeRow[]>(
-    "SELECT * FROM nodes WHERE space_id = $1 ORDER BY sort_order",
-    [spaceId]
-  );
+  return await invoke<NodeRow[]>("list_nodes", { spaceId });
 }
 
 export async function createNode(node: {
@@ -75,121 +50,123 @@ export async function createNode(node: {
   parent_id: string | null;
   name: string;
   content: string | null;
-  is_open: number;
-  is_pinned?: number;
-}): Promise<void> {
-  const database = await getDb();
-  
-  // Get max sort_order for this parent/space
-  const maxOrderResult = await database.select<Array<{ max_order: number | null }>>(
-    node.parent_id
-      ? "SELECT MAX(sort_order) as max_order FROM nodes WHERE parent_id = $1"
-      : "SELECT MAX(sort_order) as max_order FROM nodes WHERE space_id = $1 AND parent_id IS NULL",
-    [node.parent_id || node.space_id]
-  );
-  const sortOrder = (maxOrderResult[0]?.max_order ?? -1) + 1;
-  
-  await database.execute(
-    "INSERT INTO nodes (id, space_id, parent_id, name, content, is_open, sort_order, is_pinned) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
-    [
-      node.id,
-      node.space_id,
-      node.parent_id,
-      node.name,
-      node.content,
-      node.is_open,
-      sortOrder,
-      node.is_pinned ?? 0,
-    ]
-  );
+  is_open: boolean;
+  is_pinned?: boolean;
+}): Promise<NodeRow> {
+  return await invoke<NodeRow>("create_node", {
+    request: {
+      id: node.id,
+      space_id: node.space_id,
+      parent_id: node.parent_id,
+      name: node.name,
+      content: node.content,
+      is_open: node.is_open,
+      is_pinned: node.is_pinned ?? false,
+    },
+  });
 }
 
 export async function updateNode(
   id: string,
   updates: Partial<{
     name: string;
-    content: string;
-    is_open: number;
+    is_open: boolean;
+    is_pinned: boolean;
   }>
-): Promise<void> {
-  const database = await getDb();
-  const fields: string[] = [];
-  const values: Array<string | number> = [];
-
-  if (updates.name !== undefined) {
-    fields.push("name = $" + (values.length + 1));
-    values.push(updates.name);
-  }
-  if (updates.content !== undefined) {
-    fields.push("content = $" + (values.length + 1));
-    values.push(updates.content);
-  }
-  if (updates.is_open !== undefined) {
-    fields.push("is_open = $" + (values.length + 1));
-    values.push(updates.is_open);
-  }
+): Promise<NodeRow> {
+  // Content goes through `saveNodeContent`, which checks the revision.
+  return await invoke<NodeRow>("update_node", {
+    request: {
+      id,
+      name: updates.name ?? null,
+      content: null,
+      is_open: updates.is_open ?? null,
+      is_pinned: updates.is_pinned ?? null,
+    },
+  });
+}
 
-  if (fields.length === 0) return;
+export type SaveNodeError =
+  | { kind: "conflict"; current: NodeRow }
+  | { kind: "failed"; message: string };
 
-  values.push(id);
-  await database.execute(
-    `UPDATE nodes SET ${fields.join(", ")} WHERE id = $${values.length}`,
-    values
-  );
+/** Thrown by `saveNodeContent` when the node changed since `expectedRevision`. */
+export class NodeConflictError extends Error {
+  constructor(public readonly current: NodeRow) {
+    super(`Note "${current.name}" was changed elsewhere.`);
+    this.name = "NodeConflictError";
+  }
 }
 
-export async function updateNodeContent(
+/**
+ * Saves content only if the node is still at `expectedRevision`, so edits
+ * from another window are never silently overwritten.
+ */
+export async function saveNodeContent(
   id: string,
-  content: string,
-  name?: string
-): Promise<void> {
-  const database = await getDb();
-  if (name !== undefined) {
-    await database.execute(
-      "UPDATE nodes SET content = $1, name = $2 WHERE id = $3",
-      [content, name, id]
-    );
-  } else {
-    await database.execute(
-      "UPDATE nodes SET content = $1 WHERE id = $2",
-      [content, id]
-    );
+  expectedRevision: number,
+  content: string
+): Promise<NodeRow> {
+  try {
+    return await invoke<NodeRow>("save_node_content", {
+      nodeId: id,
+      expectedRevision,
+      content,
+    });
+  } catch (error) {
+    const saveError = error as SaveNodeError;
+    if (saveError?.kind === "conflict") {
+      throw new NodeConflictError(saveError.current);
+    }
+    throw new Error(saveError?.kind === "failed" ? saveError.message : String(error));
   }
 }
 
 export async function toggleNodeOpen(id: string, isOpen: boolean): Promise<void> {
-  const database = await getDb();
-  await database.execute(
-    "UPDATE nodes SET is_open = $1 WHERE id = $2",
-    [isOpen ? 1 : 0, id]
-  );
+  await updateNode(id, { is_open: isOpen });
 }
 
 export async function toggleNodePinned(id: string, isPinned: boolean): Promise<void> {
-  const database = await getDb();
-  await database.execute(
-    "UPDATE nodes SET is_pinned = $1 WHERE id = $2",
-    [isPinned ? 1 : 0, id]
-  );
+  await updateNode(id, { is_pinned: isPinned });
 }
 
-export async function getPinnedNodes(spaceId: string): Promise<NodeRow[]> {
-  const database = await getDb();
-  return await database.select<NodeRow[]>(
-    "SELECT * FROM nodes WHERE space_id = $1 AND is_pinned = 1 ORDER BY name",
-    [spaceId]
-  );
+export async function moveNode(
+  id: string,
+  newParentId: string | null,
+  position?: number,
+  newSpaceId?: string
+): Promise<NodeRow> {
+  return await invoke<NodeRow>("move_node", {
+    request: {
+      node_id: id,
+      new_parent_id: newParentId,
+      new_space_id: newSpaceId ?? null,
+      position: position ?? null,
+    },
+  });
+}
+
+export async function trashNode(id: string): Promise<void> {
+  await invoke("trash_node", { nodeId: id });
+}
+
+export async function restoreNode(id: string): Promise<NodeRow> {
+  return await invoke<NodeRow>("restore_node", { nodeId: id });
 }
 
 export async function deleteNode(id: string): Promise<void> {
-  const database = await getDb();
-  await database.execute("DELETE FROM nodes WHERE id = $1", [id]);
+  await invoke("delete_node", { nodeId: id });
+}
+
+export interface DeleteSubtreeResult {
+  node_ids: string[];
+  attachment_ids: string[];
+  removed_files: string[];
+  failed_files: string[];
 }
 
-export async function deleteNodeAndChildren(id: string): Promise<void> {
-  const database = await getDb();
-  // This is a simplified version - you might need a recursive CTE for proper deletion
-  await database.execute("DELETE FROM nodes WHERE id = $1 OR parent_id = $1", [id]);
+export async function deleteNodeAndChildren(id: string): Promise<DeleteSubtreeResult> {
+  return await invoke<DeleteSubtreeResult>("delete_subtree", { nodeId: id });
 }
 
 // Sort nodes alphabetically by name
@@ -217,6 +194,7 @@ export function buildNodeTree(rows: NodeRow[]): Node[] {
       id: row.id,
       name: row.name,
       content: row.content || undefined,
+      revision: row.revision,
       createdAt: row.created_at || undefined,
       updatedAt: row.updated_at || undefined,
       isOpen: Boolean(row.is_open),
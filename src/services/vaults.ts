@@ -0,0 +1,40 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** A folder holding a notes database and its attachments. */
+export interface Vault {
+  id: string;
+  name: string;
+  path: string;
+  last_opened_at: string | null;
+}
+
+export interface VaultList {
+  /** Id of the open vault. */
+  active_id: string;
+  /** The default vault first, then the others, most recently opened first. */
+  vaults: Vault[];
+}
+
+// Matches `DEFAULT_VAULT_ID` in `src-tauri/src/vaults.rs`
+export const DEFAULT_VAULT_ID = 'default';
+
+export async function listVaults(): Promise<VaultList> {
+  return invoke<VaultList>('list_vaults');
+}
+
+/** Opens a folder as a vault; shows a folder picker when no path is given. Null if it was canceled. */
+export async function openVault(path?: string, name?: string): Promise<Vault | null> {
+  return invoke<Vault | null>('open_vault', {
+    request: { path: path ?? null, name: name ?? null },
+  });
+}
+
+/** Switches to a known vault; the backend then emits `vault-changed`. */
+export async function switchVault(vaultId: string): Promise<Vault> {
+  return invoke<Vault>('switch_vault', { vaultId });
+}
+
+/** Removes a vault from the list, leaving its folder untouched. */
+export async function forgetVault(vaultId: string): Promise<VaultList> {
+  return invoke<VaultList>('forget_vault', { vaultId });
+}
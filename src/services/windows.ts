@@ -0,0 +1,6 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** Opens a note in its own window, or focuses the window already showing it. */
+export async function openNoteWindow(nodeId: string): Promise<void> {
+  await invoke('open_note_window', { nodeId });
+}
@@ -21,31 +21,53 @@ export interface WorkspaceSnapshot {
   spaces: WorkspaceSpace[];
 }
 
-interface AppApiError {
+export interface AppApiError {
   message: string;
+  /** Stored copy of a node whose content save was rejected as stale. */
+  conflict?: db.NodeRow;
 }
 
 function toError(error: unknown): AppApiError {
+  if (error instanceof db.NodeConflictError) {
+    return { message: error.message, conflict: error.current };
+  }
+
   if (error instanceof Error) {
     return { message: error.message };
   }
 
+  if (typeof error === 'string') {
+    return { message: error };
+  }
+
   return { message: 'Unknown error' };
 }
 
 function toPinnedNodes(rows: db.NodeRow[]): Node[] {
   return rows
-    .filter((row) => row.is_pinned === 1)
+    .filter((row) => row.is_pinned)
     .map((row) => ({
       id: row.id,
       name: row.name,
       content: row.content ?? undefined,
+      revision: row.revision,
       createdAt: row.created_at ?? undefined,
       updatedAt: row.updated_at ?? undefined,
       isPinned: true,
     }));
 }
 
+function collectNodeIds(nodes: Node[], ids: string[] = []): string[] {
+  for (const node of nodes) {
+    ids.push(node.id);
+    if (node.nodes) {
+      collectNodeIds(node.nodes, ids);
+    }
+  }
+
+  return ids;
+}
+
 function updateNodeInTree(nodes: Node[], id: string, updater: (node: Node) => void): boolean {
   for (const node of nodes) {
     if (node.id === id) {
@@ -87,6 +109,52 @@ function insertNodeInTree(nodes: Node[], parentId: string | null, node: Node): b
   return false;
 }
 
+/** Revision each node's content in this window is based on, by node ID. */
+const baseRevisions = new Map<string, number>();
+
+/** Writes that bump node revisions, chained per node so none runs against a stale revision. */
+const nodeWrites = new Map<string, Promise<unknown>>();
+
+function enqueueNodeWrite<T>(id: string, write: () => Promise<T>): Promise<T> {
+  const next = (nodeWrites.get(id) ?? Promise.resolve()).catch(() => undefined).then(write);
+  nodeWrites.set(id, next);
+
+  const clear = () => {
+    if (nodeWrites.get(id) === next) {
+      nodeWrites.delete(id);
+    }
+  };
+  next.then(clear, clear);
+
+  return next;
+}
+
+/** Resolves once this window's queued writes to a node have settled. */
+export function nodeWritesSettled(id: string): Promise<void> {
+  return (nodeWrites.get(id) ?? Promise.resolve()).then(
+    () => undefined,
+    () => undefined,
+  );
+}
+
+/**
+ * Records the revision of the content an editor loaded for a node, which
+ * its next content save is checked against.
+ */
+export function trackNodeRevision(id: string, revision: number) {
+  baseRevisions.set(id, revision);
+}
+
+/**
+ * Moves a node's base revision past a rename, unless something else
+ * changed the node in between; that change must surface as a conflict.
+ */
+function advancePastRename(row: db.NodeRow) {
+  if (baseRevisions.get(row.id) === row.revision - 1) {
+    baseRevisions.set(row.id, row.revision);
+  }
+}
+
 async function loadWorkspaceSnapshot(): Promise<WorkspaceSnapshot> {
   const spaceRows = await db.getSpaces();
 
@@ -109,7 +177,7 @@ async function loadWorkspaceSnapshot(): Promise<WorkspaceSnapshot> {
 export const appApi = createApi({
   reducerPath: 'appApi',
   baseQuery: fakeBaseQuery<AppApiError>(),
-  tagTypes: ['Workspace', 'Layout', 'Keybindings'],
+  tagTypes: ['Workspace', 'Space', 'Node', 'Attachment', 'Layout', 'Keybindings'],
   endpoints: (builder) => ({
     getWorkspace: builder.query<WorkspaceSnapshot, void>({
       queryFn: async () => {
@@ -120,20 +188,23 @@ export const appApi = createApi({
           return { error: toError(error) };
         }
       },
-      providesTags: [{ type: 'Workspace', id: 'ROOT' }],
+      // Per-space and per-node tags let change events from other windows
+      // invalidate the snapshot only when it holds what changed.
+      providesTags: (result) => [
+        { type: 'Workspace', id: 'ROOT' },
+        { type: 'Space', id: 'LIST' },
+        ...(result?.spaces ?? []).flatMap((space) => [
+          { type: 'Space' as const, id: space.id },
+          ...collectNodeIds(space.fileTree).map((id) => ({ type: 'Node' as const, id })),
+        ]),
+      ],
     }),
 
     createSpace: builder.mutation<{ id: string }, { id: string; name?: string }>({
       queryFn: async ({ id, name }) => {
         try {
           const normalizedName = name?.trim() ? name.trim() : 'new Space';
-          const existingSpaces = await db.getSpaces();
-
-          await db.createSpace({
-            id,
-            name: normalizedName,
-            sort_order: existingSpaces.length,
-          });
+          await db.createSpace({ id, name: normalizedName });
 
           return { data: { id } };
         } catch (error) {
@@ -197,14 +268,15 @@ export const appApi = createApi({
     >({
       queryFn: async ({ id, spaceId, parentId }) => {
         try {
-          await db.createNode({
+          const row = await db.createNode({
             id,
             space_id: spaceId,
             parent_id: parentId,
             name: '',
             content: '',
-            is_open: 0,
+            is_open: false,
           });
+          trackNodeRevision(row.id, row.revision);
 
           return { data: { id } };
         } catch (error) {
@@ -223,6 +295,7 @@ export const appApi = createApi({
               id,
               name: '',
               content: '',
+              revision: 0,
               isOpen: false,
               isPinned: false,
               nodes: [],
@@ -238,10 +311,35 @@ export const appApi = createApi({
       },
     }),
 
-    updateNodeContent: builder.mutation<void, { id: string; content: string }>({
-      queryFn: async ({ id, content }) => {
+    /**
+     * Saves content through `save_node_content`, checked against the
+     * revision this window last loaded or saved. `revision` is used when
+     * the window has neither, e.g. for a node it just created. A stale
+     * save fails with `conflict` set instead of overwriting the node.
+     */
+    updateNodeContent: builder.mutation<
+      void,
+      { id: string; content: string; revision?: number }
+    >({
+      queryFn: async ({ id, content, revision }, { dispatch }) => {
         try {
-          await db.updateNodeContent(id, content);
+          const row = await enqueueNodeWrite(id, async () => {
+            const expected = baseRevisions.get(id) ?? revision ?? 0;
+            const saved = await db.saveNodeContent(id, expected, content);
+            baseRevisions.set(id, saved.revision);
+            return saved;
+          });
+
+          dispatch(
+            appApi.util.updateQueryData('getWorkspace', undefined, (draft) => {
+              draft.spaces.forEach((space) => {
+                updateNodeInTree(space.fileTree, id, (node) => {
+                  node.revision = row.revision;
+                });
+              });
+            }),
+          );
+
           return { data: undefined };
         } catch (error) {
           return { error: toError(error) };
@@ -276,9 +374,24 @@ export const appApi = createApi({
     }),
 
     renameNode: builder.mutation<void, { id: string; name: string }>({
-      queryFn: async ({ id, name }) => {
+      queryFn: async ({ id, name }, { dispatch }) => {
         try {
-          await db.updateNode(id, { name: name.trim() });
+          const row = await enqueueNodeWrite(id, async () => {
+            const renamed = await db.updateNode(id, { name: name.trim() });
+            advancePastRename(renamed);
+            return renamed;
+          });
+
+          dispatch(
+            appApi.util.updateQueryData('getWorkspace', undefined, (draft) => {
+              draft.spaces.forEach((space) => {
+                updateNodeInTree(space.fileTree, id, (node) => {
+                  node.revision = row.revision;
+                });
+              });
+            }),
+          );
+
           return { data: undefined };
         } catch (error) {
           return { error: toError(error) };
@@ -353,7 +466,7 @@ export const appApi = createApi({
     deleteNode: builder.mutation<void, { id: string }>({
       queryFn: async ({ id }) => {
         try {
-          await db.deleteNode(id);
+          await db.trashNode(id);
           return { data: undefined };
         } catch (error) {
           return { error: toError(error) };
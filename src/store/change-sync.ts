@@ -0,0 +1,85 @@
+import type { UnlistenFn } from '@tauri-apps/api/event';
+
+import type { AppStore } from '@/store';
+import { appApi, nodeWritesSettled } from '@/store/api/app-api';
+import { findNodeById, selectWorkspaceQuery } from '@/store/selectors';
+import {
+  onAttachmentChanged,
+  onNodeChanged,
+  onSpaceChanged,
+  type NodeChanged,
+} from '@/services/change-events';
+
+type InvalidatedTags = Parameters<typeof appApi.util.invalidateTags>[0];
+
+function findCachedRevision(store: AppStore, id: string): number | undefined {
+  const workspace = selectWorkspaceQuery(store.getState()).data;
+  for (const space of workspace?.spaces ?? []) {
+    const node = findNodeById(space.fileTree, id);
+    if (node) {
+      return node.revision;
+    }
+  }
+
+  return undefined;
+}
+
+async function invalidateChangedNodes(store: AppStore, change: NodeChanged) {
+  const tags: InvalidatedTags = [];
+  let spacesChanged = false;
+
+  for (const node of change.nodes) {
+    // This window's own saves are announced too; once they settle the
+    // cache already holds their revision.
+    await nodeWritesSettled(node.id);
+    const cachedRevision = findCachedRevision(store, node.id);
+    if (
+      node.revision !== null &&
+      cachedRevision !== undefined &&
+      cachedRevision >= node.revision
+    ) {
+      continue;
+    }
+
+    tags.push({ type: 'Node', id: node.id });
+    // Nodes the snapshot doesn't hold yet, or no longer should, are only
+    // reachable through their space.
+    spacesChanged ||= change.kind !== 'updated' || cachedRevision === undefined;
+  }
+
+  if (spacesChanged) {
+    tags.push(...change.space_ids.map((id) => ({ type: 'Space' as const, id })));
+  }
+  if (tags.length > 0) {
+    store.dispatch(appApi.util.invalidateTags(tags));
+  }
+}
+
+/**
+ * Invalidates cached data when the backend announces a change, whichever
+ * window or background task made it. Subscribe once, at startup.
+ */
+export async function syncChangeEvents(store: AppStore): Promise<UnlistenFn> {
+  const unlisteners = await Promise.all([
+    onNodeChanged((change) => {
+      void invalidateChangedNodes(store, change);
+    }),
+    onSpaceChanged((change) => {
+      store.dispatch(
+        appApi.util.invalidateTags([
+          { type: 'Space', id: change.space_id },
+          { type: 'Space', id: 'LIST' },
+        ]),
+      );
+    }),
+    onAttachmentChanged((change) => {
+      store.dispatch(
+        appApi.util.invalidateTags(
+          change.attachment_ids.map((id) => ({ type: 'Attachment' as const, id })),
+        ),
+      );
+    }),
+  ]);
+
+  return () => unlisteners.forEach((unlisten) => unlisten());
+}
@@ -157,9 +157,10 @@ export function useFileSystemStore(): FileSystemState {
 
   const updateNodeContent = useCallback(
     async (id: string, content: string) => {
-      await updateNodeContentMutation({ id, content }).unwrap();
+      const revision = findNodeById(fileTree, id)?.revision;
+      await updateNodeContentMutation({ id, content, revision }).unwrap();
     },
-    [updateNodeContentMutation],
+    [fileTree, updateNodeContentMutation],
   );
 
   const renameNode = useCallback(
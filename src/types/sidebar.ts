@@ -14,6 +14,8 @@ export interface Node {
   id: string;
   name: string;
   content?: string;
+  /** Increases with every name or content change; saves are checked against it. */
+  revision?: number;
   createdAt?: string;
   updatedAt?: string;
   parentId?: string;
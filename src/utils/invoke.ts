@@ -1,5 +1,5 @@
 import { Layout } from "@/types/layout";
-import { invoke as tauriInvoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke as tauriInvoke } from '@tauri-apps/api/core';
 
 export interface UploadImageRequest {
     noteId: string;
@@ -13,7 +13,24 @@ export interface UploadImageRequest {
 export interface UploadImageResponse {
     id: string;
     path: string;
+    /** Size of the stored file. */
     size: number;
+    /** Size of the uploaded file; larger than `size` when the upload policy re-encoded it. */
+    original_size: number;
+    /** Attachment holding the untouched upload, when the policy keeps originals. */
+    original_id: string | null;
+}
+
+export type ImageVariantSize = 'thumbnail' | 'display' | 'original';
+
+export interface ImageVariant {
+    attachment_id: string;
+    /** Size actually served; `original` when the image needs no smaller copy. */
+    size: ImageVariantSize;
+    file_path: string;
+    width: number | null;
+    height: number | null;
+    mime_type: string;
 }
 
 export interface SaveImageRequest {
@@ -39,6 +56,10 @@ type InvokeMap = {
         args: { attachmentId: string };
         returnType: string;
     };
+    get_image_variant: {
+        args: { attachmentId: string; size: ImageVariantSize };
+        returnType: ImageVariant;
+    };
     delete_image: {
         args: { attachmentId: string };
         returnType: void;
@@ -87,6 +108,24 @@ export const uploadImage = (request: UploadImageRequest) =>
 export const getImage = (attachmentId: string) => 
     tauriInvoke<string>('get_image', { attachmentId });
 
+export const getImageVariant = (attachmentId: string, size: ImageVariantSize) =>
+    tauriInvoke<ImageVariant>('get_image_variant', { attachmentId, size });
+
+/** Asset URL of an image attachment at `size`; `fallbackSrc` when the variant can't be resolved. */
+export const getImageVariantSrc = async (
+    attachmentId: string,
+    size: ImageVariantSize,
+    fallbackSrc: string,
+) => {
+    try {
+        const variant = await getImageVariant(attachmentId, size);
+        return convertFileSrc(variant.file_path);
+    } catch (error) {
+        console.error('Failed to resolve image variant:', error);
+        return fallbackSrc;
+    }
+};
+
 export const deleteImage = (attachmentId: string) => 
     tauriInvoke('delete_image', { attachmentId });
 
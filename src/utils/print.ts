@@ -15,6 +15,7 @@ import { HeadingExtension } from '@/components/editor/extensions/heading';
 import { TiptapImage } from '@/components/editor/extensions/image';
 import { starterKit } from '@/components/editor/extensions/starterkit';
 import { editorClasses } from '@/components/editor/styles/extension-styles';
+import { getImageVariantSrc } from '@/utils/invoke';
 
 export type PrintScope = 'current' | 'branch' | 'space';
 
@@ -242,6 +243,24 @@ function buildNodeHtml(node: PrintableNode): string {
   `;
 }
 
+/**
+ * Point attachment images in `container` at their display-width variant,
+ * which covers the page width at the capture scale, and wait for them to load.
+ */
+async function loadDisplayVariants(container: HTMLElement) {
+  const images = Array.from(container.querySelectorAll<HTMLImageElement>('img[data-attachment-id]'));
+
+  await Promise.all(
+    images.map(async (img) => {
+      const attachmentId = img.dataset.attachmentId;
+      if (!attachmentId) return;
+
+      img.src = await getImageVariantSrc(attachmentId, 'display', img.src);
+      try { await img.decode(); } catch { /* captured as is */ }
+    }),
+  );
+}
+
 /**
  * Export one or more nodes to a PDF file.
  *
@@ -302,6 +321,7 @@ export async function exportToPdf(
   try {
     for (const node of nodes) {
       container.innerHTML = buildNodeHtml(node);
+      await loadDisplayVariants(container);
 
       // Allow the browser to lay out & paint the new content
       await new Promise<void>((r) =>